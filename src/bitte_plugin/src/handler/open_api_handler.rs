@@ -127,7 +127,7 @@ pub fn open_api_specification() -> Json<Value> {
                       "type": "string"
                     },
                     "example": "@ryan_soury",
-                    "description": "The X (Twitter) account handle to notify when the reward/post is complete"
+                    "description": "Who to notify when the reward/post is complete: an X (Twitter) account handle, or `tg:<chat id>` for Telegram, `mailto:<address>` for email, or an `https:` webhook URL"
                   },
                   {
                     "in": "query",
@@ -138,6 +138,16 @@ pub fn open_api_specification() -> Json<Value> {
                     },
                     "example": "680000000000000000000",
                     "description": "The required deposit amount for minting the tweet"
+                  },
+                  {
+                    "in": "query",
+                    "name": "minter",
+                    "required": false,
+                    "schema": {
+                      "type": "string"
+                    },
+                    "example": "alice.near",
+                    "description": "The NEAR account ID of the user signing the transaction. When given, the deposit is a signed quote of the price, which the user is charged at most even if the post gains engagement before it is minted. Required when quotes are signed"
                   }
                 ],
                 "responses": {
@@ -1,5 +1,11 @@
-use std::{env, ops::Index, str::FromStr};
+use std::{
+    env,
+    ops::Index,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
+use ethers::signers::{LocalWallet, Signer};
 use indexer::helper::TweetResponse;
 use near_client::{
     client::NearClient,
@@ -13,7 +19,14 @@ use rocket::serde::json::{json, Json, Value};
 use tracing::debug;
 use url::Url;
 
-use crate::{handler::IpfsData, helper, models::response::NetworkResponse};
+use crate::{
+    handler::{IpfsData, PublicMetric},
+    helper,
+    models::response::NetworkResponse,
+};
+
+/// How long a signed quote is honoured for, unless `QUOTE_TTL_SECONDS` is set.
+const DEFAULT_QUOTE_TTL_SECONDS: u64 = 10 * 60;
 
 /// Handles the request to mint a new tweet.
 ///
@@ -57,7 +70,7 @@ pub async fn mint_tweet_request(tweet_id: Option<String>) -> NetworkResponse {
         }));
     }
 
-    let (description, public_metric) = result.unwrap();
+    let (description, public_metric, _) = result.unwrap();
 
     let computed_cost = near_client
         .view::<u128>(
@@ -155,10 +168,11 @@ pub async fn mint_tweet_request(tweet_id: Option<String>) -> NetworkResponse {
 ///
 /// # Returns
 ///
-/// A `Result` containing the tweet text as a `String`, or an error if the request fails.
+/// A `Result` containing the tweet text, its public metrics and the X id of its author, or an
+/// error if the request fails.
 async fn get_tweet_content(
     tweet_id: &str,
-) -> Result<(String, Value), Box<dyn std::error::Error + Sync + Send>> {
+) -> Result<(String, Value, String), Box<dyn std::error::Error + Sync + Send>> {
     // Create a new HTTP client
     let client = Client::new();
 
@@ -190,6 +204,7 @@ async fn get_tweet_content(
     Ok((
         tweet_data.index(0).text.clone(),
         serde_json::to_value(tweet_data.index(0).public_metrics.clone()).unwrap(),
+        tweet_data.index(0).author_id.clone(),
     ))
 }
 
@@ -240,7 +255,7 @@ async fn get_computed_cost(
     )
     .unwrap();
 
-    let (description, public_metric) = result.unwrap();
+    let (description, public_metric, _) = result.unwrap();
 
     let computed_cost = near_client
         .view::<u128>(
@@ -263,19 +278,123 @@ async fn get_computed_cost(
     Ok((description, (computed_cost * 12 / 10).to_string()))
 }
 
-#[get("/tweet-contract-call?<tweet_id>&<image_url>&<notify>&<computed_cost>")]
+/// Computes the cost of minting a tweet for `minter`, and signs a quote of it with
+/// `QUOTE_SIGNER_KEY`. The contract charges at most the quoted price until the quote expires,
+/// so no margin is added. The quote also carries the author of the tweet, whose consent the
+/// contract checks before locking the tweet.
+///
+/// # Returns
+///
+/// The signed quote, passed as is to `mint_tweet_request`, and the quoted price. `None` when no
+/// quote signer is configured.
+async fn sign_quote(
+    tweet_id: &str,
+    minter: &str,
+) -> Result<Option<(Value, String)>, Box<dyn std::error::Error + Sync + Send>> {
+    let signer_key = match env::var("QUOTE_SIGNER_KEY") {
+        Ok(signer_key) => signer_key,
+        Err(_) => return Ok(None),
+    };
+    let wallet: LocalWallet = signer_key.trim_start_matches("0x").parse()?;
+    let contract_id = env::var("NEAR_CONTRACT_ADDRESS").expect("NEAR CONTRACT ADDRESS is missing");
+
+    let (_, public_metric, author_id) = get_tweet_content(tweet_id).await?;
+    let near_client = NearClient::new(
+        Url::from_str(&env::var("NEAR_RPC").expect("NEAR RPC is missing")).unwrap(),
+    )?;
+    let price = near_client
+        .view::<u128>(
+            &AccountId::from_str(&contract_id)?,
+            Finality::Final,
+            "compute_cost",
+            Some(json!({
+                "public_metrics": public_metric
+            })),
+        )
+        .await?
+        .data();
+
+    let ttl_seconds = env::var("QUOTE_TTL_SECONDS")
+        .ok()
+        .and_then(|ttl| ttl.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_QUOTE_TTL_SECONDS);
+    let expires_at_ms =
+        (SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() + ttl_seconds) * 1000;
+
+    // Must match `MintQuote::message` in the NFT contract
+    let metric: PublicMetric = serde_json::from_value(public_metric.clone())?;
+    let message = format!(
+        "x-twitter-nfts mint quote\ncontract: {}\ntweet_id: {}\nauthor_id: {}\nminter: {}\nmetrics: {},{},{},{},{},{}\nprice: {}\nexpires_at_ms: {}",
+        contract_id,
+        tweet_id,
+        author_id,
+        minter,
+        metric.bookmark_count,
+        metric.impression_count,
+        metric.like_count,
+        metric.quote_count,
+        metric.reply_count,
+        metric.retweet_count,
+        price,
+        expires_at_ms,
+    );
+    let signature = wallet.sign_message(message).await?;
+
+    Ok(Some((
+        json!({
+            "quote": {
+                "tweet_id": tweet_id,
+                "author_id": author_id,
+                "minter": minter,
+                "public_metric": public_metric,
+                "price": price.to_string(),
+                "expires_at_ms": expires_at_ms,
+            },
+            "signature": format!("0x{}", signature),
+        }),
+        price.to_string(),
+    )))
+}
+
+#[get("/tweet-contract-call?<tweet_id>&<image_url>&<notify>&<computed_cost>&<minter>")]
 pub async fn tweet_contract_call(
     tweet_id: String,
     image_url: String,
     computed_cost: Option<String>,
     notify: Option<String>,
+    minter: Option<String>,
 ) -> NetworkResponse {
     // Get the NEAR contract address from environment variable
     let contract_id = env::var("NEAR_CONTRACT_ADDRESS")
         .expect("NEAR_CONTRACT_ADDRESS must be set")
         .to_owned();
 
-    let computed_cost = if computed_cost.is_some() {
+    // With a signed quote, the user deposits the quoted price and is charged at most that
+    let quote = match &minter {
+        Some(minter) => match sign_quote(&tweet_id, minter).await {
+            Ok(quote) => quote,
+            Err(err) => {
+                return NetworkResponse::BadRequest(json!({
+                    "error": format!("failed to quote the tweet : {}", err)
+                }))
+            }
+        },
+        // The contract requires a quote once it has a quote signer
+        None if env::var("QUOTE_SIGNER_KEY").is_ok() => {
+            return NetworkResponse::BadRequest(json!({
+                "error": "minter is required to quote the tweet"
+            }))
+        }
+        None => None,
+    };
+    let (quote, quoted_cost) = match quote {
+        Some((quote, price)) => (Some(quote), Some(price)),
+        None => (None, None),
+    };
+
+    let computed_cost = if let Some(quoted_cost) = quoted_cost {
+        quoted_cost
+    } else if computed_cost.is_some() {
         let computed = computed_cost.unwrap();
         // if computed cost was auto filled by AI and too little replace
         if computed.len() < 7 {
@@ -299,6 +418,17 @@ pub async fn tweet_contract_call(
         result.unwrap().1
     };
 
+    // The contract checks the consent of the author, and that they are the author of the
+    // proven tweet when minted
+    let author_id = match get_tweet_content(&tweet_id).await {
+        Ok((_, _, author_id)) => author_id,
+        Err(err) => {
+            return NetworkResponse::BadRequest(json!({
+                "error": format!("failed to retrieve tweet author : {}", err)
+            }))
+        }
+    };
+
     // Default value for notify if not provided
     let notify = notify.unwrap_or(String::from(""));
 
@@ -312,8 +442,10 @@ pub async fn tweet_contract_call(
                 "methodName": "mint_tweet_request",
                 "args": {
                     "tweet_id": tweet_id,
+                    "author_id": author_id,
                     "image_url": cleanup_image_link(&image_url),
                     "notify": notify,
+                    "quote": quote,
                 },
                 "deposit":computed_cost,
                 "gas": "100000000000000",
@@ -0,0 +1,202 @@
+//! Upgrades the deployed version of the contract to the current one in a sandbox, and checks
+//! that requests, tokens, royalties, admin authority and prices survive `migrate`.
+//!
+//! The deployed wasm is fetched with `scripts/download_deployed_wasm.sh`, and the current one
+//! built with `scripts/build.sh`.
+use anyhow::Context;
+use near_workspaces::types::NearToken;
+use serde_json::{json, Value};
+
+const DEPLOYED_WASM: &str = "./wasm/non_fungible_token_deployed.wasm";
+const CURRENT_WASM: &str = "../../target/non_fungible_token.wasm";
+
+const AUTHOR_ID: &str = "1234";
+
+fn token_metadata(tweet_id: &str) -> Value {
+    json!({
+        "title": tweet_id,
+        "description": "A tweet",
+        "extra": json!({
+            "minted_to": "minter",
+            "public_metric": {
+                "bookmark_count": 0,
+                "impression_count": 0,
+                "like_count": 1,
+                "quote_count": 0,
+                "reply_count": 0,
+                "retweet_count": 0
+            },
+            "author_id": AUTHOR_ID
+        })
+        .to_string(),
+    })
+}
+
+#[tokio::test]
+async fn test_migrate_preserves_state() -> anyhow::Result<()> {
+    let deployed_wasm = std::fs::read(DEPLOYED_WASM)
+        .context("run scripts/download_deployed_wasm.sh to fetch the deployed contract")?;
+    let current_wasm =
+        std::fs::read(CURRENT_WASM).context("run scripts/build.sh to build the contract")?;
+
+    let worker = near_workspaces::sandbox().await?;
+    let contract = worker.dev_deploy(&deployed_wasm).await?;
+    // Stands in for the verifier proxy, the only account allowed to mint
+    let verifier = worker.dev_create_account().await?;
+    let royalty_manager = worker.dev_create_account().await?;
+    let minter = worker.dev_create_account().await?;
+
+    contract
+        .call("new_default_meta")
+        .args_json(json!({
+            "owner_id": verifier.id(),
+            "royalty_manager": royalty_manager.id(),
+        }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    // One request is minted, crediting the author, and the other is left pending
+    for tweet_id in ["1", "2"] {
+        minter
+            .call(contract.id(), "mint_tweet_request")
+            .args_json(json!({ "tweet_id": tweet_id, "image_url": "ipfs://", "notify": "" }))
+            .deposit(NearToken::from_near(1))
+            .max_gas()
+            .transact()
+            .await?
+            .into_result()?;
+    }
+    verifier
+        .call(contract.id(), "nft_mint")
+        .args_json(json!({
+            "token_id": "1",
+            "receiver_id": minter.id(),
+            "token_metadata": token_metadata("1"),
+        }))
+        .deposit(NearToken::from_millinear(100))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Upgrade
+    let contract = contract
+        .as_account()
+        .deploy(&current_wasm)
+        .await?
+        .into_result()?;
+    contract
+        .call("migrate")
+        .args_json(json!({}))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    let version: u8 = contract.view("get_state_version").await?.json()?;
+    assert_eq!(version, 9);
+
+    // The prices become the first pricing version
+    let pricing_version: u32 = contract.view("get_pricing_version").await?.json()?;
+    assert_eq!(pricing_version, 1);
+
+    // The accounts that administered the contract before roles existed hold them now
+    let is_minter: bool = contract
+        .view("has_role")
+        .args_json(json!({ "role": "Minter", "account_id": verifier.id() }))
+        .await?
+        .json()?;
+    assert!(is_minter);
+    let owners: Vec<String> = contract
+        .view("get_role_members")
+        .args_json(json!({ "role": "Owner" }))
+        .await?
+        .json()?;
+    assert_eq!(owners, vec![royalty_manager.id().to_string()]);
+
+    let minted: Value = contract
+        .view("get_request")
+        .args_json(json!({ "tweet_id": "1" }))
+        .await?
+        .json()?;
+    assert_eq!(minted["status"], "IsFulfilled");
+    let pending: Value = contract
+        .view("get_request")
+        .args_json(json!({ "tweet_id": "2" }))
+        .await?
+        .json()?;
+    assert_eq!(pending["status"], "Created");
+    assert_eq!(pending["minter"], minter.id().to_string());
+
+    let token: Value = contract
+        .view("nft_token")
+        .args_json(json!({ "token_id": "1" }))
+        .await?
+        .json()?;
+    assert_eq!(token["owner_id"], minter.id().to_string());
+
+    let royalty: u128 = contract
+        .view("get_royalty_balance")
+        .args_json(json!({ "author_id": AUTHOR_ID }))
+        .await?
+        .json()?;
+    assert!(royalty > 0, "royalty balance lost in the migration");
+
+    // Requests made before the upgrade are indexed afterwards
+    let indexed: u32 = contract
+        .call("index_requests")
+        .args_json(json!({ "tweet_ids": ["1", "2"] }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?
+        .json()?;
+    assert_eq!(indexed, 2);
+
+    // The ledger is opened with the balances held before it existed, read from the state
+    let opened: u32 = contract
+        .call("open_royalty_balances")
+        .args_json(json!({ "author_ids": [AUTHOR_ID] }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?
+        .json()?;
+    assert_eq!(opened, 1);
+    let ledger: Value = contract.view("get_ledger").await?.json()?;
+    assert_eq!(
+        ledger["escrow"],
+        json!(NearToken::from_near(1).as_yoctonear())
+    );
+    assert_eq!(ledger["author_royalties"], json!(royalty));
+
+    // The image of pending requests is recorded from their events
+    let recorded: u32 = contract
+        .call("record_request_images")
+        .args_json(json!({ "images": [["1", "ipfs://"], ["2", "ipfs://"]] }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?
+        .json()?;
+    assert_eq!(recorded, 1);
+
+    // Migrating again leaves the state, and its indexes, as they are
+    contract
+        .call("migrate")
+        .args_json(json!({}))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    let pending: Vec<Value> = contract
+        .view("get_pending_requests")
+        .args_json(json!({}))
+        .await?
+        .json()?;
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0]["tweet_id"], "2");
+
+    Ok(())
+}
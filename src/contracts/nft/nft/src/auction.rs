@@ -0,0 +1,93 @@
+/*!
+Auctions for the mint of contested tweets, as an alternative to the first-come lock of
+`mint_tweet_request`.
+
+The first bid opens a bidding window. Until it closes, other accounts can outbid the highest
+bid by at least the minimum increment, and the outbid deposit is refunded. Once the window has
+closed, the auction is settled into a mint request of the highest bidder, which the orchestrator
+picks up like any other.
+*/
+use crate::BPS_DENOMINATOR;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::{AccountId, Balance};
+use serde::{Deserialize, Serialize};
+
+/// How long auctions are open for, and by how much a bid must beat the highest one.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AuctionSettings {
+    /// The length of the bidding window opened by the first bid, in ms.
+    pub window: u64,
+    /// How much higher than the highest bid a bid must be, in basis points.
+    pub min_bid_increment_bps: u16,
+}
+
+impl Default for AuctionSettings {
+    fn default() -> Self {
+        Self {
+            window: 10 * 60 * 1000,
+            min_bid_increment_bps: 500,
+        }
+    }
+}
+
+/// An open auction, held by its highest bid.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TweetAuction {
+    pub bidder: AccountId,
+    pub deposit: Balance,
+    pub image_url: String,
+    pub notify: String,
+    /// When the bidding window closes, in ms.
+    pub ends_at: u64,
+    pub bid_count: u32,
+}
+
+/// An auction along with the tweet it is for, as returned by `get_auctions`.
+#[derive(Serialize, Deserialize)]
+pub struct TweetAuctionView {
+    pub tweet_id: String,
+    #[serde(flatten)]
+    pub auction: TweetAuction,
+}
+
+impl TweetAuction {
+    /// The lowest deposit that outbids the highest bid.
+    pub fn min_next_bid(&self, min_bid_increment_bps: u16) -> Balance {
+        let increment = self.deposit * u128::from(min_bid_increment_bps) / BPS_DENOMINATOR;
+        // A bid must be strictly higher, even without an increment
+        self.deposit + increment.max(1)
+    }
+
+    pub fn is_open(&self, now_ms: u64) -> bool {
+        now_ms < self.ends_at
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auction(deposit: Balance) -> TweetAuction {
+        TweetAuction {
+            bidder: "bidder.near".parse().unwrap(),
+            deposit,
+            image_url: "ipfs://".to_string(),
+            notify: "".to_string(),
+            ends_at: 1000,
+            bid_count: 1,
+        }
+    }
+
+    #[test]
+    fn test_min_next_bid() {
+        assert_eq!(auction(10_000).min_next_bid(500), 10_500);
+        assert_eq!(auction(10_000).min_next_bid(0), 10_001);
+        assert_eq!(auction(10).min_next_bid(500), 11);
+    }
+
+    #[test]
+    fn test_is_open() {
+        assert!(auction(1).is_open(999));
+        assert!(!auction(1).is_open(1000));
+    }
+}
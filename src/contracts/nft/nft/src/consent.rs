@@ -0,0 +1,91 @@
+/*!
+Consent of X authors to the mint of their tweets.
+
+An author who linked their account with `link_author` can opt out, so their tweets cannot be
+minted, or reserve a window after each tweet is posted during which only they can request its
+mint. Requests declare the author of the tweet, which is checked against the proven tweet when
+it is minted.
+*/
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use serde::{Deserialize, Serialize};
+
+/// The epoch of the timestamps in tweet ids, in ms.
+const TWITTER_EPOCH_MS: u64 = 1_288_834_974_657;
+
+/// The longest window an author can reserve the first mint of their tweets for, in ms.
+pub const MAX_RESERVATION_WINDOW: u64 = 30 * 24 * 60 * 60 * 1000;
+
+/// What an author allows to be done with their tweets.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AuthorConsent {
+    /// When set, none of the author's tweets can be minted.
+    pub opted_out: bool,
+    /// How long after a tweet is posted only the author can request its mint, in ms.
+    pub reservation_window: u64,
+}
+
+impl AuthorConsent {
+    /// When the first mint of a tweet stops being reserved for the author, in ms.
+    pub fn reserved_until(&self, tweet_id: &str) -> Option<u64> {
+        if self.reservation_window == 0 {
+            return None;
+        }
+        Some(tweet_posted_at(tweet_id)? + self.reservation_window)
+    }
+
+    /// Checks that a tweet can be minted for a request made at `requested_at` ms, by the
+    /// author's account when `by_author` is set.
+    pub fn check(&self, tweet_id: &str, by_author: bool, requested_at: u64) -> Result<(), String> {
+        if self.opted_out {
+            return Err("The author of this tweet has opted out of minting".to_string());
+        }
+        match self.reserved_until(tweet_id) {
+            Some(reserved_until) if !by_author && requested_at < reserved_until => Err(format!(
+                "The author of this tweet has reserved its mint until {}",
+                reserved_until
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// When a tweet was posted in ms, read from the timestamp in the upper bits of its id.
+pub fn tweet_posted_at(tweet_id: &str) -> Option<u64> {
+    let id = tweet_id.parse::<u64>().ok()?;
+    Some((id >> 22) + TWITTER_EPOCH_MS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tweet_posted_at() {
+        // https://x.com/x/status/1858184885493485672, posted on 2024-11-17 at 16:26:14 UTC
+        assert_eq!(
+            tweet_posted_at("1858184885493485672"),
+            Some(1_731_860_774_764)
+        );
+        assert_eq!(tweet_posted_at("tweet"), None);
+    }
+
+    #[test]
+    fn test_check() {
+        let tweet_id = "1858184885493485672";
+        let posted_at = tweet_posted_at(tweet_id).unwrap();
+        let consent = AuthorConsent {
+            opted_out: false,
+            reservation_window: 1000,
+        };
+        assert_eq!(consent.reserved_until(tweet_id), Some(posted_at + 1000));
+        assert!(consent.check(tweet_id, true, posted_at).is_ok());
+        assert!(consent.check(tweet_id, false, posted_at + 999).is_err());
+        assert!(consent.check(tweet_id, false, posted_at + 1000).is_ok());
+
+        let consent = AuthorConsent {
+            opted_out: true,
+            reservation_window: 0,
+        };
+        assert!(consent.check(tweet_id, true, posted_at).is_err());
+    }
+}
@@ -1,3 +1,4 @@
+use crate::Role;
 use near_contract_tools::event;
 use near_sdk::{AccountId, Balance};
 
@@ -24,3 +25,134 @@ pub struct CancelMintRequest {
     pub account: AccountId,
     pub withdraw: Balance,
 }
+
+/// `AuthorLinked` is emitted when an X author proves ownership of a NEAR account.
+///
+/// Properties:
+///
+/// * `author_id`: The X id of the author.
+/// * `account`: The account the royalties of the author can be claimed by.
+#[event(standard = "custom", version = "1.0.0")]
+pub struct AuthorLinked {
+    pub author_id: String,
+    pub account: AccountId,
+}
+
+/// `AuthorConsentSet` is emitted when an author changes what can be done with their tweets.
+///
+/// Properties:
+///
+/// * `author_id`: The X id of the author.
+/// * `opted_out`: Whether the tweets of the author can no longer be minted.
+/// * `reservation_window`: How long after a tweet is posted only the author can mint it, in ms.
+#[event(standard = "custom", version = "1.0.0")]
+pub struct AuthorConsentSet {
+    pub author_id: String,
+    pub opted_out: bool,
+    pub reservation_window: u64,
+}
+
+/// `RoyaltyClaimed` is emitted when an author withdraws their royalties.
+#[event(standard = "custom", version = "1.0.0")]
+pub struct RoyaltyClaimed {
+    pub author_id: String,
+    pub account: AccountId,
+    pub amount: Balance,
+}
+
+/// `RoleGranted` is emitted when an owner grants a role to an account.
+#[event(standard = "custom", version = "1.0.0")]
+pub struct RoleGranted {
+    pub role: Role,
+    pub account: AccountId,
+}
+
+/// `RoleRevoked` is emitted when an owner revokes a role from an account.
+#[event(standard = "custom", version = "1.0.0")]
+pub struct RoleRevoked {
+    pub role: Role,
+    pub account: AccountId,
+}
+
+/// `ContractPaused` is emitted when a pauser stops minting.
+#[event(standard = "custom", version = "1.0.0")]
+pub struct ContractPaused {
+    pub account: AccountId,
+}
+
+/// `ContractUnpaused` is emitted when a pauser resumes minting.
+#[event(standard = "custom", version = "1.0.0")]
+pub struct ContractUnpaused {
+    pub account: AccountId,
+}
+
+/// `PricingModelSet` is emitted when a pricing admin sets a new pricing model.
+///
+/// Properties:
+///
+/// * `version`: The pricing version of the model, recorded by the mints priced with it.
+#[event(standard = "custom", version = "1.0.0")]
+pub struct PricingModelSet {
+    pub version: u32,
+}
+
+/// `QuoteSignerSet` is emitted when an owner sets the address quotes are signed with.
+///
+/// Properties:
+///
+/// * `address`: The Ethereum address of the signer, `None` when quotes are no longer accepted.
+#[event(standard = "custom", version = "1.0.0")]
+pub struct QuoteSignerSet {
+    pub address: Option<String>,
+}
+
+/// `AuctionOpened` is emitted when the first bid for a tweet opens an auction.
+///
+/// Properties:
+///
+/// * `tweet_id`: The ID of the tweet being auctioned.
+/// * `account`: The account that placed the first bid.
+/// * `deposit`: The first bid.
+/// * `ends_at`: When the bidding window closes, in ms.
+#[event(standard = "custom", version = "1.0.0")]
+pub struct AuctionOpened {
+    pub tweet_id: String,
+    pub account: AccountId,
+    pub deposit: Balance,
+    pub ends_at: u64,
+}
+
+/// `AuctionBid` is emitted when a bid outbids the highest one, whose deposit is refunded.
+///
+/// Properties:
+///
+/// * `tweet_id`: The ID of the tweet being auctioned.
+/// * `account`: The account that placed the bid.
+/// * `deposit`: The bid.
+/// * `outbid`: The account that held the highest bid until now.
+/// * `refund`: The deposit refunded to `outbid`.
+#[event(standard = "custom", version = "1.0.0")]
+pub struct AuctionBid {
+    pub tweet_id: String,
+    pub account: AccountId,
+    pub deposit: Balance,
+    pub outbid: AccountId,
+    pub refund: Balance,
+}
+
+/// `AuctionSettled` is emitted when a closed auction becomes a mint request of the highest
+/// bidder, along with a `TweetMintRequest` event for it.
+///
+/// Properties:
+///
+/// * `tweet_id`: The ID of the tweet that was auctioned.
+/// * `account`: The winning account.
+/// * `deposit`: The winning bid.
+/// * `bid_count`: The number of bids placed.
+#[event(standard = "custom", version = "1.0.0")]
+pub struct AuctionSettled {
+    pub tweet_id: String,
+    pub account: AccountId,
+    pub deposit: Balance,
+    pub bid_count: u32,
+}
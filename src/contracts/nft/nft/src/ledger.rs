@@ -0,0 +1,138 @@
+/*!
+The funds held by the contract, accounted for in buckets by who they belong to.
+
+Every deposit is credited to a bucket, and every transfer out of the contract is debited from
+the bucket the funds belong to, so that the funds of one cannot be paid out as another. Moving
+funds between buckets, or out of them, fails when the bucket does not hold enough.
+*/
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::{env, Balance};
+use serde::{Deserialize, Serialize};
+
+/// Who the funds in a bucket belong to.
+#[derive(
+    BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Copy, Debug, PartialEq,
+)]
+pub enum Bucket {
+    /// The deposits of pending mint requests and open auctions, owed back to their minters
+    /// and bidders until minted.
+    Escrow,
+    /// The royalties owed to authors, claimed with `claim_royalty`.
+    AuthorRoyalties,
+    /// The share of mints and penalties kept by the protocol, withdrawn by the treasury.
+    ProtocolFees,
+    /// The part of mints kept to cover the storage of the contract.
+    StorageReserve,
+}
+
+/// The balance of each bucket, in yoctoNEAR.
+#[derive(
+    BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Debug, Default, PartialEq,
+)]
+pub struct Ledger {
+    pub escrow: Balance,
+    pub author_royalties: Balance,
+    pub protocol_fees: Balance,
+    pub storage_reserve: Balance,
+}
+
+/// The ledger along with the balance of the contract, as returned by `get_ledger`.
+#[derive(Serialize, Deserialize)]
+pub struct LedgerView {
+    #[serde(flatten)]
+    pub ledger: Ledger,
+    /// The sum of the buckets.
+    pub total: Balance,
+    /// The balance of the contract account, which should cover `total` once the storage it
+    /// stakes is taken out.
+    pub account_balance: Balance,
+    /// The balance staked for the storage the contract uses.
+    pub storage_cost: Balance,
+}
+
+impl Ledger {
+    pub fn balance(&self, bucket: Bucket) -> Balance {
+        match bucket {
+            Bucket::Escrow => self.escrow,
+            Bucket::AuthorRoyalties => self.author_royalties,
+            Bucket::ProtocolFees => self.protocol_fees,
+            Bucket::StorageReserve => self.storage_reserve,
+        }
+    }
+
+    fn balance_mut(&mut self, bucket: Bucket) -> &mut Balance {
+        match bucket {
+            Bucket::Escrow => &mut self.escrow,
+            Bucket::AuthorRoyalties => &mut self.author_royalties,
+            Bucket::ProtocolFees => &mut self.protocol_fees,
+            Bucket::StorageReserve => &mut self.storage_reserve,
+        }
+    }
+
+    pub fn total(&self) -> Balance {
+        self.escrow + self.author_royalties + self.protocol_fees + self.storage_reserve
+    }
+
+    /// Adds funds received by the contract to a bucket.
+    pub fn credit(&mut self, bucket: Bucket, amount: Balance) -> Result<(), String> {
+        let balance = self.balance_mut(bucket);
+        *balance = balance
+            .checked_add(amount)
+            .ok_or_else(|| format!("{:?} balance overflows", bucket))?;
+        Ok(())
+    }
+
+    /// Takes funds paid out by the contract from a bucket.
+    pub fn debit(&mut self, bucket: Bucket, amount: Balance) -> Result<(), String> {
+        let balance = self.balance_mut(bucket);
+        *balance = balance.checked_sub(amount).ok_or_else(|| {
+            format!(
+                "{:?} balance of {} cannot cover {}",
+                bucket, *balance, amount
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Moves funds kept by the contract from one bucket to another.
+    pub fn transfer(&mut self, from: Bucket, to: Bucket, amount: Balance) -> Result<(), String> {
+        self.debit(from, amount)?;
+        self.credit(to, amount)
+    }
+
+    /// Applies a movement of funds, panicking when a bucket does not hold enough.
+    pub fn book(&mut self, movement: impl FnOnce(&mut Self) -> Result<(), String>) {
+        if let Err(reason) = movement(self) {
+            env::panic_str(&reason);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ledger() {
+        let mut ledger = Ledger::default();
+        ledger.credit(Bucket::Escrow, 100).unwrap();
+        ledger
+            .transfer(Bucket::Escrow, Bucket::ProtocolFees, 30)
+            .unwrap();
+        assert_eq!(ledger.balance(Bucket::Escrow), 70);
+        assert_eq!(ledger.balance(Bucket::ProtocolFees), 30);
+        assert_eq!(ledger.total(), 100);
+
+        // A bucket cannot pay out the funds of another
+        assert!(ledger.debit(Bucket::ProtocolFees, 31).is_err());
+        assert!(ledger
+            .transfer(Bucket::AuthorRoyalties, Bucket::Escrow, 1)
+            .is_err());
+        assert_eq!(ledger.total(), 100);
+
+        ledger.debit(Bucket::Escrow, 70).unwrap();
+        assert_eq!(ledger.balance(Bucket::Escrow), 0);
+        assert!(ledger.credit(Bucket::StorageReserve, Balance::MAX).is_ok());
+        assert!(ledger.credit(Bucket::StorageReserve, 1).is_err());
+    }
+}
@@ -15,10 +15,22 @@ NOTES:
   - To prevent the deployed contract from being modified or deleted, it should not have any access
     keys on its account.
 */
+mod auction;
+mod consent;
 mod events;
+mod ledger;
+mod migration;
+mod pricing;
+mod quote;
 
 use crate::events::TweetMintRequest;
-use events::CancelMintRequest;
+use consent::MAX_RESERVATION_WINDOW;
+use events::{
+    AuctionBid, AuctionOpened, AuctionSettled, AuthorConsentSet, AuthorLinked, CancelMintRequest,
+    ContractPaused, ContractUnpaused, PricingModelSet, QuoteSignerSet, RoleGranted, RoleRevoked,
+    RoyaltyClaimed,
+};
+use migration::{state_version, VersionedContract, STATE_VERSION};
 use near_contract_standards::non_fungible_token::events::NftMint;
 use near_contract_standards::non_fungible_token::metadata::{
     NFTContractMetadata, NonFungibleTokenMetadataProvider, TokenMetadata, NFT_METADATA_SPEC,
@@ -27,15 +39,26 @@ use near_contract_standards::non_fungible_token::NonFungibleToken;
 use near_contract_standards::non_fungible_token::{Token, TokenId};
 use near_contract_tools::standard::nep297::Event;
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{LazyOption, LookupMap};
+use near_sdk::collections::{LazyOption, LookupMap, LookupSet, UnorderedMap, UnorderedSet, Vector};
+use near_sdk::json_types::{Base64VecU8, U128};
 use near_sdk::{
-    env, near_bindgen, require, AccountId, Balance, BorshStorageKey, PanicOnDefault, Promise,
-    PromiseOrValue,
+    env, near_bindgen, require, AccountId, Balance, BorshStorageKey, Gas, PanicOnDefault, Promise,
+    PromiseOrValue, PromiseResult,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-
-#[derive(Serialize, Deserialize, BorshDeserialize, BorshSerialize, Clone, Copy)]
+use std::collections::HashMap;
+use std::convert::TryInto;
+
+pub use auction::{AuctionSettings, TweetAuction, TweetAuctionView};
+pub use consent::AuthorConsent;
+pub use ledger::{Bucket, Ledger, LedgerView};
+pub use pricing::{PriceQuote, PricingCurve, PricingModel, PricingTier};
+pub use quote::{MintQuote, SignedMintQuote};
+
+#[derive(
+    Serialize, Deserialize, BorshDeserialize, BorshSerialize, Clone, Copy, Debug, PartialEq,
+)]
 pub struct PublicMetric {
     bookmark_count: u128,
     impression_count: u128,
@@ -50,10 +73,29 @@ pub struct NFtMetaDataExtra {
     minted_to: String,
     public_metric: PublicMetric,
     author_id: String,
+    // Missing from tokens proven before the verifier passed it on.
+    #[serde(default)]
+    created_at: Option<String>,
+}
+
+/// The NEP-199 payout of a sale, the amount each account receives.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Payout {
+    pub payout: HashMap<AccountId, U128>,
+}
+
+/// How the proceeds of a secondary sale are split, in basis points of the sale price.
+///
+/// The seller receives what is left once the author and the protocol are paid.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PayoutConfig {
+    pub author_bps: u16,
+    pub protocol_bps: u16,
+    pub treasury: AccountId,
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, PartialEq, Clone)]
-enum MintRequestStatus {
+pub enum MintRequestStatus {
     Created,
     Cancelled,
     Unsuccessful,
@@ -61,6 +103,27 @@ enum MintRequestStatus {
     RoyaltyClaimed,
 }
 
+/// The roles admin methods are restricted to. An account can hold several roles, and a role
+/// can be held by several accounts.
+///
+/// * `Owner`: Grants and revokes roles, and manages the royalty manager, payout config and
+///   quote signer.
+/// * `PricingAdmin`: Sets the lock time, minimum deposit, pricing models, quote tolerance and
+///   auction settings.
+/// * `Minter`: Mints NFTs and links authors, held by the verifier proxy.
+/// * `Treasury`: Withdraws the protocol fees kept by the contract.
+/// * `Pauser`: Pauses and unpauses minting.
+#[derive(
+    BorshSerialize, BorshDeserialize, Serialize, Deserialize, PartialEq, Clone, Copy, Debug,
+)]
+pub enum Role {
+    Owner,
+    PricingAdmin,
+    Minter,
+    Treasury,
+    Pauser,
+}
+
 #[derive(BorshSerialize, BorshDeserialize, PartialEq)]
 enum RoyaltyOperation {
     Increase,
@@ -76,6 +139,14 @@ pub struct MintRequestData {
     status: MintRequestStatus,
 }
 
+/// A mint request along with the tweet it was made for, as returned by the enumeration views.
+#[derive(Serialize, Deserialize)]
+pub struct TweetRequest {
+    tweet_id: String,
+    #[serde(flatten)]
+    request: MintRequestData,
+}
+
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct Contract {
@@ -84,12 +155,42 @@ pub struct Contract {
     tweet_requests: LookupMap<String, MintRequestData>,
     lock_time: u64,
     min_deposit: Balance,
-    price_per_point: Balance,
-    // NOTE DENOMINATOR is 10e6
-    cost_per_metric: PublicMetric,
+    // Every pricing model set, the last one is used. Version `n` is at index `n - 1`.
+    pricing_models: Vector<PricingModel>,
+    // The pricing version each token was minted with.
+    token_pricing_versions: LookupMap<TokenId, u32>,
 
     royalty_manager: AccountId,
     royalty_balances: LookupMap<String, Balance>,
+
+    // Tweet ids of the requests in `tweet_requests`, indexed by status and by minter.
+    requests_by_status: LookupMap<MintRequestStatus, UnorderedSet<String>>,
+    requests_by_minter: LookupMap<AccountId, UnorderedSet<String>>,
+
+    roles: LookupMap<Role, UnorderedSet<AccountId>>,
+    // While paused, no request can be made or minted. Requests can still be cancelled.
+    paused: bool,
+
+    // The Ethereum address quotes are signed with, quotes are not accepted without one.
+    quote_signer: Option<[u8; 20]>,
+    // How much the cost of a quoted request may grow past the quote, in basis points.
+    quote_tolerance_bps: u16,
+    // The quotes requests were made with, removed with the request.
+    request_quotes: LookupMap<String, MintQuote>,
+
+    // Open auctions, keyed by tweet id. A tweet has either an auction or a request.
+    auctions: UnorderedMap<String, TweetAuction>,
+    auction_settings: AuctionSettings,
+
+    // The consent of authors who set one, keyed by their X id.
+    author_consents: LookupMap<String, AuthorConsent>,
+    // The X author requests and auctions were made for, checked against the proven tweet.
+    request_authors: LookupMap<String, String>,
+    // The sha256 hash of the image url requests were made with, checked by the verifier proxy.
+    request_images: LookupMap<String, Vec<u8>>,
+
+    // What the funds held by the contract are owed to.
+    ledger: Ledger,
 }
 
 const DATA_IMAGE_PNG: &str =
@@ -104,9 +205,101 @@ enum StorageKey {
     Approval,
     TweetRequests,
     RoyaltyBalances,
+    AuthorAccounts,
+    PayoutConfig,
+    RequestsByStatus,
+    RequestsByStatusInner { status: MintRequestStatus },
+    RequestsByMinter,
+    RequestsByMinterInner { account_hash: Vec<u8> },
+    StateVersion,
+    Roles,
+    RoleMembers { role: Role },
+    PricingModels,
+    TokenPricingVersions,
+    RequestQuotes,
+    Auctions,
+    AuthorConsents,
+    RequestAuthors,
+    RequestImages,
+    AuthorLinkTimes,
+    OpenedRoyalties,
+    LedgerOpenedAt,
+    EscrowedRequests,
+    OwedRoyalties,
 }
 
 const PRICE_PER_POINT: Balance = 2000000000000000000000;
+const GAS_FOR_ROYALTY_CLAIMED: Gas = Gas(5_000_000_000_000);
+
+// The accounts authors claim their royalties with, keyed by their X id.
+// Kept outside of the contract struct so the existing state does not need to be migrated.
+fn author_accounts() -> LookupMap<String, AccountId> {
+    LookupMap::new(StorageKey::AuthorAccounts)
+}
+
+// When the proof each author was linked with was served, in ms. Links made before it was
+// recorded are missing, and can be replaced by any proof.
+fn author_link_times() -> LookupMap<String, u64> {
+    LookupMap::new(StorageKey::AuthorLinkTimes)
+}
+
+// The authors whose royalty balance is held in the ledger. Balances held before the ledger
+// existed are credited to it when the author is first added.
+fn opened_royalties() -> LookupSet<String> {
+    LookupSet::new(StorageKey::OpenedRoyalties)
+}
+
+// When `migrate` opened the ledger, in ms. Pending requests made before it are credited to the
+// escrow when they are first seen. `None` for contracts created with the ledger.
+fn ledger_opened_at() -> LazyOption<u64> {
+    LazyOption::new(StorageKey::LedgerOpenedAt, None)
+}
+
+// The requests made before the ledger whose deposit has been credited to the escrow.
+fn escrowed_requests() -> LookupSet<String> {
+    LookupSet::new(StorageKey::EscrowedRequests)
+}
+
+// The shares of sales paid to the contract for authors who had not linked an account, keyed by
+// their X id. They are credited to the royalty balance of the author once the funds arrived.
+fn owed_royalties() -> LookupMap<String, Balance> {
+    LookupMap::new(StorageKey::OwedRoyalties)
+}
+
+// Kept outside of the contract struct for the same reason, `None` until it is first set.
+fn payout_config() -> LazyOption<PayoutConfig> {
+    LazyOption::new(StorageKey::PayoutConfig, None)
+}
+
+const BPS_DENOMINATOR: u128 = 10_000;
+const DEFAULT_PAGE_LIMIT: u64 = 50;
+const DEFAULT_AUTHOR_BPS: u16 = 500;
+const DEFAULT_PROTOCOL_BPS: u16 = 250;
+const DEFAULT_QUOTE_TOLERANCE_BPS: u16 = 1000;
+
+#[derive(Deserialize)]
+struct TokenTrait {
+    trait_type: String,
+    value: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TokenAuthor {
+    author_id: String,
+}
+
+// Reads the X author of a token from the `extra` written by `nft_mint`, where the metadata
+// verified by the verifier proxy is kept as the value of the "text" trait.
+fn author_of(token_metadata: &TokenMetadata) -> Option<String> {
+    let traits: Vec<TokenTrait> = serde_json::from_str(token_metadata.extra.as_ref()?).ok()?;
+    let text = traits
+        .into_iter()
+        .find(|token_trait| token_trait.trait_type == "text")?
+        .value?;
+    serde_json::from_str::<TokenAuthor>(&text)
+        .ok()
+        .map(|author| author.author_id)
+}
 
 #[near_bindgen]
 impl Contract {
@@ -137,7 +330,8 @@ impl Contract {
     ) -> Self {
         assert!(!env::state_exists(), "Already initialized");
         metadata.assert_valid();
-        Self {
+        state_version().set(&STATE_VERSION);
+        let mut contract = Self {
             tokens: NonFungibleToken::new(
                 StorageKey::NonFungibleToken,
                 owner_id,
@@ -151,10 +345,39 @@ impl Contract {
 
             // Min deposit is calculated dynamically based on storage cost.
             min_deposit: env::storage_byte_cost() * 1024,
-            price_per_point: PRICE_PER_POINT,
+            pricing_models: Vector::new(StorageKey::PricingModels),
+            token_pricing_versions: LookupMap::new(StorageKey::TokenPricingVersions),
+
+            royalty_manager,
+            royalty_balances: LookupMap::new(StorageKey::RoyaltyBalances),
+
+            requests_by_status: LookupMap::new(StorageKey::RequestsByStatus),
+            requests_by_minter: LookupMap::new(StorageKey::RequestsByMinter),
+
+            roles: LookupMap::new(StorageKey::Roles),
+            paused: false,
 
-            // NOT DENOMINATOR 10e6
-            cost_per_metric: PublicMetric {
+            quote_signer: None,
+            quote_tolerance_bps: DEFAULT_QUOTE_TOLERANCE_BPS,
+            request_quotes: LookupMap::new(StorageKey::RequestQuotes),
+
+            auctions: UnorderedMap::new(StorageKey::Auctions),
+            auction_settings: AuctionSettings::default(),
+
+            author_consents: LookupMap::new(StorageKey::AuthorConsents),
+            request_authors: LookupMap::new(StorageKey::RequestAuthors),
+            request_images: LookupMap::new(StorageKey::RequestImages),
+
+            ledger: Ledger::default(),
+        };
+        Self::grant_initial_roles(
+            &mut contract.roles,
+            &contract.tokens.owner_id,
+            &contract.royalty_manager,
+        );
+        // NOT DENOMINATOR 10e6
+        contract.pricing_models.push(&PricingModel::linear(
+            PublicMetric {
                 bookmark_count: 1190000,
                 impression_count: 100,
                 like_count: 500000,
@@ -162,10 +385,44 @@ impl Contract {
                 reply_count: 2000000,
                 retweet_count: 1400000,
             },
+            PRICE_PER_POINT,
+        ));
+        contract
+    }
 
-            royalty_manager,
-            royalty_balances: LookupMap::new(StorageKey::RoyaltyBalances),
+    /// Upgrades the stored state to the current layout, from any version it was written with.
+    ///
+    /// Called when deploying a new version of the contract. Migrating a state that is already
+    /// current leaves it as is.
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let contract = VersionedContract::read().upgrade();
+        state_version().set(&STATE_VERSION);
+        contract
+    }
+
+    /// Returns the version of the layout the state is stored with.
+    pub fn get_state_version(&self) -> u8 {
+        state_version().get().unwrap_or(1)
+    }
+
+    /// Adds requests made before `migrate` to the indexes, returning how many were found.
+    ///
+    /// The tweet ids can be taken from the orchestrator's database, and passed in batches.
+    /// Tweet ids without a request are ignored, and indexing a request twice has no effect.
+    /// The deposit of a pending request is credited to the escrow if it was not yet.
+    #[private]
+    pub fn index_requests(&mut self, tweet_ids: Vec<String>) -> u32 {
+        let mut indexed = 0;
+        for tweet_id in tweet_ids {
+            if let Some(request) = self.tweet_requests.get(&tweet_id) {
+                self.open_request_escrow(&tweet_id, &request);
+                self.index_request(&tweet_id, &request);
+                indexed += 1;
+            }
         }
+        indexed
     }
 
     /// Mint a new token with ID=`token_id` belonging to `receiver_id`.
@@ -176,13 +433,17 @@ impl Contract {
     ///
     /// `self.tokens.mint` will enforce `predecessor_account_id` to equal the `owner_id` given in
     /// initialization call to `new`.
+    ///
+    /// Returns `None` without minting when the request is refunded instead: it was made for
+    /// another author than the tweet's, the author does not consent, or the cost grew past the
+    /// quoted price. The refund would be reverted by a panic, so the reason is logged instead.
     #[payable]
     pub fn nft_mint(
         &mut self,
         token_id: TokenId,
         receiver_id: AccountId,
         mut token_metadata: TokenMetadata,
-    ) -> Token {
+    ) -> Option<Token> {
         // Get the mint request for the given token ID
         let mut request = self
             .get_request(token_id.clone())
@@ -194,17 +455,42 @@ impl Contract {
             serde_json::from_str(&token_metadata.clone().extra.expect("nft extra must exit"))
                 .unwrap();
 
-        // Check if the caller is the owner of the contract
-        require!(
-            env::predecessor_account_id().eq(&self.tokens.owner_id),
-            "NOT OWNER"
-        );
+        // Check if the caller is the verifier proxy
+        self.assert_role(Role::Minter);
+        self.assert_not_paused();
+
+        // The consent of the author was checked for the author the request was made for,
+        // which must be the proven one. Requests made before authors were declared have none.
+        if let Some(author_id) = self.request_authors.get(&token_id) {
+            if author_id != extra.author_id {
+                env::log_str("The request was made for another author than the tweet's");
+                self.claim_funds(token_id, request, MintRequestStatus::Cancelled);
+                return None;
+            }
+        }
+        // The author may have opted out since the request was made
+        if let Err(reason) = self.check_consent(
+            &token_id,
+            &extra.author_id,
+            &request.minter,
+            request.lock_time,
+        ) {
+            env::log_str(&reason);
+            self.claim_funds(token_id, request, MintRequestStatus::Unsuccessful);
+            return None;
+        }
 
-        // Check if the request has enough deposit to cover costs
-        if request
-            .claimable_deposit
-            .ge(&self.compute_cost(extra.public_metric.clone()))
-        {
+        // Check if the request has enough deposit to cover costs. A quoted request is charged
+        // the quoted price, unless the cost grew past it by more than the tolerance.
+        let quote = self.quote(&extra.public_metric, extra.created_at.as_deref());
+        let request_quote = self.request_quotes.get(&token_id);
+        let price = match &request_quote {
+            Some(request_quote) => {
+                request_quote.honoured_price(quote.total, self.quote_tolerance_bps)
+            }
+            None => Some(quote.total),
+        };
+        if let Some(price) = price.filter(|price| request.claimable_deposit.ge(price)) {
             // Create extra metadata for the NFT
             let json_extra = json!([
                     {
@@ -232,23 +518,36 @@ impl Contract {
                 Some(receiver_id.clone()),
             );
 
+            self.token_pricing_versions
+                .insert(&token_id, &quote.pricing_version);
+
+            self.request_quotes.remove(&token_id);
+            self.request_authors.remove(&token_id);
+            self.request_images.remove(&token_id);
+
             // Calculate refund amount
-            let refund_amount =
-                request.claimable_deposit - (&self.compute_cost(extra.public_metric.clone()));
-            let value = request.claimable_deposit
-                - &self.min_deposit
-                - &refund_amount
-                - (env::used_gas().0 as u128);
+            let refund_amount = request.claimable_deposit - price;
+            // The minimum deposit is kept for the storage of the request
+            let storage = price.min(self.min_deposit);
+            let value = price - storage;
 
             // We're allocating 80% of the deposit to the author.
             // 20% is used to cover the cost of the minting.
             // 8 / 10 = 80 / 100
-            // The remaining 20% of the value remains in the contract.
-            self.royalty_operation(extra.author_id, value * 8 / 10, RoyaltyOperation::Increase);
+            // The remaining 20% of the value, with what the rounding leaves, remains in the
+            // contract.
+            let royalty = value * 8 / 10;
+            self.open_request_escrow(&token_id, &request);
+            self.ledger.book(|ledger| {
+                ledger.transfer(Bucket::Escrow, Bucket::StorageReserve, storage)?;
+                ledger.transfer(Bucket::Escrow, Bucket::AuthorRoyalties, royalty)?;
+                ledger.transfer(Bucket::Escrow, Bucket::ProtocolFees, value - royalty)
+            });
+            self.royalty_operation(extra.author_id, royalty, RoyaltyOperation::Increase);
 
             // Update request status and refund amount
             request.claimable_deposit = refund_amount;
-            self.tweet_requests.insert(&token.token_id, &request);
+            self.insert_request(&token.token_id, &request);
             self.claim_funds(token_id.clone(), request, MintRequestStatus::IsFulfilled);
             NftMint {
                 owner_id: &receiver_id,
@@ -256,25 +555,46 @@ impl Contract {
                 memo: None,
             }
             .emit();
-            return token;
+            return Some(token);
+        } else if request_quote.is_some() {
+            // The user is not penalized for growth they were not quoted for
+            env::log_str(&format!(
+                "Cost of {} exceeds the quoted price beyond the tolerance",
+                quote.total
+            ));
+            self.claim_funds(token_id, request, MintRequestStatus::Unsuccessful);
+            None
         } else {
             // penalize user by decreasing Claimable Balance
             self.claim_funds(token_id, request.clone(), MintRequestStatus::Cancelled);
             env::panic_str(&format!(
                 "Minimum deposit Not met of {}, you attached {} while minting.",
-                self.compute_cost(extra.public_metric),
-                request.claimable_deposit
+                quote.total, request.claimable_deposit
             ))
         }
     }
 
+    /// Requests the mint of a tweet, locking it for the caller.
+    ///
+    /// `author_id` is the X id of the author of the tweet, whose consent is checked. Once a quote
+    /// signer is set, a `quote` signed by it is required, and the author must be the one the
+    /// quote signer read from the tweet. Without a quote signer, the author is not proven until
+    /// the tweet is minted: the request is then refunded with the cancellation penalty if it is
+    /// not the author of the proven tweet. The consent of the proven author is checked again
+    /// when minting.
+    ///
+    /// With a `quote`, the deposit must cover the quoted price, and the request is charged at
+    /// most the quoted price when minted.
     #[payable]
     pub fn mint_tweet_request(
         &mut self,
         tweet_id: String,
+        author_id: String,
         image_url: String,
         notify: String,
+        quote: Option<SignedMintQuote>,
     ) -> MintRequestData {
+        self.assert_not_paused();
         require!(
             env::attached_deposit().ge(&self.min_deposit),
             format!(
@@ -283,6 +603,21 @@ impl Contract {
                 env::attached_deposit()
             )
         );
+        if self.quote_signer.is_some() {
+            require!(quote.is_some(), "A signed quote is required");
+        }
+        let quote = quote.map(|quote| self.verify_quote(&tweet_id, quote));
+        if let Some(quote) = &quote {
+            require!(quote.author_id == author_id, "Quote is for another author");
+            require!(
+                env::attached_deposit().ge(&quote.price.0),
+                format!(
+                    "Quoted price Not met of {}, you attached {}",
+                    quote.price.0,
+                    env::attached_deposit()
+                )
+            );
+        }
         if tweet_id.clone().parse::<u64>().is_err() {
             env::panic_str("tweet_id must be a positive number");
         }
@@ -293,6 +628,14 @@ impl Contract {
         if !self.is_tweet_available(tweet_id.clone()) {
             env::panic_str("This tweet_id has a lock on it");
         }
+        if let Err(reason) = self.check_consent(
+            &tweet_id,
+            &author_id,
+            &env::predecessor_account_id(),
+            env::block_timestamp_ms(),
+        ) {
+            env::panic_str(&reason);
+        }
 
         let entry = MintRequestData {
             // Get the signer's account ID
@@ -303,7 +646,16 @@ impl Contract {
             claimable_deposit: env::attached_deposit(),
             status: MintRequestStatus::Created,
         };
-        self.tweet_requests.insert(&tweet_id, &entry);
+        self.insert_request(&tweet_id, &entry);
+        match &quote {
+            Some(quote) => self.request_quotes.insert(&tweet_id, quote),
+            None => self.request_quotes.remove(&tweet_id),
+        };
+        self.request_authors.insert(&tweet_id, &author_id);
+        self.request_images
+            .insert(&tweet_id, &env::sha256(image_url.as_bytes()));
+        self.ledger
+            .book(|ledger| ledger.credit(Bucket::Escrow, env::attached_deposit()));
 
         // Log an event-like message
         let event = TweetMintRequest {
@@ -334,10 +686,313 @@ impl Contract {
         }
     }
 
+    /// Bids for the mint of a tweet, with the attached deposit.
+    ///
+    /// The first bid opens an auction for the tweet, closing after the auction window. Later
+    /// bids must beat the highest one by the minimum increment, and refund it.
+    #[payable]
+    pub fn bid_tweet_request(
+        &mut self,
+        tweet_id: String,
+        author_id: String,
+        image_url: String,
+        notify: String,
+    ) -> TweetAuction {
+        self.assert_not_paused();
+        let deposit = env::attached_deposit();
+        let bidder = env::predecessor_account_id();
+        let now = env::block_timestamp_ms();
+
+        let auction = match self.auctions.get(&tweet_id) {
+            Some(highest) => {
+                require!(highest.is_open(now), "Auction has ended");
+                if let Some(auction_author_id) = self.request_authors.get(&tweet_id) {
+                    require!(
+                        auction_author_id == author_id,
+                        "The auction was opened for another author"
+                    );
+                }
+                let min_bid = highest.min_next_bid(self.auction_settings.min_bid_increment_bps);
+                require!(
+                    deposit >= min_bid,
+                    format!("Bid must be at least {}, you attached {}", min_bid, deposit)
+                );
+                self.ledger
+                    .book(|ledger| ledger.debit(Bucket::Escrow, highest.deposit));
+                Promise::new(highest.bidder.clone()).transfer(highest.deposit);
+                AuctionBid {
+                    tweet_id: tweet_id.clone(),
+                    account: bidder.clone(),
+                    deposit,
+                    outbid: highest.bidder,
+                    refund: highest.deposit,
+                }
+                .emit();
+                TweetAuction {
+                    bidder,
+                    deposit,
+                    image_url,
+                    notify,
+                    ends_at: highest.ends_at,
+                    bid_count: highest.bid_count + 1,
+                }
+            }
+            None => {
+                require!(
+                    deposit >= self.min_deposit,
+                    format!(
+                        "Minimum deposit Not met of {}, you attached {}",
+                        &self.min_deposit, deposit
+                    )
+                );
+                if tweet_id.parse::<u64>().is_err() {
+                    env::panic_str("tweet_id must be a positive number");
+                }
+                if self.tokens.owner_by_id.get(&tweet_id).is_some() {
+                    env::panic_str("tweet_id has been minted already");
+                }
+                if !self.is_tweet_available(tweet_id.clone()) {
+                    env::panic_str("This tweet_id has a lock on it");
+                }
+                let ends_at = now + self.auction_settings.window;
+                AuctionOpened {
+                    tweet_id: tweet_id.clone(),
+                    account: bidder.clone(),
+                    deposit,
+                    ends_at,
+                }
+                .emit();
+                TweetAuction {
+                    bidder,
+                    deposit,
+                    image_url,
+                    notify,
+                    ends_at,
+                    bid_count: 1,
+                }
+            }
+        };
+        if let Err(reason) = self.check_consent(&tweet_id, &author_id, &auction.bidder, now) {
+            env::panic_str(&reason);
+        }
+        self.auctions.insert(&tweet_id, &auction);
+        self.request_authors.insert(&tweet_id, &author_id);
+        self.ledger
+            .book(|ledger| ledger.credit(Bucket::Escrow, deposit));
+        auction
+    }
+
+    /// Closes the auction of a tweet once its bidding window has passed, turning the highest
+    /// bid into a mint request. Can be called by anyone.
+    ///
+    /// It can be called while paused, so the highest bidder can cancel the request and be
+    /// refunded like any other minter.
+    pub fn settle_auction(&mut self, tweet_id: String) -> MintRequestData {
+        let auction = self
+            .auctions
+            .get(&tweet_id)
+            .unwrap_or_else(|| env::panic_str("No auction for this tweet_id"));
+        require!(
+            !auction.is_open(env::block_timestamp_ms()),
+            format!("Auction is open until {}", auction.ends_at)
+        );
+        self.auctions.remove(&tweet_id);
+
+        let entry = MintRequestData {
+            minter: auction.bidder.clone(),
+            lock_time: env::block_timestamp_ms(),
+            claimable_deposit: auction.deposit,
+            status: MintRequestStatus::Created,
+        };
+        self.insert_request(&tweet_id, &entry);
+        self.request_images
+            .insert(&tweet_id, &env::sha256(auction.image_url.as_bytes()));
+
+        AuctionSettled {
+            tweet_id: tweet_id.clone(),
+            account: auction.bidder.clone(),
+            deposit: auction.deposit,
+            bid_count: auction.bid_count,
+        }
+        .emit();
+        TweetMintRequest {
+            tweet_id,
+            account: auction.bidder,
+            deposit: auction.deposit,
+            image_url: auction.image_url,
+            notify: auction.notify,
+        }
+        .emit();
+
+        entry
+    }
+
+    pub fn get_auction(&self, tweet_id: String) -> Option<TweetAuction> {
+        self.auctions.get(&tweet_id)
+    }
+
+    /// Lists the auctions that have not been settled, including closed ones.
+    pub fn get_auctions(
+        &self,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<TweetAuctionView> {
+        let from_index = from_index.map(|index| index.0).unwrap_or(0);
+        let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT);
+        require!(limit != 0, "Cannot provide limit of 0.");
+        self.auctions
+            .iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .map(|(tweet_id, auction)| TweetAuctionView { tweet_id, auction })
+            .collect()
+    }
+
+    pub fn get_auction_settings(&self) -> AuctionSettings {
+        self.auction_settings.clone()
+    }
+
+    /// Sets the settings of auctions opened from now on.
+    pub fn update_auction_settings(&mut self, settings: AuctionSettings) {
+        self.assert_role(Role::PricingAdmin);
+        require!(settings.window > 0, "Auction window cannot be 0");
+        self.auction_settings = settings;
+    }
+
     pub fn get_request(&self, tweet_id: String) -> Option<MintRequestData> {
         self.tweet_requests.get(&tweet_id)
     }
 
+    /// Lists the requests made by `account`, `limit` at a time from `from_index`.
+    pub fn get_requests_by_minter(
+        &self,
+        account: AccountId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<TweetRequest> {
+        match self.requests_by_minter.get(&account) {
+            Some(tweet_ids) => self.paginate_requests(&tweet_ids, from_index, limit),
+            None => vec![],
+        }
+    }
+
+    /// Lists the requests with `status`, `limit` at a time from `from_index`.
+    ///
+    /// Cancelled and unsuccessful requests are removed once refunded, so only `Created` and
+    /// `IsFulfilled` requests are found.
+    pub fn get_requests_by_status(
+        &self,
+        status: MintRequestStatus,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<TweetRequest> {
+        match self.requests_by_status.get(&status) {
+            Some(tweet_ids) => self.paginate_requests(&tweet_ids, from_index, limit),
+            None => vec![],
+        }
+    }
+
+    /// Lists the requests waiting to be minted, `limit` at a time from `from_index`.
+    pub fn get_pending_requests(
+        &self,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<TweetRequest> {
+        self.get_requests_by_status(MintRequestStatus::Created, from_index, limit)
+    }
+
+    /// Number of requests with `status`.
+    pub fn get_request_count_by_status(&self, status: MintRequestStatus) -> u64 {
+        self.requests_by_status
+            .get(&status)
+            .map(|tweet_ids| tweet_ids.len())
+            .unwrap_or(0)
+    }
+
+    fn paginate_requests(
+        &self,
+        tweet_ids: &UnorderedSet<String>,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<TweetRequest> {
+        let from_index = from_index.map(|index| index.0).unwrap_or(0);
+        let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT);
+        require!(limit != 0, "Cannot provide limit of 0.");
+        tweet_ids
+            .as_vector()
+            .iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .filter_map(|tweet_id| {
+                self.tweet_requests
+                    .get(&tweet_id)
+                    .map(|request| TweetRequest { tweet_id, request })
+            })
+            .collect()
+    }
+
+    fn requests_of_status(status: &MintRequestStatus) -> UnorderedSet<String> {
+        UnorderedSet::new(StorageKey::RequestsByStatusInner {
+            status: status.clone(),
+        })
+    }
+
+    fn requests_of_minter(account: &AccountId) -> UnorderedSet<String> {
+        UnorderedSet::new(StorageKey::RequestsByMinterInner {
+            account_hash: env::sha256(account.as_bytes()),
+        })
+    }
+
+    fn index_request(&mut self, tweet_id: &String, request: &MintRequestData) {
+        let mut by_status = self
+            .requests_by_status
+            .get(&request.status)
+            .unwrap_or_else(|| Self::requests_of_status(&request.status));
+        by_status.insert(tweet_id);
+        self.requests_by_status.insert(&request.status, &by_status);
+
+        let mut by_minter = self
+            .requests_by_minter
+            .get(&request.minter)
+            .unwrap_or_else(|| Self::requests_of_minter(&request.minter));
+        by_minter.insert(tweet_id);
+        self.requests_by_minter.insert(&request.minter, &by_minter);
+    }
+
+    fn unindex_request(&mut self, tweet_id: &String, request: &MintRequestData) {
+        if let Some(mut by_status) = self.requests_by_status.get(&request.status) {
+            by_status.remove(tweet_id);
+            self.requests_by_status.insert(&request.status, &by_status);
+        }
+        if let Some(mut by_minter) = self.requests_by_minter.get(&request.minter) {
+            by_minter.remove(tweet_id);
+            if by_minter.is_empty() {
+                self.requests_by_minter.remove(&request.minter);
+            } else {
+                self.requests_by_minter.insert(&request.minter, &by_minter);
+            }
+        }
+    }
+
+    // Every change to `tweet_requests` goes through `insert_request` and `remove_request`, so
+    // the indexes follow it.
+    fn insert_request(&mut self, tweet_id: &String, request: &MintRequestData) {
+        if let Some(previous) = self.tweet_requests.insert(tweet_id, request) {
+            self.unindex_request(tweet_id, &previous);
+        }
+        self.index_request(tweet_id, request);
+    }
+
+    fn remove_request(&mut self, tweet_id: &String) {
+        if let Some(previous) = self.tweet_requests.remove(tweet_id) {
+            self.unindex_request(tweet_id, &previous);
+        }
+        self.request_quotes.remove(tweet_id);
+        self.request_authors.remove(tweet_id);
+        self.request_images.remove(tweet_id);
+        escrowed_requests().remove(tweet_id);
+    }
+
     #[private]
     fn is_tweet_available(&mut self, tweet_id: String) -> bool {
         let entry = self.tweet_requests.get(&tweet_id);
@@ -350,6 +1005,9 @@ impl Contract {
         {
             return false;
         }
+        if self.auctions.get(&tweet_id).is_some() {
+            return false;
+        }
         //replace env::block_timestamp with
         match entry {
             Some(mint_request) => {
@@ -374,14 +1032,13 @@ impl Contract {
         amount: Balance,
         operation: RoyaltyOperation,
     ) {
+        self.open_royalty_balance(&author_id);
         if operation == RoyaltyOperation::Erase {
             self.royalty_balances.insert(&author_id, &0);
         } else if self.royalty_balances.contains_key(&author_id) {
             let balance = self.royalty_balances.get(&author_id).unwrap();
             match operation {
-                // This is never called.
-                // It's added because Storage settings are immutable and cannote be changed.
-                // However, the logic of the contract can be upgraded.
+                // Called when an author claims part of their royalties.
                 RoyaltyOperation::Decrease => {
                     if balance < amount {
                         env::panic_str(
@@ -401,7 +1058,7 @@ impl Contract {
                     self.royalty_balances
                         .insert(&author_id, &(balance + amount));
                 }
-                // Once on-chain payouts are integrated, balances can be automatically erased on payout
+                // Handled above, balances are erased when an author claims all of their royalties
                 RoyaltyOperation::Erase => {}
             }
         } else {
@@ -413,142 +1070,790 @@ impl Contract {
         }
     }
 
+    /// Withdraws protocol fees kept by the contract to the calling treasury account.
+    ///
+    /// Escrowed deposits, author royalties and the storage reserve cannot be withdrawn.
     pub fn royalty_withdraw(&mut self, amount: Balance) {
-        // Check ensures that the royalty manager does not need to be deployer/AccountID of the Contract.
-        require!(
-            env::predecessor_account_id() == self.royalty_manager,
-            "Insufficient Access"
-        );
+        self.assert_role(Role::Treasury);
         let storage_cost = u128::from(env::storage_usage()) * env::storage_byte_cost();
-        if env::account_balance() - storage_cost >= amount {
-            Promise::new(self.royalty_manager.clone()).transfer(amount);
-        } else {
+        let available = env::account_balance().saturating_sub(storage_cost);
+        if available < amount {
             env::panic_str(
-                format!(
-                    "Invalid Amount: Claimable Account Balance: {}",
-                    storage_cost - env::account_balance()
-                )
-                .as_str(),
+                format!("Invalid Amount: Claimable Account Balance: {}", available).as_str(),
             )
         }
+        self.ledger
+            .book(|ledger| ledger.debit(Bucket::ProtocolFees, amount));
+        Promise::new(env::predecessor_account_id()).transfer(amount);
     }
 
-    pub fn update_royalty_manager(&mut self, account: AccountId) {
-        // Check ensures that the royalty manager does not need to be deployer/AccountID of the Contract.
+    /// Returns the balance of each bucket of the ledger, along with the balance of the contract
+    /// to reconcile them with.
+    pub fn get_ledger(&self) -> LedgerView {
+        LedgerView {
+            ledger: self.ledger.clone(),
+            total: self.ledger.total(),
+            account_balance: env::account_balance(),
+            storage_cost: u128::from(env::storage_usage()) * env::storage_byte_cost(),
+        }
+    }
+
+    /// Credits the ledger with the protocol fees and storage reserve held before it existed.
+    ///
+    /// Deposits and royalties are owed to users, so they are read from the state instead: the
+    /// deposits of requests made before `migrate` are credited by `index_requests`, and the
+    /// royalties by `open_royalty_balances`, or when they are first used otherwise. The ledger
+    /// cannot exceed the funds of the contract.
+    #[private]
+    pub fn credit_opening_balances(
+        &mut self,
+        protocol_fees: Balance,
+        storage_reserve: Balance,
+    ) -> LedgerView {
+        self.ledger.book(|ledger| {
+            ledger.credit(Bucket::ProtocolFees, protocol_fees)?;
+            ledger.credit(Bucket::StorageReserve, storage_reserve)
+        });
+        let view = self.get_ledger();
         require!(
-            env::predecessor_account_id() == self.royalty_manager,
-            "Insufficient Access"
+            view.total <= view.account_balance.saturating_sub(view.storage_cost),
+            "Opening balances exceed the funds of the contract"
         );
-        self.royalty_manager = account
+        view
     }
 
-    pub fn get_lock_time(&self) -> u64 {
-        self.lock_time
+    /// Credits the ledger with the royalty balances authors held before it existed, returning
+    /// how many authors were added to it.
+    ///
+    /// The author ids can be taken from the orchestrator's database, and passed in batches.
+    /// Authors already in the ledger are ignored. A balance is otherwise added the next time it
+    /// changes.
+    #[private]
+    pub fn open_royalty_balances(&mut self, author_ids: Vec<String>) -> u32 {
+        author_ids
+            .iter()
+            .filter(|author_id| self.open_royalty_balance(author_id))
+            .count() as u32
     }
 
-    #[private]
-    fn claim_funds(
-        &mut self,
-        tweet_id: String,
-        mint_request: MintRequestData,
-        status: MintRequestStatus,
-    ) {
-        if status == MintRequestStatus::IsFulfilled {
-            Promise::new(mint_request.minter.clone()).transfer(mint_request.claimable_deposit);
-            self.tweet_requests.insert(
-                &tweet_id,
-                &MintRequestData {
-                    minter: mint_request.minter,
-                    lock_time: mint_request.lock_time,
-                    claimable_deposit: 0,
-                    status: MintRequestStatus::IsFulfilled,
-                },
-            );
-        } else if status == MintRequestStatus::Cancelled
-            || status == MintRequestStatus::Unsuccessful
+    // Credits the escrow with the deposit of a pending request made before the ledger, the first
+    // time it is seen. Must be called before the deposit of a request is debited from the escrow.
+    fn open_request_escrow(&mut self, tweet_id: &String, request: &MintRequestData) -> bool {
+        let is_legacy = ledger_opened_at()
+            .get()
+            .map_or(false, |opened_at| request.lock_time < opened_at);
+        if !is_legacy
+            || request.status != MintRequestStatus::Created
+            || !escrowed_requests().insert(tweet_id)
         {
-            let amount = if status == MintRequestStatus::Cancelled {
-                mint_request.claimable_deposit * 9 / 10 // Transferring 90% of the origin deposit back to the minter.
-            } else {
-                mint_request.claimable_deposit // Else if unsuccessful, transferring 100% of the origin deposit back to the minter.
-            };
-            Promise::new(mint_request.minter.clone()).transfer(amount);
-            self.tweet_requests.remove(&tweet_id);
-            let event = CancelMintRequest {
-                tweet_id: tweet_id, // You might want to generate a unique ID here
-                account: env::predecessor_account_id(),
-                // TODO: Set the withdraw amount once, and re-use
-                withdraw: amount,
-            };
-            event.emit();
+            return false;
         }
+        self.ledger
+            .book(|ledger| ledger.credit(Bucket::Escrow, request.claimable_deposit));
+        true
     }
 
-    pub fn update_lock_time(&mut self, new_value: u64) -> u64 {
-        require!(
-            env::predecessor_account_id() == self.tokens.owner_id,
-            "NOT OWNER"
-        );
-        self.lock_time = new_value;
-        // Log an event-like message
-        env::log_str(format!("lock_time updated: {}", new_value).as_str());
-        new_value
+    // Credits the ledger with the royalty balance of an author the first time it is seen.
+    fn open_royalty_balance(&mut self, author_id: &String) -> bool {
+        if !opened_royalties().insert(author_id) {
+            return false;
+        }
+        let balance = self.get_royalty_balance(author_id.clone());
+        self.ledger
+            .book(|ledger| ledger.credit(Bucket::AuthorRoyalties, balance));
+        true
     }
 
-    // NOT DENOMINATOR 10e6
-    #[private]
-    pub fn set_cost_per_metric(&mut self, cost_per_metric: PublicMetric) {
-        self.cost_per_metric = cost_per_metric;
+    /// Links an X author to the account their royalties can be claimed by.
+    ///
+    /// Only the verifier proxy, which holds the minter role, can link an author. It does so once
+    /// it has verified a zkTLS proof of a tweet or bio of the author that mentions the account.
+    /// `proven_at` is when the proven response was served, in ms. Linking an author again
+    /// replaces the account, but only with a proof served after the one of the current link.
+    pub fn link_author(&mut self, author_id: String, account_id: AccountId, proven_at: u64) {
+        self.assert_role(Role::Minter);
+        let mut link_times = author_link_times();
+        if author_accounts().contains_key(&author_id) {
+            require!(
+                link_times
+                    .get(&author_id)
+                    .map_or(true, |linked_at| proven_at > linked_at),
+                "The author is already linked with a newer proof"
+            );
+        }
+        author_accounts().insert(&author_id, &account_id);
+        link_times.insert(&author_id, &proven_at);
+        AuthorLinked {
+            author_id,
+            account: account_id,
+        }
+        .emit();
     }
 
-    // This function is called internally, and externally.
-    // External calls are used to determine the cost of minting an NFT.
-    pub fn compute_cost(&self, public_metrics: PublicMetric) -> u128 {
-        let cost_per_metric = self.cost_per_metric.clone();
-        let cost = self.min_deposit
-            + (self.price_per_point
-                * (cost_per_metric.bookmark_count * public_metrics.bookmark_count
-                    + cost_per_metric.impression_count * public_metrics.impression_count
-                    + cost_per_metric.like_count * public_metrics.like_count
-                    + cost_per_metric.quote_count * public_metrics.quote_count
-                    + cost_per_metric.reply_count * public_metrics.reply_count
-                    + cost_per_metric.retweet_count * public_metrics.retweet_count)
-                / 1000000);
-        if cost.lt(&self.min_deposit) {
-            // The 5x is a buffer that allows the UX to determine what minimum it should deposit at the time of intent submission.
-            // This prevents extremely early X posts that haven't accumulated any metrics from being rejected from minting.
-            return self.min_deposit * 5;
-        }
-        cost
+    pub fn get_author_account(&self, author_id: String) -> Option<AccountId> {
+        author_accounts().get(&author_id)
     }
 
-    pub fn update_min_deposit(&mut self, min_deposit: Balance) {
+    /// Sets what can be done with the tweets of an author. Only the account linked to the
+    /// author with `link_author` can set it.
+    ///
+    /// With `opted_out`, none of the author's tweets can be minted. Otherwise, only the author
+    /// can request the mint of a tweet for `reservation_window` ms after it is posted.
+    pub fn set_author_consent(
+        &mut self,
+        author_id: String,
+        opted_out: bool,
+        reservation_window: u64,
+    ) -> AuthorConsent {
+        let account = author_accounts()
+            .get(&author_id)
+            .expect("No account linked to this author");
         require!(
-            env::predecessor_account_id() == self.tokens.owner_id,
-            "NOT OWNER"
+            account == env::predecessor_account_id(),
+            "Only the account linked to the author can set its consent"
         );
-        self.min_deposit = min_deposit;
+        require!(
+            reservation_window <= MAX_RESERVATION_WINDOW,
+            format!(
+                "Reservation window cannot exceed {}",
+                MAX_RESERVATION_WINDOW
+            )
+        );
+        let consent = AuthorConsent {
+            opted_out,
+            reservation_window,
+        };
+        self.author_consents.insert(&author_id, &consent);
+        AuthorConsentSet {
+            author_id,
+            opted_out,
+            reservation_window,
+        }
+        .emit();
+        consent
     }
-}
 
-near_contract_standards::impl_non_fungible_token_core!(Contract, tokens);
-near_contract_standards::impl_non_fungible_token_approval!(Contract, tokens);
-near_contract_standards::impl_non_fungible_token_enumeration!(Contract, tokens);
+    pub fn get_author_consent(&self, author_id: String) -> Option<AuthorConsent> {
+        self.author_consents.get(&author_id)
+    }
 
-#[near_bindgen]
-impl NonFungibleTokenMetadataProvider for Contract {
-    fn nft_metadata(&self) -> NFTContractMetadata {
-        self.metadata.get().unwrap()
+    /// Returns the X author a pending request or open auction was made for.
+    pub fn get_request_author(&self, tweet_id: String) -> Option<String> {
+        self.request_authors.get(&tweet_id)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use near_sdk::test_utils::{accounts, VMContextBuilder};
-    use near_sdk::testing_env;
-    use std::collections::HashMap;
-    use std::default;
-    use std::time::SystemTime;
+    /// Returns the sha256 hash of the image url a pending request was made with.
+    ///
+    /// The verifier proxy only mints a tweet with the image it was requested with.
+    pub fn get_request_image_hash(&self, tweet_id: String) -> Option<Base64VecU8> {
+        self.request_images.get(&tweet_id).map(Base64VecU8)
+    }
+
+    /// Records the image url of requests made before `migrate`, returning how many were
+    /// recorded.
+    ///
+    /// The image urls can be taken from the `tweet_mint_request` events indexed by the
+    /// orchestrator, and passed in batches. Only pending requests without an image are recorded.
+    #[private]
+    pub fn record_request_images(&mut self, images: Vec<(String, String)>) -> u32 {
+        let mut recorded = 0;
+        for (tweet_id, image_url) in images {
+            let pending = self.tweet_requests.get(&tweet_id).map_or(false, |request| {
+                request.status == MintRequestStatus::Created
+            });
+            if pending && self.request_images.get(&tweet_id).is_none() {
+                self.request_images
+                    .insert(&tweet_id, &env::sha256(image_url.as_bytes()));
+                recorded += 1;
+            }
+        }
+        recorded
+    }
+
+    // Checks that the author of a tweet consents to its mint being requested by `minter` at
+    // `requested_at` ms.
+    fn check_consent(
+        &self,
+        tweet_id: &str,
+        author_id: &str,
+        minter: &AccountId,
+        requested_at: u64,
+    ) -> Result<(), String> {
+        let author_id = author_id.to_string();
+        match self.author_consents.get(&author_id) {
+            Some(consent) => {
+                let by_author = author_accounts().get(&author_id).as_ref() == Some(minter);
+                consent.check(tweet_id, by_author, requested_at)
+            }
+            None => Ok(()),
+        }
+    }
+
+    pub fn get_royalty_balance(&self, author_id: String) -> Balance {
+        self.royalty_balances.get(&author_id).unwrap_or(0)
+    }
+
+    /// Returns the shares of sales paid to the contract for an author which are not yet credited
+    /// to their royalty balance.
+    pub fn get_owed_royalty(&self, author_id: String) -> Balance {
+        owed_royalties().get(&author_id).unwrap_or(0)
+    }
+
+    /// Withdraws the royalties of an author to the account linked with `link_author`.
+    ///
+    /// `amount` defaults to the whole balance. The shares of sales owed to the author are
+    /// credited to the balance first. The balance is debited before the transfer and credited
+    /// back if the transfer fails.
+    pub fn claim_royalty(&mut self, author_id: String, amount: Option<Balance>) -> Promise {
+        let account = author_accounts()
+            .get(&author_id)
+            .expect("No account linked to this author");
+        require!(
+            env::predecessor_account_id() == account,
+            "Only the account linked to the author can claim"
+        );
+        self.credit_owed_royalty(&author_id);
+
+        let balance = self.get_royalty_balance(author_id.clone());
+        let amount = amount.unwrap_or(balance);
+        require!(amount > 0, "No royalty to claim");
+        if amount == balance {
+            self.royalty_operation(author_id.clone(), amount, RoyaltyOperation::Erase);
+        } else {
+            self.royalty_operation(author_id.clone(), amount, RoyaltyOperation::Decrease);
+        }
+        self.ledger
+            .book(|ledger| ledger.debit(Bucket::AuthorRoyalties, amount));
+
+        Promise::new(account.clone()).transfer(amount).then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_ROYALTY_CLAIMED)
+                .on_royalty_claimed(author_id, account, amount),
+        )
+    }
+
+    #[private]
+    pub fn on_royalty_claimed(
+        &mut self,
+        author_id: String,
+        account: AccountId,
+        amount: Balance,
+    ) -> bool {
+        match env::promise_result(0) {
+            PromiseResult::Successful(_) => {
+                RoyaltyClaimed {
+                    author_id,
+                    account,
+                    amount,
+                }
+                .emit();
+                true
+            }
+            _ => {
+                env::log_str(&format!(
+                    "Royalty transfer of {} to {} failed, balance restored",
+                    amount, account
+                ));
+                self.royalty_operation(author_id, amount, RoyaltyOperation::Increase);
+                self.ledger
+                    .book(|ledger| ledger.credit(Bucket::AuthorRoyalties, amount));
+                false
+            }
+        }
+    }
+
+    // Credits the shares of sales owed to an author to their royalty balance, as far as the
+    // funds of the contract outside of the ledger cover them. Marketplaces pay the shares with
+    // plain transfers after `nft_transfer_payout`, so they can only be told apart from the
+    // balance of the contract.
+    fn credit_owed_royalty(&mut self, author_id: &String) {
+        let mut owed = owed_royalties();
+        let amount = match owed.get(author_id) {
+            Some(amount) => amount,
+            None => return,
+        };
+        let view = self.get_ledger();
+        let untracked = view
+            .account_balance
+            .saturating_sub(view.storage_cost)
+            .saturating_sub(view.total);
+        let credited = amount.min(untracked);
+        if credited == amount {
+            owed.remove(author_id);
+        } else {
+            owed.insert(author_id, &(amount - credited));
+        }
+        if credited > 0 {
+            self.royalty_operation(author_id.clone(), credited, RoyaltyOperation::Increase);
+            self.ledger
+                .book(|ledger| ledger.credit(Bucket::AuthorRoyalties, credited));
+        }
+    }
+
+    pub fn get_payout_config(&self) -> PayoutConfig {
+        payout_config().get().unwrap_or(PayoutConfig {
+            author_bps: DEFAULT_AUTHOR_BPS,
+            protocol_bps: DEFAULT_PROTOCOL_BPS,
+            treasury: self.royalty_manager.clone(),
+        })
+    }
+
+    pub fn set_payout_config(&mut self, author_bps: u16, protocol_bps: u16, treasury: AccountId) {
+        self.assert_role(Role::Owner);
+        require!(
+            u128::from(author_bps) + u128::from(protocol_bps) <= BPS_DENOMINATOR,
+            "Payout shares exceed 100%"
+        );
+        payout_config().set(&PayoutConfig {
+            author_bps,
+            protocol_bps,
+            treasury,
+        });
+    }
+
+    // Splits `balance` between the author, the treasury and `seller`.
+    // The share of an author who has not linked an account is paid to the contract, and returned
+    // along with the author.
+    fn compute_payout(
+        &self,
+        token_id: &TokenId,
+        seller: &AccountId,
+        balance: Balance,
+        max_len_payout: Option<u32>,
+    ) -> (Payout, Option<(String, Balance)>) {
+        let config = self.get_payout_config();
+        let author = self
+            .tokens
+            .token_metadata_by_id
+            .as_ref()
+            .and_then(|metadata| metadata.get(token_id))
+            .and_then(|metadata| author_of(&metadata));
+
+        let mut payout = HashMap::new();
+        let mut remaining = balance;
+        let mut owed = None;
+        if let Some(author_id) = author {
+            let amount = balance * u128::from(config.author_bps) / BPS_DENOMINATOR;
+            let account = match author_accounts().get(&author_id) {
+                Some(account) => account,
+                None => {
+                    owed = Some((author_id, amount));
+                    env::current_account_id()
+                }
+            };
+            *payout.entry(account).or_insert(0) += amount;
+            remaining -= amount;
+        }
+        let protocol = balance * u128::from(config.protocol_bps) / BPS_DENOMINATOR;
+        *payout.entry(config.treasury).or_insert(0) += protocol;
+        remaining -= protocol;
+        *payout.entry(seller.clone()).or_insert(0) += remaining;
+
+        let payout: HashMap<AccountId, U128> = payout
+            .into_iter()
+            .filter(|(_, amount)| *amount > 0)
+            .map(|(account, amount)| (account, U128(amount)))
+            .collect();
+        if let Some(max_len_payout) = max_len_payout {
+            require!(
+                payout.len() <= max_len_payout as usize,
+                "Payout is longer than max_len_payout"
+            );
+        }
+        (Payout { payout }, owed)
+    }
+
+    /// Returns the NEP-199 payout of a sale of `token_id` for `balance`.
+    pub fn nft_payout(
+        &self,
+        token_id: TokenId,
+        balance: U128,
+        max_len_payout: Option<u32>,
+    ) -> Payout {
+        let owner_id = self
+            .tokens
+            .owner_by_id
+            .get(&token_id)
+            .expect("Token not found");
+        self.compute_payout(&token_id, &owner_id, balance.0, max_len_payout)
+            .0
+    }
+
+    /// Transfers `token_id` and returns the NEP-199 payout the marketplace should pay out.
+    ///
+    /// When the author has not linked an account, their share is paid to the contract and
+    /// recorded as owed to them. It is credited to their royalty balance when they claim it,
+    /// once the marketplace has paid it.
+    #[payable]
+    pub fn nft_transfer_payout(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+        balance: U128,
+        max_len_payout: Option<u32>,
+    ) -> Payout {
+        near_sdk::assert_one_yocto();
+        let sender_id = env::predecessor_account_id();
+        let (owner_id, _) =
+            self.tokens
+                .internal_transfer(&sender_id, &receiver_id, &token_id, approval_id, memo);
+
+        let (payout, owed) = self.compute_payout(&token_id, &owner_id, balance.0, max_len_payout);
+        if let Some((author_id, amount)) = owed.filter(|(_, amount)| *amount > 0) {
+            let mut owed = owed_royalties();
+            let previous = owed.get(&author_id).unwrap_or(0);
+            owed.insert(&author_id, &(previous + amount));
+        }
+        payout
+    }
+
+    pub fn update_royalty_manager(&mut self, account: AccountId) {
+        self.assert_role(Role::Owner);
+        self.royalty_manager = account
+    }
+
+    /// Grants `role` to `account_id`. Only an owner can grant roles.
+    pub fn grant_role(&mut self, role: Role, account_id: AccountId) {
+        self.assert_role(Role::Owner);
+        if Self::add_role_member(&mut self.roles, role, &account_id) {
+            RoleGranted {
+                role,
+                account: account_id,
+            }
+            .emit();
+        }
+    }
+
+    /// Revokes `role` from `account_id`. Only an owner can revoke roles, and the last owner
+    /// cannot be revoked.
+    pub fn revoke_role(&mut self, role: Role, account_id: AccountId) {
+        self.assert_role(Role::Owner);
+        let mut members = self.role_members(role);
+        require!(
+            role != Role::Owner || members.len() > 1 || !members.contains(&account_id),
+            "Cannot revoke the last owner"
+        );
+        if members.remove(&account_id) {
+            self.roles.insert(&role, &members);
+            RoleRevoked {
+                role,
+                account: account_id,
+            }
+            .emit();
+        }
+    }
+
+    pub fn has_role(&self, role: Role, account_id: AccountId) -> bool {
+        self.role_members(role).contains(&account_id)
+    }
+
+    pub fn get_role_members(&self, role: Role) -> Vec<AccountId> {
+        self.role_members(role).to_vec()
+    }
+
+    /// Stops new mint requests and mints during an incident. Pending requests can still be
+    /// cancelled and refunded.
+    pub fn pause(&mut self) {
+        self.assert_role(Role::Pauser);
+        require!(!self.paused, "Contract is already paused");
+        self.paused = true;
+        ContractPaused {
+            account: env::predecessor_account_id(),
+        }
+        .emit();
+    }
+
+    pub fn unpause(&mut self) {
+        self.assert_role(Role::Pauser);
+        require!(self.paused, "Contract is not paused");
+        self.paused = false;
+        ContractUnpaused {
+            account: env::predecessor_account_id(),
+        }
+        .emit();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    fn role_members(&self, role: Role) -> UnorderedSet<AccountId> {
+        self.roles
+            .get(&role)
+            .unwrap_or_else(|| UnorderedSet::new(StorageKey::RoleMembers { role }))
+    }
+
+    // Returns whether the account was added, `false` if it already held the role.
+    fn add_role_member(
+        roles: &mut LookupMap<Role, UnorderedSet<AccountId>>,
+        role: Role,
+        account_id: &AccountId,
+    ) -> bool {
+        let mut members = roles
+            .get(&role)
+            .unwrap_or_else(|| UnorderedSet::new(StorageKey::RoleMembers { role }));
+        let added = members.insert(account_id);
+        roles.insert(&role, &members);
+        added
+    }
+
+    // Gives the roles to the accounts that held the same authority before roles existed: the
+    // verifier proxy owning the tokens mints and sets prices, and the royalty manager does the
+    // rest.
+    fn grant_initial_roles(
+        roles: &mut LookupMap<Role, UnorderedSet<AccountId>>,
+        verifier: &AccountId,
+        royalty_manager: &AccountId,
+    ) {
+        for role in [Role::PricingAdmin, Role::Minter].iter() {
+            Self::add_role_member(roles, *role, verifier);
+        }
+        for role in [Role::Owner, Role::Treasury, Role::Pauser].iter() {
+            Self::add_role_member(roles, *role, royalty_manager);
+        }
+    }
+
+    fn assert_role(&self, role: Role) {
+        require!(
+            self.has_role(role, env::predecessor_account_id()),
+            format!("Missing role {:?}", role)
+        );
+    }
+
+    fn assert_not_paused(&self) {
+        require!(!self.paused, "Contract is paused");
+    }
+
+    pub fn get_lock_time(&self) -> u64 {
+        self.lock_time
+    }
+
+    #[private]
+    fn claim_funds(
+        &mut self,
+        tweet_id: String,
+        mint_request: MintRequestData,
+        status: MintRequestStatus,
+    ) {
+        self.open_request_escrow(&tweet_id, &mint_request);
+        if status == MintRequestStatus::IsFulfilled {
+            self.ledger
+                .book(|ledger| ledger.debit(Bucket::Escrow, mint_request.claimable_deposit));
+            Promise::new(mint_request.minter.clone()).transfer(mint_request.claimable_deposit);
+            escrowed_requests().remove(&tweet_id);
+            self.insert_request(
+                &tweet_id,
+                &MintRequestData {
+                    minter: mint_request.minter,
+                    lock_time: mint_request.lock_time,
+                    claimable_deposit: 0,
+                    status: MintRequestStatus::IsFulfilled,
+                },
+            );
+        } else if status == MintRequestStatus::Cancelled
+            || status == MintRequestStatus::Unsuccessful
+        {
+            let amount = if status == MintRequestStatus::Cancelled {
+                mint_request.claimable_deposit * 9 / 10 // Transferring 90% of the origin deposit back to the minter.
+            } else {
+                mint_request.claimable_deposit // Else if unsuccessful, transferring 100% of the origin deposit back to the minter.
+            };
+            // The penalty is kept as protocol fees
+            self.ledger.book(|ledger| {
+                ledger.transfer(
+                    Bucket::Escrow,
+                    Bucket::ProtocolFees,
+                    mint_request.claimable_deposit - amount,
+                )?;
+                ledger.debit(Bucket::Escrow, amount)
+            });
+            Promise::new(mint_request.minter.clone()).transfer(amount);
+            self.remove_request(&tweet_id);
+            let event = CancelMintRequest {
+                tweet_id: tweet_id, // You might want to generate a unique ID here
+                account: env::predecessor_account_id(),
+                // TODO: Set the withdraw amount once, and re-use
+                withdraw: amount,
+            };
+            event.emit();
+        }
+    }
+
+    pub fn update_lock_time(&mut self, new_value: u64) -> u64 {
+        self.assert_role(Role::PricingAdmin);
+        self.lock_time = new_value;
+        // Log an event-like message
+        env::log_str(format!("lock_time updated: {}", new_value).as_str());
+        new_value
+    }
+
+    // NOT DENOMINATOR 10e6
+    /// Sets the weights of the metrics, as a new pricing version of the current model.
+    pub fn set_cost_per_metric(&mut self, cost_per_metric: PublicMetric) -> u32 {
+        let model = PricingModel {
+            cost_per_metric,
+            ..self.pricing_model()
+        };
+        self.set_pricing_model(model)
+    }
+
+    /// Adds a pricing model, used by mints from now on, and returns its version.
+    pub fn set_pricing_model(&mut self, model: PricingModel) -> u32 {
+        self.assert_role(Role::PricingAdmin);
+        if let Err(reason) = model.validate() {
+            env::panic_str(reason);
+        }
+        self.pricing_models.push(&model);
+        let version = self.pricing_models.len() as u32;
+        PricingModelSet { version }.emit();
+        version
+    }
+
+    /// Returns the pricing model of `version`, the current one by default.
+    pub fn get_pricing_model(&self, version: Option<u32>) -> Option<PricingModel> {
+        let version = version.unwrap_or(self.pricing_models.len() as u32);
+        version
+            .checked_sub(1)
+            .and_then(|index| self.pricing_models.get(u64::from(index)))
+    }
+
+    pub fn get_pricing_version(&self) -> u32 {
+        self.pricing_models.len() as u32
+    }
+
+    /// Returns the pricing version a token was minted with, `None` for tokens minted before
+    /// pricing was versioned.
+    pub fn get_token_pricing_version(&self, token_id: TokenId) -> Option<u32> {
+        self.token_pricing_versions.get(&token_id)
+    }
+
+    // This function is called internally, and externally.
+    // External calls are used to determine the cost of minting an NFT.
+    pub fn compute_cost(&self, public_metrics: PublicMetric) -> u128 {
+        self.quote(&public_metrics, None).total
+    }
+
+    /// Returns the itemised cost of minting a tweet with the current pricing model.
+    ///
+    /// `created_at` is the time the tweet was posted, as returned by X. Without it, no age
+    /// discount applies.
+    pub fn get_price_quote(
+        &self,
+        public_metrics: PublicMetric,
+        created_at: Option<String>,
+    ) -> PriceQuote {
+        self.quote(&public_metrics, created_at.as_deref())
+    }
+
+    fn pricing_model(&self) -> PricingModel {
+        self.get_pricing_model(None).expect("No pricing model")
+    }
+
+    fn quote(&self, public_metrics: &PublicMetric, created_at: Option<&str>) -> PriceQuote {
+        let age_days = created_at
+            .and_then(pricing::parse_timestamp_ms)
+            .map(|created_at| pricing::age_in_days(created_at, env::block_timestamp_ms()));
+        self.pricing_model().quote(
+            self.pricing_models.len() as u32,
+            self.min_deposit,
+            public_metrics,
+            age_days,
+        )
+    }
+
+    pub fn update_min_deposit(&mut self, min_deposit: Balance) {
+        self.assert_role(Role::PricingAdmin);
+        self.min_deposit = min_deposit;
+    }
+
+    /// Sets the Ethereum address quotes must be signed with. Without one, requests cannot be
+    /// made with a quote.
+    pub fn set_quote_signer(&mut self, address: Option<String>) {
+        self.assert_role(Role::Owner);
+        self.quote_signer = address.map(|address| {
+            quote::parse_hex(&address)
+                .and_then(|address| address.try_into().ok())
+                .unwrap_or_else(|| env::panic_str("Invalid quote signer address"))
+        });
+        QuoteSignerSet {
+            address: self.get_quote_signer(),
+        }
+        .emit();
+    }
+
+    pub fn get_quote_signer(&self) -> Option<String> {
+        self.quote_signer.map(|address| quote::to_hex(&address))
+    }
+
+    /// Sets how much the cost of a quoted request may grow past the quote, in basis points.
+    pub fn set_quote_tolerance(&mut self, tolerance_bps: u16) {
+        self.assert_role(Role::PricingAdmin);
+        require!(
+            u128::from(tolerance_bps) <= BPS_DENOMINATOR,
+            "Tolerance cannot exceed 100%"
+        );
+        self.quote_tolerance_bps = tolerance_bps;
+    }
+
+    pub fn get_quote_tolerance(&self) -> u16 {
+        self.quote_tolerance_bps
+    }
+
+    /// Returns the quote a pending request was made with.
+    pub fn get_request_quote(&self, tweet_id: String) -> Option<MintQuote> {
+        self.request_quotes.get(&tweet_id).map(|quote| MintQuote {
+            author_id: self.request_authors.get(&tweet_id).unwrap_or_default(),
+            ..quote
+        })
+    }
+
+    // Checks that the quote is for this request, signed by the quote signer and not expired.
+    fn verify_quote(&self, tweet_id: &String, signed: SignedMintQuote) -> MintQuote {
+        let signer = self
+            .quote_signer
+            .unwrap_or_else(|| env::panic_str("Quotes are not accepted"));
+        require!(
+            signed.signer(&env::current_account_id()) == Some(signer),
+            "Invalid quote signature"
+        );
+        let quote = signed.quote;
+        require!(&quote.tweet_id == tweet_id, "Quote is for another tweet");
+        require!(
+            quote.minter == env::predecessor_account_id(),
+            "Quote is for another minter"
+        );
+        require!(
+            env::block_timestamp_ms() <= quote.expires_at_ms,
+            "Quote has expired"
+        );
+        require!(
+            quote.price.0 >= self.min_deposit,
+            "Quoted price is below the minimum deposit"
+        );
+        quote
+    }
+}
+
+near_contract_standards::impl_non_fungible_token_core!(Contract, tokens);
+near_contract_standards::impl_non_fungible_token_approval!(Contract, tokens);
+near_contract_standards::impl_non_fungible_token_enumeration!(Contract, tokens);
+
+#[near_bindgen]
+impl NonFungibleTokenMetadataProvider for Contract {
+    fn nft_metadata(&self) -> NFTContractMetadata {
+        self.metadata.get().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+    use std::collections::HashMap;
+    use std::default;
+    use std::time::SystemTime;
 
     use super::*;
 
@@ -648,7 +1953,13 @@ mod tests {
             .build());
 
         let token_id = "1".to_string();
-        contract.mint_tweet_request(token_id.clone(), format!("ipfs://"), "@xxxxxx".to_owned());
+        contract.mint_tweet_request(
+            token_id.clone(),
+            "1234".to_string(),
+            format!("ipfs://"),
+            "@xxxxxx".to_owned(),
+            None,
+        );
         let _ = contract.nft_mint(
             token_id.clone(),
             accounts(0),
@@ -675,7 +1986,13 @@ mod tests {
             .build());
 
         let token_id = "1".to_string();
-        contract.mint_tweet_request(token_id.clone(), format!("ipfs://"), "@xxxxxx".to_owned());
+        contract.mint_tweet_request(
+            token_id.clone(),
+            "1234".to_string(),
+            format!("ipfs://"),
+            "@xxxxxx".to_owned(),
+            None,
+        );
 
         let balance = env::account_balance();
         let _ = contract.cancel_mint_request(token_id.clone());
@@ -707,7 +2024,13 @@ mod tests {
             .build());
 
         let token_id = "1".to_string();
-        contract.mint_tweet_request(token_id.clone(), format!("ipfs://"), "@xxxxxx".to_owned());
+        contract.mint_tweet_request(
+            token_id.clone(),
+            "1234".to_string(),
+            format!("ipfs://"),
+            "@xxxxxx".to_owned(),
+            None,
+        );
         let token = contract.nft_mint(token_id.clone(), accounts(0), sample_token_metadata(likes));
 
         // duplicated mint
@@ -735,11 +2058,15 @@ mod tests {
         let token_id = "0".to_string();
         contract.mint_tweet_request(
             token_id.clone(),
+            "1234".to_string(),
             format!("ipfs://"),
             "@xxxxxx".to_owned(),
             // get_test_public_metrics(1),
+            None,
         );
-        let token = contract.nft_mint(token_id.clone(), accounts(0), sample_token_metadata(likes));
+        let token = contract
+            .nft_mint(token_id.clone(), accounts(0), sample_token_metadata(likes))
+            .unwrap();
         assert_eq!(token.token_id, token_id);
         assert_eq!(token.owner_id.to_string(), accounts(0).to_string());
         assert_eq!(token.approved_account_ids.unwrap(), HashMap::new());
@@ -749,13 +2076,307 @@ mod tests {
             .attached_deposit(1)
             .predecessor_account_id(accounts(5))
             .build());
+        // The price leaves the escrow, split between the storage reserve, the author and the
+        // protocol
+        let ledger = contract.get_ledger().ledger;
+        let royalty = contract.get_royalty_balance("1234".to_string());
+        assert_eq!(ledger.escrow, 0);
+        assert_eq!(ledger.storage_reserve, contract.min_deposit);
+        assert_eq!(ledger.author_royalties, royalty);
+        assert_eq!(
+            ledger.protocol_fees,
+            deposit - contract.min_deposit - royalty
+        );
+
+        // Only the protocol fees can be withdrawn
         let balance = env::account_balance();
-        contract.royalty_withdraw(deposit * 2 / 10);
-        assert_eq!(balance, env::account_balance() + deposit * 2 / 10);
+        contract.royalty_withdraw(ledger.protocol_fees);
+        assert_eq!(balance, env::account_balance() + ledger.protocol_fees);
+        assert_eq!(contract.get_ledger().ledger.protocol_fees, 0);
     }
 
-    #[test]
-    fn test_get_lock_time() {
+    fn mint_with_royalty(context: &mut VMContextBuilder, contract: &mut Contract) -> Balance {
+        let likes: u128 = 1 as u128;
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.compute_cost(get_test_public_metrics(likes)))
+            .predecessor_account_id(accounts(0))
+            .build());
+        let token_id = "0".to_string();
+        contract.mint_tweet_request(
+            token_id.clone(),
+            "1234".to_string(),
+            format!("ipfs://"),
+            "@xxxxxx".to_owned(),
+            None,
+        );
+        contract.nft_mint(token_id, accounts(0), sample_token_metadata(likes));
+        contract.get_royalty_balance("1234".to_string())
+    }
+
+    #[test]
+    fn test_claim_royalty() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), accounts(5));
+        let royalty = mint_with_royalty(&mut context, &mut contract);
+        assert!(royalty > 0);
+
+        // The verifier proxy links the author once their proof is verified
+        contract.link_author("1234".to_string(), accounts(3), 1);
+        assert_eq!(
+            contract.get_author_account("1234".to_string()),
+            Some(accounts(3))
+        );
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(0)
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.claim_royalty("1234".to_string(), Some(royalty / 4));
+        assert_eq!(
+            contract.get_royalty_balance("1234".to_string()),
+            royalty - royalty / 4
+        );
+
+        contract.claim_royalty("1234".to_string(), None);
+        assert_eq!(contract.get_royalty_balance("1234".to_string()), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the account linked to the author can claim")]
+    fn test_claim_royalty_other_account() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), accounts(5));
+        mint_with_royalty(&mut context, &mut contract);
+        contract.link_author("1234".to_string(), accounts(3), 1);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(4))
+            .build());
+        contract.claim_royalty("1234".to_string(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Missing role")]
+    fn test_link_author_other_user() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), accounts(5));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.link_author("1234".to_string(), accounts(3), 1);
+    }
+
+    #[test]
+    fn test_relink_author() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), accounts(5));
+        contract.link_author("1234".to_string(), accounts(3), 1_000);
+
+        // A proof served after the one of the current link replaces the account
+        contract.link_author("1234".to_string(), accounts(4), 2_000);
+        assert_eq!(
+            contract.get_author_account("1234".to_string()),
+            Some(accounts(4))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "The author is already linked with a newer proof")]
+    fn test_relink_author_older_proof() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), accounts(5));
+        contract.link_author("1234".to_string(), accounts(3), 2_000);
+        contract.link_author("1234".to_string(), accounts(4), 2_000);
+    }
+
+    #[test]
+    fn test_nft_payout() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), accounts(5));
+        mint_with_royalty(&mut context, &mut contract);
+
+        // The author has not linked an account, their share is escrowed by the contract
+        let payout = contract.nft_payout("0".to_string(), U128(10_000), None);
+        assert_eq!(payout.payout.get(&accounts(0)), Some(&U128(500 + 9_250)));
+        assert_eq!(payout.payout.get(&accounts(5)), Some(&U128(250)));
+
+        contract.link_author("1234".to_string(), accounts(3), 1);
+        let payout = contract.nft_payout("0".to_string(), U128(10_000), Some(3));
+        assert_eq!(payout.payout.get(&accounts(3)), Some(&U128(500)));
+        assert_eq!(payout.payout.get(&accounts(5)), Some(&U128(250)));
+        assert_eq!(payout.payout.get(&accounts(0)), Some(&U128(9_250)));
+    }
+
+    #[test]
+    fn test_nft_transfer_payout() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), accounts(5));
+        let royalty = mint_with_royalty(&mut context, &mut contract);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(5))
+            .build());
+        contract.set_payout_config(1_000, 500, accounts(4));
+
+        // The owner sells the token, the contract is moved to accounts(2) to tell its escrow
+        // share apart from the seller's
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(0))
+            .current_account_id(accounts(2))
+            .build());
+        let payout = contract.nft_transfer_payout(
+            accounts(1),
+            "0".to_string(),
+            None,
+            None,
+            U128(10_000),
+            None,
+        );
+        assert_eq!(payout.payout.get(&accounts(2)), Some(&U128(1_000)));
+        assert_eq!(payout.payout.get(&accounts(4)), Some(&U128(500)));
+        assert_eq!(payout.payout.get(&accounts(0)), Some(&U128(8_500)));
+        assert_eq!(
+            contract.nft_token("0".to_string()).unwrap().owner_id,
+            accounts(1)
+        );
+        // The share paid to the contract is owed to the author until they claim it
+        assert_eq!(contract.get_royalty_balance("1234".to_string()), royalty);
+        assert_eq!(contract.get_owed_royalty("1234".to_string()), 1_000);
+        let author_royalties = contract.get_ledger().ledger.author_royalties;
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(0)
+            .predecessor_account_id(accounts(0))
+            .build());
+        contract.link_author("1234".to_string(), accounts(3), 1);
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.claim_royalty("1234".to_string(), Some(royalty));
+        assert_eq!(contract.get_owed_royalty("1234".to_string()), 0);
+        assert_eq!(contract.get_royalty_balance("1234".to_string()), 1_000);
+        assert_eq!(
+            contract.get_ledger().ledger.author_royalties,
+            author_royalties + 1_000 - royalty
+        );
+    }
+
+    #[test]
+    fn test_owed_royalty_unpaid() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), accounts(5));
+        let royalty = mint_with_royalty(&mut context, &mut contract);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(0))
+            .build());
+        contract.nft_transfer_payout(accounts(1), "0".to_string(), None, None, U128(10_000), None);
+        contract.link_author("1234".to_string(), accounts(3), 1);
+
+        // Only the funds of the contract outside of the ledger are credited
+        let ledger = contract.get_ledger();
+        let untracked = 200;
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(0)
+            .account_balance(ledger.storage_cost + ledger.total + untracked)
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.claim_royalty("1234".to_string(), Some(royalty));
+        assert_eq!(
+            contract.get_owed_royalty("1234".to_string()),
+            500 - untracked
+        );
+        assert_eq!(contract.get_royalty_balance("1234".to_string()), untracked);
+    }
+
+    #[test]
+    fn test_enumerate_requests() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), accounts(5));
+        let _ = contract.update_lock_time(0);
+        let deposit = contract.compute_cost(get_test_public_metrics(1));
+
+        for (tweet_id, minter) in [("1", accounts(2)), ("2", accounts(2)), ("3", accounts(3))] {
+            testing_env!(context
+                .storage_usage(env::storage_usage())
+                .attached_deposit(deposit)
+                .predecessor_account_id(minter)
+                .build());
+            contract.mint_tweet_request(
+                tweet_id.to_string(),
+                "1234".to_string(),
+                format!("ipfs://"),
+                format!(""),
+                None,
+            );
+        }
+        assert_eq!(
+            contract.get_request_count_by_status(MintRequestStatus::Created),
+            3
+        );
+        let page = contract.get_requests_by_minter(accounts(2), Some(U128(1)), Some(10));
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].tweet_id, "2");
+
+        // Minted requests move to `IsFulfilled`
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(deposit)
+            .predecessor_account_id(accounts(0))
+            .build());
+        contract.nft_mint("1".to_string(), accounts(2), sample_token_metadata(1));
+        let fulfilled = contract.get_requests_by_status(MintRequestStatus::IsFulfilled, None, None);
+        assert_eq!(fulfilled.len(), 1);
+        assert_eq!(fulfilled[0].tweet_id, "1");
+
+        // Cancelled requests are removed
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.cancel_mint_request("3".to_string());
+        assert!(contract
+            .get_requests_by_minter(accounts(3), None, None)
+            .is_empty());
+
+        let pending: Vec<String> = contract
+            .get_pending_requests(None, None)
+            .into_iter()
+            .map(|request| request.tweet_id)
+            .collect();
+        assert_eq!(pending, vec!["2".to_string()]);
+        assert_eq!(
+            contract
+                .get_requests_by_minter(accounts(2), None, None)
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_get_lock_time() {
         let mut context = get_context(accounts(0));
         testing_env!(context.build());
         let contract = Contract::new_default_meta(accounts(0).into(), accounts(5));
@@ -809,9 +2430,11 @@ mod tests {
         let tweet_id = "XXX4071245224308850";
         let entry = contract.mint_tweet_request(
             tweet_id.to_string(),
+            "1234".to_string(),
             format!("ipfs://"),
             format!(""),
             // get_test_public_metrics(1),
+            None,
         );
         assert_eq!(entry.minter, accounts(3));
     }
@@ -836,9 +2459,11 @@ mod tests {
         let tweet_id = "1834071245224308850";
         let entry = contract.mint_tweet_request(
             tweet_id.to_string(),
+            "1234".to_string(),
             format!("ipfs://"),
             format!(""),
             // get_test_public_metrics(1),
+            None,
         );
         assert_eq!(entry.minter, accounts(3));
         assert_eq!(entry.lock_time, current_time.as_millis() as u64);
@@ -851,9 +2476,11 @@ mod tests {
             .build());
         let entry = contract.mint_tweet_request(
             tweet_id.to_string(),
+            "1234".to_string(),
             format!("ipfs://"),
             format!(""),
             // get_test_public_metrics(1),
+            None,
         );
         assert_eq!(entry.minter, accounts(3));
     }
@@ -878,12 +2505,18 @@ mod tests {
         let tweet_id = "1834071245224308850";
         let entry = contract.mint_tweet_request(
             tweet_id.to_string(),
+            "1234".to_string(),
             format!("ipfs://"),
             format!(""),
             // get_test_public_metrics(1),
+            None,
         );
         assert_eq!(entry.minter, accounts(3));
         assert_eq!(entry.lock_time, current_time.as_millis() as u64);
+        assert_eq!(
+            contract.get_request_image_hash(tweet_id.to_string()),
+            Some(Base64VecU8(env::sha256(b"ipfs://")))
+        );
 
         let offset_sec = 1;
         testing_env!(context
@@ -898,15 +2531,17 @@ mod tests {
 
         let entry = contract.mint_tweet_request(
             tweet_id.to_string(),
+            "1234".to_string(),
             format!("ipfs://"),
             format!(""),
             // get_test_public_metrics(1),
+            None,
         );
         assert_eq!(entry.minter, accounts(4));
     }
 
     #[test]
-    #[should_panic(expected = "NOT OWNER")]
+    #[should_panic(expected = "Missing role")]
     fn test_update_lock_time_other_user() {
         let mut context = get_context(accounts(0));
         testing_env!(context.build());
@@ -951,9 +2586,11 @@ mod tests {
         let token_id = "0".to_string();
         contract.mint_tweet_request(
             token_id.clone(),
+            "1234".to_string(),
             format!("ipfs://"),
             "@xxxxxx".to_owned(),
             // get_test_public_metrics(1),
+            None,
         );
         contract.nft_mint(token_id.clone(), accounts(0), sample_token_metadata(likes));
 
@@ -993,9 +2630,11 @@ mod tests {
         let token_id = "0".to_string();
         contract.mint_tweet_request(
             token_id.clone(),
+            "1234".to_string(),
             format!("ipfs://"),
             "@xxxxxx".to_owned(),
             // get_test_public_metrics(1),
+            None,
         );
         contract.nft_mint(token_id.clone(), accounts(0), sample_token_metadata(1));
 
@@ -1030,9 +2669,11 @@ mod tests {
         let token_id = "0".to_string();
         contract.mint_tweet_request(
             token_id.clone(),
+            "1234".to_string(),
             format!("ipfs://"),
             "@xxxxxx".to_owned(),
             // get_test_public_metrics(1),
+            None,
         );
         contract.nft_mint(token_id.clone(), accounts(0), sample_token_metadata(1));
 
@@ -1074,9 +2715,11 @@ mod tests {
         let token_id = "0".to_string();
         contract.mint_tweet_request(
             token_id.clone(),
+            "1234".to_string(),
             format!("ipfs://"),
             "@xxxxxx".to_owned(),
             // get_test_public_metrics(1),
+            None,
         );
         contract.nft_mint(token_id.clone(), accounts(0), sample_token_metadata(1));
 
@@ -1103,4 +2746,734 @@ mod tests {
             .build());
         assert!(!contract.nft_is_approved(token_id.clone(), accounts(1), Some(1)));
     }
+
+    #[test]
+    fn test_grant_and_revoke_role() {
+        let mut context = get_context(accounts(5));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), accounts(5));
+        assert!(contract.has_role(Role::Minter, accounts(0)));
+        assert_eq!(contract.get_role_members(Role::Owner), vec![accounts(5)]);
+
+        contract.grant_role(Role::PricingAdmin, accounts(3));
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.update_min_deposit(1);
+        assert_eq!(contract.min_deposit, 1);
+
+        testing_env!(context.predecessor_account_id(accounts(5)).build());
+        contract.revoke_role(Role::PricingAdmin, accounts(3));
+        assert!(!contract.has_role(Role::PricingAdmin, accounts(3)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot revoke the last owner")]
+    fn test_revoke_last_owner() {
+        let context = get_context(accounts(5));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), accounts(5));
+        contract.revoke_role(Role::Owner, accounts(5));
+    }
+
+    #[test]
+    fn test_pricing_versions() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), accounts(5));
+        let linear_cost = contract.compute_cost(get_test_public_metrics(1000));
+
+        let logarithmic = PricingModel {
+            curve: PricingCurve::Logarithmic {
+                price_per_point: PRICE_PER_POINT,
+                linear_up_to: 10,
+            },
+            ..contract.get_pricing_model(None).unwrap()
+        };
+        assert_eq!(contract.set_pricing_model(logarithmic.clone()), 2);
+        assert!(contract.compute_cost(get_test_public_metrics(1000)) < linear_cost);
+
+        // Mints record the version they were priced with
+        mint_with_royalty(&mut context, &mut contract);
+        assert_eq!(contract.get_token_pricing_version("0".to_string()), Some(2));
+
+        // Older tweets are discounted
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .block_timestamp(10 * 24 * 60 * 60 * 1000 * 1_000_000)
+            .build());
+        let discounted = PricingModel {
+            age_discount_bps_per_day: 100,
+            max_age_discount_bps: 5_000,
+            ..logarithmic
+        };
+        assert_eq!(contract.set_pricing_model(discounted), 3);
+        let quote = contract.get_price_quote(
+            get_test_public_metrics(1000),
+            Some("1970-01-01T00:00:00.000Z".to_string()),
+        );
+        assert_eq!(quote.pricing_version, 3);
+        assert_eq!(quote.age_days, Some(10));
+        assert_eq!(quote.age_discount, quote.curve_cost / 10);
+        assert_eq!(
+            quote.total,
+            quote.min_deposit + quote.curve_cost - quote.age_discount
+        );
+    }
+
+    #[test]
+    fn test_pause() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), accounts(5));
+        let _ = contract.update_lock_time(0);
+        let deposit = contract.compute_cost(get_test_public_metrics(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(deposit)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.mint_tweet_request(
+            "1".to_string(),
+            "1234".to_string(),
+            format!("ipfs://"),
+            format!(""),
+            None,
+        );
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(0)
+            .predecessor_account_id(accounts(5))
+            .build());
+        contract.pause();
+        assert!(contract.is_paused());
+
+        // Requests can still be cancelled and refunded while paused
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.cancel_mint_request("1".to_string());
+        assert!(contract.get_request("1".to_string()).is_none());
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(5))
+            .build());
+        contract.unpause();
+        assert!(!contract.is_paused());
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract is paused")]
+    fn test_mint_tweet_request_paused() {
+        let mut context = get_context(accounts(5));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), accounts(5));
+        contract.pause();
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.min_deposit)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.mint_tweet_request(
+            "1".to_string(),
+            "1234".to_string(),
+            format!("ipfs://"),
+            format!(""),
+            None,
+        );
+    }
+
+    // Signed with the key 0x1111...11, whose address is `QUOTE_SIGNER`, for contract `alice`
+    const QUOTE_SIGNER: &str = "0x19e7e376e7c213b7e7e7e46cc70a5dd086daff2a";
+    const QUOTE_SIGNATURE: &str = "0xc6fbceaaf8f06a994457ccf9f98e7969840b376b523bbdddf539f405685199975199f1abcf0f87c16559253affb959fd5a2fe8607066d73939014b7bb962a6091b";
+    const QUOTED_PRICE: Balance = 1_000_000_000_000_000_000_000_000;
+
+    fn signed_quote() -> SignedMintQuote {
+        SignedMintQuote {
+            quote: MintQuote {
+                tweet_id: "1".to_string(),
+                author_id: "1234".to_string(),
+                minter: accounts(3),
+                public_metric: get_test_public_metrics(1000),
+                price: U128(QUOTED_PRICE),
+                expires_at_ms: 10_000_000_000_000,
+            },
+            signature: QUOTE_SIGNATURE.to_string(),
+        }
+    }
+
+    fn contract_accepting_quotes(context: &mut VMContextBuilder) -> Contract {
+        testing_env!(context.predecessor_account_id(accounts(5)).build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), accounts(5));
+        contract.set_quote_signer(Some(QUOTE_SIGNER.to_string()));
+        assert_eq!(contract.get_quote_signer(), Some(QUOTE_SIGNER.to_string()));
+        contract
+    }
+
+    #[test]
+    fn test_mint_with_quote() {
+        let mut context = get_context(accounts(0));
+        let mut contract = contract_accepting_quotes(&mut context);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(QUOTED_PRICE)
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.mint_tweet_request(
+            "1".to_string(),
+            "1234".to_string(),
+            format!("ipfs://"),
+            format!(""),
+            Some(signed_quote()),
+        );
+        assert_eq!(
+            contract.get_request_quote("1".to_string()),
+            Some(signed_quote().quote)
+        );
+
+        // The tweet grew past the quote within the tolerance, the deposit would not cover it
+        let likes = 1050;
+        assert!(contract.compute_cost(get_test_public_metrics(likes)) > QUOTED_PRICE);
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.min_deposit)
+            .predecessor_account_id(accounts(0))
+            .build());
+        contract.nft_mint("1".to_string(), accounts(3), sample_token_metadata(likes));
+
+        let request = contract.get_request("1".to_string()).unwrap();
+        assert!(request.status == MintRequestStatus::IsFulfilled);
+        assert_eq!(contract.get_request_quote("1".to_string()), None);
+    }
+
+    #[test]
+    fn test_mint_beyond_quote() {
+        let mut context = get_context(accounts(0));
+        let mut contract = contract_accepting_quotes(&mut context);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(QUOTED_PRICE)
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.mint_tweet_request(
+            "1".to_string(),
+            "1234".to_string(),
+            format!("ipfs://"),
+            format!(""),
+            Some(signed_quote()),
+        );
+
+        // The tweet grew past the quote beyond the tolerance, the request is refunded in full
+        let likes = 2000;
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.min_deposit)
+            .predecessor_account_id(accounts(0))
+            .build());
+        let token = contract.nft_mint("1".to_string(), accounts(3), sample_token_metadata(likes));
+        assert!(token.is_none());
+        assert!(contract.nft_token("1".to_string()).is_none());
+        assert!(contract.get_request("1".to_string()).is_none());
+        let ledger = contract.get_ledger().ledger;
+        assert_eq!(ledger.escrow, 0);
+        assert_eq!(ledger.protocol_fees, 0);
+        // The whole deposit is transferred back to the minter
+        let refund = near_sdk::mock::VmAction::Transfer {
+            deposit: QUOTED_PRICE,
+        };
+        assert!(near_sdk::test_utils::get_created_receipts()
+            .iter()
+            .any(
+                |receipt| receipt.receiver_id == accounts(3) && receipt.actions == [refund.clone()]
+            ));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid quote signature")]
+    fn test_mint_tweet_request_tampered_quote() {
+        let mut context = get_context(accounts(0));
+        let mut contract = contract_accepting_quotes(&mut context);
+
+        let mut quote = signed_quote();
+        quote.quote.price = U128(contract.min_deposit);
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.min_deposit)
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.mint_tweet_request(
+            "1".to_string(),
+            "1234".to_string(),
+            format!("ipfs://"),
+            format!(""),
+            Some(quote),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Quote has expired")]
+    fn test_mint_tweet_request_expired_quote() {
+        let mut context = get_context(accounts(0));
+        let mut contract = contract_accepting_quotes(&mut context);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(QUOTED_PRICE)
+            .predecessor_account_id(accounts(3))
+            .block_timestamp((signed_quote().quote.expires_at_ms + 1) * 1_000_000)
+            .build());
+        contract.mint_tweet_request(
+            "1".to_string(),
+            "1234".to_string(),
+            format!("ipfs://"),
+            format!(""),
+            Some(signed_quote()),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Quote is for another author")]
+    fn test_mint_tweet_request_quote_for_other_author() {
+        let mut context = get_context(accounts(0));
+        let mut contract = contract_accepting_quotes(&mut context);
+
+        // The author read by the quote signer is the one whose consent is checked
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(QUOTED_PRICE)
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.mint_tweet_request(
+            "1".to_string(),
+            "5678".to_string(),
+            format!("ipfs://"),
+            format!(""),
+            Some(signed_quote()),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "A signed quote is required")]
+    fn test_mint_tweet_request_without_quote() {
+        let mut context = get_context(accounts(0));
+        let mut contract = contract_accepting_quotes(&mut context);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(QUOTED_PRICE)
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.mint_tweet_request(
+            "1".to_string(),
+            "5678".to_string(),
+            format!("ipfs://"),
+            format!(""),
+            None,
+        );
+    }
+
+    #[test]
+    fn test_auction() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), accounts(5));
+        let bid = contract.min_deposit;
+
+        // The first bid opens the auction
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(bid)
+            .predecessor_account_id(accounts(2))
+            .block_timestamp(0)
+            .build());
+        let auction = contract.bid_tweet_request(
+            "1".to_string(),
+            "1234".to_string(),
+            format!("ipfs://"),
+            format!(""),
+        );
+        assert_eq!(auction.ends_at, contract.get_auction_settings().window);
+        assert_eq!(contract.get_auctions(None, None).len(), 1);
+
+        // A higher bid takes over, and the outbid deposit is refunded
+        let next_bid = auction.min_next_bid(contract.get_auction_settings().min_bid_increment_bps);
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(next_bid)
+            .predecessor_account_id(accounts(3))
+            .build());
+        let auction = contract.bid_tweet_request(
+            "1".to_string(),
+            "1234".to_string(),
+            format!("ipfs://"),
+            format!("@danny"),
+        );
+        assert_eq!(auction.bidder, accounts(3));
+        assert_eq!(auction.bid_count, 2);
+        assert_eq!(contract.get_ledger().ledger.escrow, next_bid);
+        assert_eq!(contract.get_auction("1".to_string()), Some(auction.clone()));
+
+        // Once closed, the highest bid becomes a mint request
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(0)
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(auction.ends_at * 1_000_000)
+            .build());
+        let request = contract.settle_auction("1".to_string());
+        assert_eq!(request.minter, accounts(3));
+        assert_eq!(request.claimable_deposit, next_bid);
+        assert!(contract.get_auction("1".to_string()).is_none());
+        assert_eq!(
+            contract.get_request_count_by_status(MintRequestStatus::Created),
+            1
+        );
+    }
+
+    #[test]
+    fn test_settle_auction_paused() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), accounts(5));
+        let _ = contract.update_lock_time(0);
+        let bid = contract.min_deposit;
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(bid)
+            .predecessor_account_id(accounts(2))
+            .block_timestamp(0)
+            .build());
+        let auction = contract.bid_tweet_request(
+            "1".to_string(),
+            "1234".to_string(),
+            format!("ipfs://"),
+            format!(""),
+        );
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(0)
+            .predecessor_account_id(accounts(5))
+            .build());
+        contract.pause();
+
+        // The highest bid is not stuck while paused: it is settled, then cancelled and refunded
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(2))
+            .block_timestamp(auction.ends_at * 1_000_000)
+            .build());
+        let request = contract.settle_auction("1".to_string());
+        assert_eq!(request.claimable_deposit, bid);
+        contract.cancel_mint_request("1".to_string());
+        assert!(contract.get_request("1".to_string()).is_none());
+        assert_eq!(contract.get_ledger().ledger.escrow, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "ProtocolFees balance of 0 cannot cover")]
+    fn test_royalty_withdraw_escrow() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), accounts(5));
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.min_deposit)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.mint_tweet_request(
+            "1".to_string(),
+            "1234".to_string(),
+            format!("ipfs://"),
+            format!(""),
+            None,
+        );
+
+        // The deposit of the pending request is escrowed
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(0)
+            .predecessor_account_id(accounts(5))
+            .build());
+        contract.royalty_withdraw(contract.min_deposit);
+    }
+
+    #[test]
+    #[should_panic(expected = "Bid must be at least")]
+    fn test_auction_low_bid() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), accounts(5));
+        let bid = contract.min_deposit;
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(bid)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.bid_tweet_request(
+            "1".to_string(),
+            "1234".to_string(),
+            format!("ipfs://"),
+            format!(""),
+        );
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.bid_tweet_request(
+            "1".to_string(),
+            "1234".to_string(),
+            format!("ipfs://"),
+            format!(""),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "This tweet_id has a lock on it")]
+    fn test_mint_tweet_request_during_auction() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), accounts(5));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.min_deposit)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.bid_tweet_request(
+            "1".to_string(),
+            "1234".to_string(),
+            format!("ipfs://"),
+            format!(""),
+        );
+        contract.mint_tweet_request(
+            "1".to_string(),
+            "1234".to_string(),
+            format!("ipfs://"),
+            format!(""),
+            None,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Auction is open until")]
+    fn test_settle_open_auction() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), accounts(5));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.min_deposit)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.bid_tweet_request(
+            "1".to_string(),
+            "1234".to_string(),
+            format!("ipfs://"),
+            format!(""),
+        );
+        contract.settle_auction("1".to_string());
+    }
+
+    // A tweet posted at 2024-11-17 16:26:14 UTC, by author "1234" in the sample metadata.
+    const CONSENT_TWEET_ID: &str = "1858184885493485672";
+
+    fn reserve_tweets(context: &mut VMContextBuilder, contract: &mut Contract) -> u64 {
+        contract.link_author("1234".to_string(), accounts(3), 1);
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.set_author_consent("1234".to_string(), false, 60 * 60 * 1000);
+        contract
+            .get_author_consent("1234".to_string())
+            .unwrap()
+            .reserved_until(CONSENT_TWEET_ID)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_author_reservation() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), accounts(5));
+        let reserved_until = reserve_tweets(&mut context, &mut contract);
+
+        // The author can mint their tweet during the reservation
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.min_deposit)
+            .predecessor_account_id(accounts(3))
+            .block_timestamp((reserved_until - 1) * 1_000_000)
+            .build());
+        let entry = contract.mint_tweet_request(
+            CONSENT_TWEET_ID.to_string(),
+            "1234".to_string(),
+            format!("ipfs://"),
+            format!(""),
+            None,
+        );
+        assert_eq!(entry.minter, accounts(3));
+        assert_eq!(
+            contract.get_request_author(CONSENT_TWEET_ID.to_string()),
+            Some("1234".to_string())
+        );
+
+        // Anyone can once it has passed
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.min_deposit)
+            .predecessor_account_id(accounts(2))
+            .block_timestamp(reserved_until * 1_000_000)
+            .build());
+        let entry = contract.mint_tweet_request(
+            "1858184885493485673".to_string(),
+            "1234".to_string(),
+            format!("ipfs://"),
+            format!(""),
+            None,
+        );
+        assert_eq!(entry.minter, accounts(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "The author of this tweet has reserved its mint until")]
+    fn test_mint_tweet_request_during_reservation() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), accounts(5));
+        let reserved_until = reserve_tweets(&mut context, &mut contract);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.min_deposit)
+            .predecessor_account_id(accounts(2))
+            .block_timestamp((reserved_until - 1) * 1_000_000)
+            .build());
+        contract.mint_tweet_request(
+            CONSENT_TWEET_ID.to_string(),
+            "1234".to_string(),
+            format!("ipfs://"),
+            format!(""),
+            None,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "The author of this tweet has opted out of minting")]
+    fn test_mint_tweet_request_opted_out() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), accounts(5));
+        contract.link_author("1234".to_string(), accounts(3), 1);
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.set_author_consent("1234".to_string(), true, 0);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.min_deposit)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.bid_tweet_request(
+            "1".to_string(),
+            "1234".to_string(),
+            format!("ipfs://"),
+            format!(""),
+        );
+    }
+
+    #[test]
+    fn test_mint_other_author() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), accounts(5));
+        let deposit = contract.compute_cost(get_test_public_metrics(1));
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(deposit)
+            .predecessor_account_id(accounts(0))
+            .build());
+        contract.mint_tweet_request(
+            "0".to_string(),
+            "5678".to_string(),
+            format!("ipfs://"),
+            format!(""),
+            None,
+        );
+
+        // The request is cancelled with a penalty rather than reverted
+        let token = contract.nft_mint("0".to_string(), accounts(0), sample_token_metadata(1));
+        assert!(token.is_none());
+        assert!(contract.nft_token("0".to_string()).is_none());
+        assert!(contract.get_request("0".to_string()).is_none());
+        let ledger = contract.get_ledger().ledger;
+        assert_eq!(ledger.escrow, 0);
+        assert_eq!(ledger.protocol_fees, deposit - deposit * 9 / 10);
+    }
+
+    #[test]
+    fn test_mint_after_opt_out() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), accounts(5));
+        let deposit = contract.compute_cost(get_test_public_metrics(1));
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(deposit)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.mint_tweet_request(
+            "0".to_string(),
+            "1234".to_string(),
+            format!("ipfs://"),
+            format!(""),
+            None,
+        );
+
+        // The author opts out before the tweet is minted
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(0)
+            .predecessor_account_id(accounts(0))
+            .build());
+        contract.link_author("1234".to_string(), accounts(3), 1);
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.set_author_consent("1234".to_string(), true, 0);
+
+        // The request is refunded in full
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(0))
+            .build());
+        let token = contract.nft_mint("0".to_string(), accounts(2), sample_token_metadata(1));
+        assert!(token.is_none());
+        assert!(contract.get_request("0".to_string()).is_none());
+        let ledger = contract.get_ledger().ledger;
+        assert_eq!(ledger.escrow, 0);
+        assert_eq!(ledger.protocol_fees, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the account linked to the author can set its consent")]
+    fn test_set_author_consent_other_account() {
+        let context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), accounts(5));
+        contract.link_author("1234".to_string(), accounts(3), 1);
+        contract.set_author_consent("1234".to_string(), true, 0);
+    }
 }
@@ -0,0 +1,793 @@
+/*!
+Versions of the contract state, read by `Contract::migrate` when a new version is deployed.
+
+Every layout the contract has been deployed with is kept here, oldest first. Changing the
+fields of `Contract` means freezing its current layout into a new `ContractVn`, adding it to
+`VersionedContract` and bumping `STATE_VERSION`.
+*/
+use crate::{
+    escrowed_requests, ledger_opened_at, AuctionSettings, AuthorConsent, Contract, Ledger,
+    MintQuote, MintRequestData, MintRequestStatus, PricingModel, PublicMetric, Role, StorageKey,
+    TweetAuction, DEFAULT_QUOTE_TOLERANCE_BPS,
+};
+use near_contract_standards::non_fungible_token::metadata::NFTContractMetadata;
+use near_contract_standards::non_fungible_token::NonFungibleToken;
+use near_contract_standards::non_fungible_token::TokenId;
+use near_sdk::borsh::{self, BorshDeserialize};
+use near_sdk::collections::{LazyOption, LookupMap, UnorderedMap, UnorderedSet, Vector};
+use near_sdk::{env, AccountId, Balance};
+
+/// The version of the layout of `Contract`.
+pub const STATE_VERSION: u8 = 9;
+
+// The key near-sdk keeps the contract struct under.
+const STATE_KEY: &[u8] = b"STATE";
+
+// The version the state was written with. Missing for contracts deployed before it was stored.
+pub(crate) fn state_version() -> LazyOption<u8> {
+    LazyOption::new(StorageKey::StateVersion, None)
+}
+
+/// The state as originally deployed, before the requests were indexed.
+#[derive(BorshDeserialize)]
+pub(crate) struct ContractV1 {
+    tokens: NonFungibleToken,
+    metadata: LazyOption<NFTContractMetadata>,
+    tweet_requests: LookupMap<String, MintRequestData>,
+    lock_time: u64,
+    min_deposit: Balance,
+    price_per_point: Balance,
+    cost_per_metric: PublicMetric,
+    royalty_manager: AccountId,
+    royalty_balances: LookupMap<String, Balance>,
+}
+
+/// The state with the requests indexed, before roles and pausing.
+#[derive(BorshDeserialize)]
+pub(crate) struct ContractV2 {
+    tokens: NonFungibleToken,
+    metadata: LazyOption<NFTContractMetadata>,
+    tweet_requests: LookupMap<String, MintRequestData>,
+    lock_time: u64,
+    min_deposit: Balance,
+    price_per_point: Balance,
+    cost_per_metric: PublicMetric,
+    royalty_manager: AccountId,
+    royalty_balances: LookupMap<String, Balance>,
+    requests_by_status: LookupMap<MintRequestStatus, UnorderedSet<String>>,
+    requests_by_minter: LookupMap<AccountId, UnorderedSet<String>>,
+}
+
+impl From<ContractV1> for ContractV2 {
+    // The indexes start empty, as existing requests cannot be listed from `tweet_requests`.
+    // They are added with `index_requests`.
+    fn from(old: ContractV1) -> Self {
+        Self {
+            tokens: old.tokens,
+            metadata: old.metadata,
+            tweet_requests: old.tweet_requests,
+            lock_time: old.lock_time,
+            min_deposit: old.min_deposit,
+            price_per_point: old.price_per_point,
+            cost_per_metric: old.cost_per_metric,
+            royalty_manager: old.royalty_manager,
+            royalty_balances: old.royalty_balances,
+
+            requests_by_status: LookupMap::new(StorageKey::RequestsByStatus),
+            requests_by_minter: LookupMap::new(StorageKey::RequestsByMinter),
+        }
+    }
+}
+
+/// The state with roles and pausing, before pricing models.
+#[derive(BorshDeserialize)]
+pub(crate) struct ContractV3 {
+    tokens: NonFungibleToken,
+    metadata: LazyOption<NFTContractMetadata>,
+    tweet_requests: LookupMap<String, MintRequestData>,
+    lock_time: u64,
+    min_deposit: Balance,
+    price_per_point: Balance,
+    cost_per_metric: PublicMetric,
+    royalty_manager: AccountId,
+    royalty_balances: LookupMap<String, Balance>,
+    requests_by_status: LookupMap<MintRequestStatus, UnorderedSet<String>>,
+    requests_by_minter: LookupMap<AccountId, UnorderedSet<String>>,
+    roles: LookupMap<Role, UnorderedSet<AccountId>>,
+    paused: bool,
+}
+
+impl From<ContractV2> for ContractV3 {
+    // The roles go to the accounts that had the same authority before.
+    fn from(old: ContractV2) -> Self {
+        let mut roles = LookupMap::new(StorageKey::Roles);
+        Contract::grant_initial_roles(&mut roles, &old.tokens.owner_id, &old.royalty_manager);
+        Self {
+            tokens: old.tokens,
+            metadata: old.metadata,
+            tweet_requests: old.tweet_requests,
+            lock_time: old.lock_time,
+            min_deposit: old.min_deposit,
+            price_per_point: old.price_per_point,
+            cost_per_metric: old.cost_per_metric,
+            royalty_manager: old.royalty_manager,
+            royalty_balances: old.royalty_balances,
+            requests_by_status: old.requests_by_status,
+            requests_by_minter: old.requests_by_minter,
+
+            roles,
+            paused: false,
+        }
+    }
+}
+
+impl From<ContractV3> for ContractV4 {
+    // The prices become the first, linear, pricing model.
+    fn from(old: ContractV3) -> Self {
+        let mut pricing_models = Vector::new(StorageKey::PricingModels);
+        pricing_models.push(&PricingModel::linear(
+            old.cost_per_metric,
+            old.price_per_point,
+        ));
+        Self {
+            tokens: old.tokens,
+            metadata: old.metadata,
+            tweet_requests: old.tweet_requests,
+            lock_time: old.lock_time,
+            min_deposit: old.min_deposit,
+            royalty_manager: old.royalty_manager,
+            royalty_balances: old.royalty_balances,
+            requests_by_status: old.requests_by_status,
+            requests_by_minter: old.requests_by_minter,
+            roles: old.roles,
+            paused: old.paused,
+
+            pricing_models,
+            token_pricing_versions: LookupMap::new(StorageKey::TokenPricingVersions),
+        }
+    }
+}
+
+/// The state with pricing models, before signed quotes.
+#[derive(BorshDeserialize)]
+pub(crate) struct ContractV4 {
+    tokens: NonFungibleToken,
+    metadata: LazyOption<NFTContractMetadata>,
+    tweet_requests: LookupMap<String, MintRequestData>,
+    lock_time: u64,
+    min_deposit: Balance,
+    pricing_models: Vector<PricingModel>,
+    token_pricing_versions: LookupMap<TokenId, u32>,
+    royalty_manager: AccountId,
+    royalty_balances: LookupMap<String, Balance>,
+    requests_by_status: LookupMap<MintRequestStatus, UnorderedSet<String>>,
+    requests_by_minter: LookupMap<AccountId, UnorderedSet<String>>,
+    roles: LookupMap<Role, UnorderedSet<AccountId>>,
+    paused: bool,
+}
+
+impl From<ContractV4> for ContractV5 {
+    // Quotes are not accepted until an owner sets the quote signer.
+    fn from(old: ContractV4) -> Self {
+        Self {
+            tokens: old.tokens,
+            metadata: old.metadata,
+            tweet_requests: old.tweet_requests,
+            lock_time: old.lock_time,
+            min_deposit: old.min_deposit,
+            pricing_models: old.pricing_models,
+            token_pricing_versions: old.token_pricing_versions,
+            royalty_manager: old.royalty_manager,
+            royalty_balances: old.royalty_balances,
+            requests_by_status: old.requests_by_status,
+            requests_by_minter: old.requests_by_minter,
+            roles: old.roles,
+            paused: old.paused,
+
+            quote_signer: None,
+            quote_tolerance_bps: DEFAULT_QUOTE_TOLERANCE_BPS,
+            request_quotes: LookupMap::new(StorageKey::RequestQuotes),
+        }
+    }
+}
+
+/// The state with signed quotes, before auctions.
+#[derive(BorshDeserialize)]
+pub(crate) struct ContractV5 {
+    tokens: NonFungibleToken,
+    metadata: LazyOption<NFTContractMetadata>,
+    tweet_requests: LookupMap<String, MintRequestData>,
+    lock_time: u64,
+    min_deposit: Balance,
+    pricing_models: Vector<PricingModel>,
+    token_pricing_versions: LookupMap<TokenId, u32>,
+    royalty_manager: AccountId,
+    royalty_balances: LookupMap<String, Balance>,
+    requests_by_status: LookupMap<MintRequestStatus, UnorderedSet<String>>,
+    requests_by_minter: LookupMap<AccountId, UnorderedSet<String>>,
+    roles: LookupMap<Role, UnorderedSet<AccountId>>,
+    paused: bool,
+    quote_signer: Option<[u8; 20]>,
+    quote_tolerance_bps: u16,
+    request_quotes: LookupMap<String, MintQuote>,
+}
+
+impl From<ContractV5> for ContractV6 {
+    fn from(old: ContractV5) -> Self {
+        Self {
+            tokens: old.tokens,
+            metadata: old.metadata,
+            tweet_requests: old.tweet_requests,
+            lock_time: old.lock_time,
+            min_deposit: old.min_deposit,
+            pricing_models: old.pricing_models,
+            token_pricing_versions: old.token_pricing_versions,
+            royalty_manager: old.royalty_manager,
+            royalty_balances: old.royalty_balances,
+            requests_by_status: old.requests_by_status,
+            requests_by_minter: old.requests_by_minter,
+            roles: old.roles,
+            paused: old.paused,
+            quote_signer: old.quote_signer,
+            quote_tolerance_bps: old.quote_tolerance_bps,
+            request_quotes: old.request_quotes,
+
+            auctions: UnorderedMap::new(StorageKey::Auctions),
+            auction_settings: AuctionSettings::default(),
+        }
+    }
+}
+
+/// The state with auctions, before author consent.
+#[derive(BorshDeserialize)]
+pub(crate) struct ContractV6 {
+    tokens: NonFungibleToken,
+    metadata: LazyOption<NFTContractMetadata>,
+    tweet_requests: LookupMap<String, MintRequestData>,
+    lock_time: u64,
+    min_deposit: Balance,
+    pricing_models: Vector<PricingModel>,
+    token_pricing_versions: LookupMap<TokenId, u32>,
+    royalty_manager: AccountId,
+    royalty_balances: LookupMap<String, Balance>,
+    requests_by_status: LookupMap<MintRequestStatus, UnorderedSet<String>>,
+    requests_by_minter: LookupMap<AccountId, UnorderedSet<String>>,
+    roles: LookupMap<Role, UnorderedSet<AccountId>>,
+    paused: bool,
+    quote_signer: Option<[u8; 20]>,
+    quote_tolerance_bps: u16,
+    request_quotes: LookupMap<String, MintQuote>,
+    auctions: UnorderedMap<String, TweetAuction>,
+    auction_settings: AuctionSettings,
+}
+
+impl From<ContractV6> for ContractV7 {
+    // Pending requests and open auctions were made without declaring an author. The consent of
+    // the proven author is still checked when they are minted.
+    fn from(old: ContractV6) -> Self {
+        Self {
+            tokens: old.tokens,
+            metadata: old.metadata,
+            tweet_requests: old.tweet_requests,
+            lock_time: old.lock_time,
+            min_deposit: old.min_deposit,
+            pricing_models: old.pricing_models,
+            token_pricing_versions: old.token_pricing_versions,
+            royalty_manager: old.royalty_manager,
+            royalty_balances: old.royalty_balances,
+            requests_by_status: old.requests_by_status,
+            requests_by_minter: old.requests_by_minter,
+            roles: old.roles,
+            paused: old.paused,
+            quote_signer: old.quote_signer,
+            quote_tolerance_bps: old.quote_tolerance_bps,
+            request_quotes: old.request_quotes,
+            auctions: old.auctions,
+            auction_settings: old.auction_settings,
+
+            author_consents: LookupMap::new(StorageKey::AuthorConsents),
+            request_authors: LookupMap::new(StorageKey::RequestAuthors),
+        }
+    }
+}
+
+/// The state with author consent, before the ledger.
+#[derive(BorshDeserialize)]
+pub(crate) struct ContractV7 {
+    tokens: NonFungibleToken,
+    metadata: LazyOption<NFTContractMetadata>,
+    tweet_requests: LookupMap<String, MintRequestData>,
+    lock_time: u64,
+    min_deposit: Balance,
+    pricing_models: Vector<PricingModel>,
+    token_pricing_versions: LookupMap<TokenId, u32>,
+    royalty_manager: AccountId,
+    royalty_balances: LookupMap<String, Balance>,
+    requests_by_status: LookupMap<MintRequestStatus, UnorderedSet<String>>,
+    requests_by_minter: LookupMap<AccountId, UnorderedSet<String>>,
+    roles: LookupMap<Role, UnorderedSet<AccountId>>,
+    paused: bool,
+    quote_signer: Option<[u8; 20]>,
+    quote_tolerance_bps: u16,
+    request_quotes: LookupMap<String, MintQuote>,
+    auctions: UnorderedMap<String, TweetAuction>,
+    auction_settings: AuctionSettings,
+    author_consents: LookupMap<String, AuthorConsent>,
+    request_authors: LookupMap<String, String>,
+}
+
+impl From<ContractV7> for ContractV8 {
+    // The escrow is opened with the deposits of the indexed pending requests and of the open
+    // auctions. The deposits of other requests made before are credited by `index_requests` or
+    // when they are first used, the royalties of authors by `open_royalty_balances` or when they
+    // are first used, and fees with `credit_opening_balances`.
+    fn from(old: ContractV7) -> Self {
+        let mut ledger = Ledger::default();
+        ledger_opened_at().set(&env::block_timestamp_ms());
+        if let Some(pending) = old.requests_by_status.get(&MintRequestStatus::Created) {
+            let mut escrowed = escrowed_requests();
+            for tweet_id in pending.iter() {
+                if let Some(request) = old.tweet_requests.get(&tweet_id) {
+                    ledger.escrow += request.claimable_deposit;
+                    escrowed.insert(&tweet_id);
+                }
+            }
+        }
+        ledger.escrow += old
+            .auctions
+            .values()
+            .map(|auction| auction.deposit)
+            .sum::<Balance>();
+
+        Self {
+            tokens: old.tokens,
+            metadata: old.metadata,
+            tweet_requests: old.tweet_requests,
+            lock_time: old.lock_time,
+            min_deposit: old.min_deposit,
+            pricing_models: old.pricing_models,
+            token_pricing_versions: old.token_pricing_versions,
+            royalty_manager: old.royalty_manager,
+            royalty_balances: old.royalty_balances,
+            requests_by_status: old.requests_by_status,
+            requests_by_minter: old.requests_by_minter,
+            roles: old.roles,
+            paused: old.paused,
+            quote_signer: old.quote_signer,
+            quote_tolerance_bps: old.quote_tolerance_bps,
+            request_quotes: old.request_quotes,
+            auctions: old.auctions,
+            auction_settings: old.auction_settings,
+            author_consents: old.author_consents,
+            request_authors: old.request_authors,
+
+            ledger,
+        }
+    }
+}
+
+/// The state with the ledger, before the image of requests was recorded.
+#[derive(BorshDeserialize)]
+pub(crate) struct ContractV8 {
+    tokens: NonFungibleToken,
+    metadata: LazyOption<NFTContractMetadata>,
+    tweet_requests: LookupMap<String, MintRequestData>,
+    lock_time: u64,
+    min_deposit: Balance,
+    pricing_models: Vector<PricingModel>,
+    token_pricing_versions: LookupMap<TokenId, u32>,
+    royalty_manager: AccountId,
+    royalty_balances: LookupMap<String, Balance>,
+    requests_by_status: LookupMap<MintRequestStatus, UnorderedSet<String>>,
+    requests_by_minter: LookupMap<AccountId, UnorderedSet<String>>,
+    roles: LookupMap<Role, UnorderedSet<AccountId>>,
+    paused: bool,
+    quote_signer: Option<[u8; 20]>,
+    quote_tolerance_bps: u16,
+    request_quotes: LookupMap<String, MintQuote>,
+    auctions: UnorderedMap<String, TweetAuction>,
+    auction_settings: AuctionSettings,
+    author_consents: LookupMap<String, AuthorConsent>,
+    request_authors: LookupMap<String, String>,
+    ledger: Ledger,
+}
+
+impl From<ContractV8> for Contract {
+    // The image of pending requests was only emitted in their event. Until it is recorded with
+    // `record_request_images`, the verifier proxy cannot mint them.
+    fn from(old: ContractV8) -> Self {
+        Self {
+            tokens: old.tokens,
+            metadata: old.metadata,
+            tweet_requests: old.tweet_requests,
+            lock_time: old.lock_time,
+            min_deposit: old.min_deposit,
+            pricing_models: old.pricing_models,
+            token_pricing_versions: old.token_pricing_versions,
+            royalty_manager: old.royalty_manager,
+            royalty_balances: old.royalty_balances,
+            requests_by_status: old.requests_by_status,
+            requests_by_minter: old.requests_by_minter,
+            roles: old.roles,
+            paused: old.paused,
+            quote_signer: old.quote_signer,
+            quote_tolerance_bps: old.quote_tolerance_bps,
+            request_quotes: old.request_quotes,
+            auctions: old.auctions,
+            auction_settings: old.auction_settings,
+            author_consents: old.author_consents,
+            request_authors: old.request_authors,
+            ledger: old.ledger,
+
+            request_images: LookupMap::new(StorageKey::RequestImages),
+        }
+    }
+}
+
+/// The stored state, in whichever layout it was written with.
+pub(crate) enum VersionedContract {
+    V1(ContractV1),
+    V2(ContractV2),
+    V3(ContractV3),
+    V4(ContractV4),
+    V5(ContractV5),
+    V6(ContractV6),
+    V7(ContractV7),
+    V8(ContractV8),
+    V9(Contract),
+}
+
+impl VersionedContract {
+    /// Reads the stored state, using the stored version to pick its layout.
+    ///
+    /// Contracts deployed before the version was stored are either in the original layout, or
+    /// were already migrated to the indexed one. Later versions are always stored. Borsh rejects
+    /// trailing and missing bytes, so the state only deserializes as the layout it was written
+    /// with.
+    pub(crate) fn read() -> Self {
+        let state = env::storage_read(STATE_KEY)
+            .unwrap_or_else(|| env::panic_str("Contract is not initialized"));
+        let version = state_version().get();
+        match version {
+            Some(1) => Self::V1(Self::decode(&state)),
+            Some(2) => Self::V2(Self::decode(&state)),
+            Some(3) => Self::V3(Self::decode(&state)),
+            Some(4) => Self::V4(Self::decode(&state)),
+            Some(5) => Self::V5(Self::decode(&state)),
+            Some(6) => Self::V6(Self::decode(&state)),
+            Some(7) => Self::V7(Self::decode(&state)),
+            Some(8) => Self::V8(Self::decode(&state)),
+            Some(9) => Self::V9(Self::decode(&state)),
+            Some(version) => env::panic_str(&format!("Unknown state version {}", version)),
+            None => ContractV2::try_from_slice(&state)
+                .map(Self::V2)
+                .or_else(|_| ContractV1::try_from_slice(&state).map(Self::V1))
+                .unwrap_or_else(|_| env::panic_str("Unrecognized contract state")),
+        }
+    }
+
+    /// Upgrades the state to the current layout.
+    pub(crate) fn upgrade(self) -> Contract {
+        match self {
+            Self::V1(old) => Self::V2(old.into()).upgrade(),
+            Self::V2(old) => Self::V3(old.into()).upgrade(),
+            Self::V3(old) => Self::V4(old.into()).upgrade(),
+            Self::V4(old) => Self::V5(old.into()).upgrade(),
+            Self::V5(old) => Self::V6(old.into()).upgrade(),
+            Self::V6(old) => Self::V7(old.into()).upgrade(),
+            Self::V7(old) => Self::V8(old.into()).upgrade(),
+            Self::V8(old) => old.into(),
+            Self::V9(contract) => contract,
+        }
+    }
+
+    fn decode<T: BorshDeserialize>(state: &[u8]) -> T {
+        T::try_from_slice(state).unwrap_or_else(|_| env::panic_str("Cannot deserialize the state"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_contract_standards::non_fungible_token::metadata::TokenMetadata;
+    use near_sdk::json_types::Base64VecU8;
+    use near_sdk::serde_json::json;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::{Role, PRICE_PER_POINT};
+
+    fn weights() -> PublicMetric {
+        PublicMetric {
+            bookmark_count: 1,
+            impression_count: 2,
+            like_count: 3,
+            quote_count: 4,
+            reply_count: 5,
+            retweet_count: 6,
+        }
+    }
+
+    // Stores the state of `contract` the way the original deployment did, without the indexes
+    // or a version.
+    fn write_v1(contract: Contract) {
+        let Contract {
+            tokens,
+            metadata,
+            tweet_requests,
+            lock_time,
+            min_deposit,
+            royalty_manager,
+            royalty_balances,
+            ..
+        } = contract;
+        env::state_write(&(
+            tokens,
+            metadata,
+            tweet_requests,
+            lock_time,
+            min_deposit,
+            PRICE_PER_POINT,
+            weights(),
+            royalty_manager,
+            royalty_balances,
+        ));
+        state_version().remove();
+    }
+
+    #[test]
+    fn test_migrate_from_v1() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        let mut contract = Contract::new_default_meta(accounts(0), accounts(5));
+        contract.tweet_requests.insert(
+            &"1".to_string(),
+            &MintRequestData {
+                minter: accounts(2),
+                lock_time: 0,
+                claimable_deposit: 10,
+                status: MintRequestStatus::Created,
+            },
+        );
+        contract.royalty_balances.insert(&"1234".to_string(), &100);
+        write_v1(contract);
+
+        let mut contract = Contract::migrate();
+        assert_eq!(contract.get_state_version(), STATE_VERSION);
+        assert_eq!(contract.get_royalty_balance("1234".to_string()), 100);
+        assert_eq!(
+            contract.get_request("1".to_string()).unwrap().minter,
+            accounts(2)
+        );
+        assert_eq!(contract.royalty_manager, accounts(5));
+        assert_eq!(
+            contract.get_pricing_model(None),
+            Some(PricingModel::linear(weights(), PRICE_PER_POINT))
+        );
+
+        // Requests made before the migration are listed once indexed
+        assert_eq!(
+            contract.get_request_count_by_status(MintRequestStatus::Created),
+            0
+        );
+        assert_eq!(
+            contract.index_requests(vec!["1".to_string(), "2".to_string()]),
+            1
+        );
+        assert_eq!(
+            contract
+                .get_requests_by_minter(accounts(2), None, None)
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_migrate_from_v1_balances() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        let mut contract = Contract::new_default_meta(accounts(0), accounts(5));
+        contract.tweet_requests.insert(
+            &"1".to_string(),
+            &MintRequestData {
+                minter: accounts(2),
+                lock_time: 0,
+                claimable_deposit: 10,
+                status: MintRequestStatus::Created,
+            },
+        );
+        contract.royalty_balances.insert(&"1234".to_string(), &100);
+        write_v1(contract);
+
+        // The balances held before the ledger are read from the state once migrated
+        testing_env!(context.block_timestamp(1_000_000).build());
+        let mut contract = Contract::migrate();
+        assert_eq!(contract.get_ledger().ledger, Ledger::default());
+        assert_eq!(contract.index_requests(vec!["1".to_string()]), 1);
+        assert_eq!(contract.index_requests(vec!["1".to_string()]), 1);
+        assert_eq!(contract.get_ledger().ledger.escrow, 10);
+        assert_eq!(
+            contract.open_royalty_balances(vec!["1234".to_string(), "1234".to_string()]),
+            1
+        );
+        assert_eq!(contract.get_ledger().ledger.author_royalties, 100);
+
+        // The old request can be cancelled and the old royalty claimed
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .block_timestamp(contract.get_lock_time() * 1_000_000)
+            .build());
+        contract.cancel_mint_request("1".to_string());
+        assert!(contract.get_request("1".to_string()).is_none());
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.link_author("1234".to_string(), accounts(3), 1);
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.claim_royalty("1234".to_string(), None);
+        assert_eq!(contract.get_royalty_balance("1234".to_string()), 0);
+        assert_eq!(contract.get_ledger().ledger, Ledger::default());
+    }
+
+    #[test]
+    fn test_migrate_from_v1_unindexed() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        let mut contract = Contract::new_default_meta(accounts(0), accounts(5));
+        let metrics = PublicMetric {
+            bookmark_count: 0,
+            impression_count: 0,
+            like_count: 1,
+            quote_count: 0,
+            reply_count: 0,
+            retweet_count: 0,
+        };
+        let deposit = 10u128.pow(24);
+        for (tweet_id, claimable_deposit) in [("1", 10), ("2", deposit)] {
+            contract.tweet_requests.insert(
+                &tweet_id.to_string(),
+                &MintRequestData {
+                    minter: accounts(2),
+                    lock_time: 0,
+                    claimable_deposit,
+                    status: MintRequestStatus::Created,
+                },
+            );
+        }
+        write_v1(contract);
+        testing_env!(context.block_timestamp(1_000_000).build());
+        let mut contract = Contract::migrate();
+
+        // Requests that were never indexed are credited to the escrow when they are used
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .block_timestamp(contract.get_lock_time() * 1_000_000)
+            .build());
+        contract.cancel_mint_request("1".to_string());
+        assert!(contract.get_request("1".to_string()).is_none());
+        assert_eq!(contract.get_ledger().ledger, Ledger::default());
+
+        // The verifier proxy attaches the storage of the token
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(10u128.pow(22))
+            .build());
+        let extra = json!({
+            "minted_to": accounts(2),
+            "public_metric": metrics,
+            "author_id": "1234",
+        });
+        let token = contract.nft_mint(
+            "2".to_string(),
+            accounts(2),
+            TokenMetadata {
+                title: None,
+                description: None,
+                media: None,
+                media_hash: None,
+                copies: None,
+                issued_at: None,
+                expires_at: None,
+                starts_at: None,
+                updated_at: None,
+                extra: Some(extra.to_string()),
+                reference: None,
+                reference_hash: None,
+            },
+        );
+        assert!(token.is_some());
+        // The price is split between the buckets and the rest of the deposit refunded
+        let ledger = contract.get_ledger().ledger;
+        assert_eq!(ledger.escrow, 0);
+        assert_eq!(ledger.total(), contract.compute_cost(metrics));
+        // Indexing the request later does not credit it again
+        assert_eq!(contract.index_requests(vec!["2".to_string()]), 1);
+        assert_eq!(contract.get_ledger().ledger.escrow, 0);
+    }
+
+    #[test]
+    fn test_migrate_from_v2() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        let mut contract = Contract::new_default_meta(accounts(0), accounts(5));
+        contract.insert_request(
+            &"1".to_string(),
+            &MintRequestData {
+                minter: accounts(2),
+                lock_time: 0,
+                claimable_deposit: 10,
+                status: MintRequestStatus::Created,
+            },
+        );
+        let Contract {
+            tokens,
+            metadata,
+            tweet_requests,
+            lock_time,
+            min_deposit,
+            royalty_manager,
+            royalty_balances,
+            requests_by_status,
+            requests_by_minter,
+            ..
+        } = contract;
+        env::state_write(&(
+            tokens,
+            metadata,
+            tweet_requests,
+            lock_time,
+            min_deposit,
+            PRICE_PER_POINT,
+            weights(),
+            royalty_manager,
+            royalty_balances,
+            requests_by_status,
+            requests_by_minter,
+        ));
+        // The version is not stored by contracts migrated to the indexed layout
+        state_version().remove();
+
+        let mut contract = Contract::migrate();
+        assert_eq!(contract.get_state_version(), STATE_VERSION);
+        assert!(contract.has_role(Role::Minter, accounts(0)));
+        assert!(contract.has_role(Role::Owner, accounts(5)));
+        assert!(!contract.is_paused());
+        assert_eq!(contract.get_pricing_version(), 1);
+        assert_eq!(contract.get_quote_signer(), None);
+        assert_eq!(contract.get_quote_tolerance(), DEFAULT_QUOTE_TOLERANCE_BPS);
+        assert_eq!(contract.get_auction_settings(), AuctionSettings::default());
+        assert_eq!(contract.get_author_consent("1234".to_string()), None);
+        // The escrow holds the deposits of the indexed requests
+        assert_eq!(contract.get_ledger().ledger.escrow, 10);
+
+        // The image of pending requests is recorded once taken from their events
+        assert_eq!(contract.get_request_image_hash("1".to_string()), None);
+        assert_eq!(
+            contract.record_request_images(vec![
+                ("1".to_string(), "ipfs://".to_string()),
+                ("2".to_string(), "ipfs://".to_string()),
+            ]),
+            1
+        );
+        assert_eq!(
+            contract.get_request_image_hash("1".to_string()),
+            Some(Base64VecU8(env::sha256(b"ipfs://")))
+        );
+    }
+
+    #[test]
+    fn test_migrate_current_state() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(5)).build());
+        let mut contract = Contract::new_default_meta(accounts(0), accounts(5));
+        contract.royalty_balances.insert(&"1234".to_string(), &100);
+        contract.grant_role(Role::Pauser, accounts(3));
+        contract.pause();
+        env::state_write(&contract);
+
+        let contract = Contract::migrate();
+        assert_eq!(contract.get_state_version(), STATE_VERSION);
+        assert_eq!(contract.get_royalty_balance("1234".to_string()), 100);
+        assert!(contract.has_role(Role::Pauser, accounts(3)));
+        assert!(contract.is_paused());
+    }
+}
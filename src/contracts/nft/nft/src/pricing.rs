@@ -0,0 +1,514 @@
+/*!
+Pricing models turning the public metrics of a tweet into the cost of minting it.
+
+The metrics are weighted by `cost_per_metric` into points, denominated in 10e6, and the points
+are priced on a curve. The cost of the points can be discounted for older tweets, and capped.
+The minimum deposit is always added on top.
+*/
+use crate::{PublicMetric, BPS_DENOMINATOR};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::Balance;
+use serde::{Deserialize, Serialize};
+
+/// The denominator of `cost_per_metric`, and of points.
+pub const POINTS_DENOMINATOR: u128 = 1_000_000;
+
+/// The largest weight of a metric, so that the points of counts up to `u64::MAX` fit in u128.
+pub const MAX_METRIC_WEIGHT: u128 = 1 << 60;
+
+const MS_PER_DAY: u64 = 24 * 60 * 60 * 1000;
+
+/// A tier of a tiered curve. Points from `from_points` up to the next tier cost
+/// `price_per_point` each.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PricingTier {
+    pub from_points: u128,
+    pub price_per_point: Balance,
+}
+
+/// How points are priced.
+///
+/// * `Linear`: Every point costs `price_per_point`.
+/// * `Logarithmic`: Points cost `price_per_point` up to `linear_up_to`, after which every
+///   doubling of the points adds another `linear_up_to` points, so viral tweets stay affordable.
+/// * `Tiered`: Points are priced at the rate of the tier they fall in.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum PricingCurve {
+    Linear {
+        price_per_point: Balance,
+    },
+    Logarithmic {
+        price_per_point: Balance,
+        linear_up_to: u128,
+    },
+    Tiered {
+        tiers: Vec<PricingTier>,
+    },
+}
+
+/// A pricing model. Every model set is kept, under an increasing version.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PricingModel {
+    // NOTE DENOMINATOR is 10e6
+    pub cost_per_metric: PublicMetric,
+    pub curve: PricingCurve,
+    /// The most the points can cost, before the minimum deposit is added.
+    pub max_metric_cost: Option<Balance>,
+    /// Discount on the cost of the points for every day since the tweet was posted, in basis
+    /// points.
+    pub age_discount_bps_per_day: u16,
+    /// The largest age discount, in basis points.
+    pub max_age_discount_bps: u16,
+}
+
+/// The itemised cost of minting a tweet.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PriceQuote {
+    pub pricing_version: u32,
+    /// The points of each metric, denominated in 10e6.
+    pub points: PublicMetric,
+    pub total_points: u128,
+    /// The cost of the points on the curve.
+    pub curve_cost: Balance,
+    /// Days since the tweet was posted, when known.
+    pub age_days: Option<u64>,
+    pub age_discount: Balance,
+    /// Whether the cost of the points was lowered to `max_metric_cost`.
+    pub capped: bool,
+    pub metric_cost: Balance,
+    pub min_deposit: Balance,
+    pub total: Balance,
+}
+
+impl PricingModel {
+    /// The model the contract was deployed with.
+    pub fn linear(cost_per_metric: PublicMetric, price_per_point: Balance) -> Self {
+        Self {
+            cost_per_metric,
+            curve: PricingCurve::Linear { price_per_point },
+            max_metric_cost: None,
+            age_discount_bps_per_day: 0,
+            max_age_discount_bps: 0,
+        }
+    }
+
+    /// Returns why the model cannot be used, if it cannot.
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if u128::from(self.max_age_discount_bps) > BPS_DENOMINATOR {
+            return Err("Age discount exceeds 100%");
+        }
+        let weights = &self.cost_per_metric;
+        if [
+            weights.bookmark_count,
+            weights.impression_count,
+            weights.like_count,
+            weights.quote_count,
+            weights.reply_count,
+            weights.retweet_count,
+        ]
+        .iter()
+        .any(|weight| *weight > MAX_METRIC_WEIGHT)
+        {
+            return Err("Metric weights cannot exceed 2^60");
+        }
+        match &self.curve {
+            PricingCurve::Linear { .. } => Ok(()),
+            PricingCurve::Logarithmic { linear_up_to, .. } if *linear_up_to == 0 => {
+                Err("Logarithmic curve must be linear up to a positive number of points")
+            }
+            PricingCurve::Logarithmic {
+                price_per_point,
+                linear_up_to,
+            } => {
+                let knee_cost = price_per_point.checked_mul(*linear_up_to);
+                let knee = linear_up_to.checked_mul(POINTS_DENOMINATOR);
+                if knee_cost.is_none() || knee.is_none() {
+                    return Err("Logarithmic curve is linear up to too many points");
+                }
+                Ok(())
+            }
+            PricingCurve::Tiered { tiers } => {
+                if tiers.first().map(|tier| tier.from_points) != Some(0) {
+                    return Err("The first tier must start from 0 points");
+                }
+                if tiers
+                    .windows(2)
+                    .any(|pair| pair[0].from_points >= pair[1].from_points)
+                {
+                    return Err("Tiers must be sorted by increasing points");
+                }
+                if tiers
+                    .iter()
+                    .any(|tier| tier.from_points.checked_mul(POINTS_DENOMINATOR).is_none())
+                {
+                    return Err("Tiers start from too many points");
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Prices the metrics of a tweet posted `age_days` ago.
+    ///
+    /// Points and costs saturate rather than overflow, so even a model that was not validated
+    /// prices every tweet.
+    pub fn quote(
+        &self,
+        pricing_version: u32,
+        min_deposit: Balance,
+        public_metrics: &PublicMetric,
+        age_days: Option<u64>,
+    ) -> PriceQuote {
+        let weights = &self.cost_per_metric;
+        let points = PublicMetric {
+            bookmark_count: weights
+                .bookmark_count
+                .saturating_mul(public_metrics.bookmark_count),
+            impression_count: weights
+                .impression_count
+                .saturating_mul(public_metrics.impression_count),
+            like_count: weights.like_count.saturating_mul(public_metrics.like_count),
+            quote_count: weights
+                .quote_count
+                .saturating_mul(public_metrics.quote_count),
+            reply_count: weights
+                .reply_count
+                .saturating_mul(public_metrics.reply_count),
+            retweet_count: weights
+                .retweet_count
+                .saturating_mul(public_metrics.retweet_count),
+        };
+        let total_points = [
+            points.bookmark_count,
+            points.impression_count,
+            points.like_count,
+            points.quote_count,
+            points.reply_count,
+            points.retweet_count,
+        ]
+        .iter()
+        .fold(0u128, |total, points| total.saturating_add(*points));
+
+        let curve_cost = self.curve_cost(total_points);
+        let discount_bps = age_days
+            .map(|days| {
+                (u128::from(days) * u128::from(self.age_discount_bps_per_day))
+                    .min(u128::from(self.max_age_discount_bps))
+            })
+            .unwrap_or(0);
+        // Split so that a saturated cost can still be discounted
+        let age_discount = curve_cost / BPS_DENOMINATOR * discount_bps
+            + curve_cost % BPS_DENOMINATOR * discount_bps / BPS_DENOMINATOR;
+        let discounted = curve_cost - age_discount;
+        let metric_cost = self
+            .max_metric_cost
+            .map_or(discounted, |max| discounted.min(max));
+
+        let total = min_deposit.saturating_add(metric_cost);
+
+        PriceQuote {
+            pricing_version,
+            points,
+            total_points,
+            curve_cost,
+            age_days,
+            age_discount,
+            capped: metric_cost < discounted,
+            metric_cost,
+            min_deposit,
+            total,
+        }
+    }
+
+    // The cost saturates rather than overflows, it can then never be paid.
+    fn curve_cost(&self, total_points: u128) -> Balance {
+        match &self.curve {
+            PricingCurve::Linear { price_per_point } => {
+                price_per_point.saturating_mul(total_points) / POINTS_DENOMINATOR
+            }
+            PricingCurve::Logarithmic {
+                price_per_point,
+                linear_up_to,
+            } => {
+                let knee = linear_up_to.saturating_mul(POINTS_DENOMINATOR);
+                if total_points <= knee {
+                    price_per_point.saturating_mul(total_points) / POINTS_DENOMINATOR
+                } else {
+                    // linear_up_to * (1 + log2(points / linear_up_to)), the cost of the knee is
+                    // scaled by the doublings a part at a time to stay within u128
+                    let doublings = log2_fixed(total_points, knee);
+                    let knee_cost = price_per_point.saturating_mul(*linear_up_to);
+                    let scaled = (knee_cost / LOG2_ONE)
+                        .saturating_mul(doublings)
+                        .saturating_add(knee_cost % LOG2_ONE * doublings / LOG2_ONE);
+                    knee_cost.saturating_add(scaled)
+                }
+            }
+            PricingCurve::Tiered { tiers } => tiers
+                .iter()
+                .enumerate()
+                .map(|(index, tier)| {
+                    let from = tier.from_points.saturating_mul(POINTS_DENOMINATOR);
+                    let to = tiers.get(index + 1).map_or(total_points, |next| {
+                        next.from_points
+                            .saturating_mul(POINTS_DENOMINATOR)
+                            .min(total_points)
+                    });
+                    tier.price_per_point.saturating_mul(to.saturating_sub(from))
+                        / POINTS_DENOMINATOR
+                })
+                .fold(0, Balance::saturating_add),
+        }
+    }
+}
+
+/// Returns the number of whole days between two timestamps in milliseconds.
+pub fn age_in_days(created_at_ms: u64, now_ms: u64) -> u64 {
+    now_ms.saturating_sub(created_at_ms) / MS_PER_DAY
+}
+
+/// Parses an RFC 3339 UTC timestamp, as X returns in `created_at`, into milliseconds since the
+/// Unix epoch. For example `2024-07-17T10:15:30.000Z`.
+pub fn parse_timestamp_ms(timestamp: &str) -> Option<u64> {
+    let bytes = timestamp.as_bytes();
+    if bytes.len() < 20 || bytes[4] != b'-' || bytes[7] != b'-' || bytes[10] != b'T' {
+        return None;
+    }
+    if bytes[13] != b':' || bytes[16] != b':' || *bytes.last()? != b'Z' {
+        return None;
+    }
+    let number = |from: usize, to: usize| -> Option<u64> { timestamp.get(from..to)?.parse().ok() };
+    let (year, month, day) = (number(0, 4)?, number(5, 7)?, number(8, 10)?);
+    let (hour, minute, second) = (number(11, 13)?, number(14, 16)?, number(17, 19)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+        return None;
+    }
+    let millis = match timestamp.get(19..timestamp.len() - 1)? {
+        "" => 0,
+        fraction if fraction.len() >= 2 && fraction.starts_with('.') => {
+            let digits = &fraction[1..fraction.len().min(4)];
+            digits.parse::<u64>().ok()? * 10u64.pow(3 - digits.len() as u32)
+        }
+        _ => return None,
+    };
+
+    // Days from the civil calendar date, see http://howardhinnant.github.io/date_algorithms.html
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = (era * 146_097 + day_of_era).checked_sub(719_468)?;
+
+    Some(((days * 24 + hour) * 60 + minute) * 60_000 + second * 1000 + millis)
+}
+
+const LOG2_ONE: u128 = 1 << 32;
+
+// log2(numerator / denominator) in 32 bit fixed point, for numerator >= denominator > 0.
+// The numerator is shifted rather than the denominator, so no bits are shifted out.
+fn log2_fixed(numerator: u128, denominator: u128) -> u128 {
+    let mut integer = 0;
+    while integer < 127 && numerator >> (integer + 1) >= denominator {
+        integer += 1;
+    }
+    // Large denominators are scaled down so the fraction below fits in u128
+    let excess = (128 - denominator.leading_zeros()).saturating_sub(94);
+    let (numerator, denominator) = (numerator >> excess, denominator >> excess);
+    // numerator / 2^integer / denominator, in [1, 2)
+    let mut x = if integer >= 32 {
+        (numerator >> (integer - 32)) / denominator
+    } else {
+        (numerator << (32 - integer)) / denominator
+    };
+    let mut result = (integer as u128) << 32;
+    let mut bit = LOG2_ONE >> 1;
+    while bit > 0 {
+        x = x * x >> 32;
+        if x >= 2 * LOG2_ONE {
+            x >>= 1;
+            result += bit;
+        }
+        bit >>= 1;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn likes(like_count: u128) -> PublicMetric {
+        PublicMetric {
+            bookmark_count: 0,
+            impression_count: 0,
+            like_count,
+            quote_count: 0,
+            reply_count: 0,
+            retweet_count: 0,
+        }
+    }
+
+    // A like is worth one point
+    fn model(curve: PricingCurve) -> PricingModel {
+        PricingModel {
+            curve,
+            ..PricingModel::linear(likes(POINTS_DENOMINATOR), 0)
+        }
+    }
+
+    #[test]
+    fn test_parse_timestamp_ms() {
+        assert_eq!(parse_timestamp_ms("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(
+            parse_timestamp_ms("2024-07-17T10:15:30.250Z"),
+            Some(1_721_211_330_250)
+        );
+        assert_eq!(
+            parse_timestamp_ms("2024-02-29T00:00:00.000Z"),
+            Some(1_709_164_800_000)
+        );
+        assert_eq!(parse_timestamp_ms("2024-13-01T00:00:00.000Z"), None);
+        assert_eq!(parse_timestamp_ms("yesterday"), None);
+    }
+
+    #[test]
+    fn test_log2_fixed() {
+        assert_eq!(log2_fixed(1, 1), 0);
+        assert_eq!(log2_fixed(8, 1), 3 * LOG2_ONE);
+        // log2(3) = 1.58496...
+        let log2_3 = log2_fixed(3, 1) * 100_000 / LOG2_ONE;
+        assert_eq!(log2_3, 158_496);
+    }
+
+    #[test]
+    fn test_curves() {
+        let linear = model(PricingCurve::Linear {
+            price_per_point: 10,
+        });
+        assert_eq!(linear.quote(1, 0, &likes(1_000), None).total, 10_000);
+
+        // Linear up to 100 points, then 100 more points per doubling
+        let logarithmic = model(PricingCurve::Logarithmic {
+            price_per_point: 10,
+            linear_up_to: 100,
+        });
+        assert_eq!(logarithmic.quote(1, 0, &likes(50), None).total, 500);
+        assert_eq!(logarithmic.quote(1, 0, &likes(800), None).total, 4_000);
+
+        let tiered = model(PricingCurve::Tiered {
+            tiers: vec![
+                PricingTier {
+                    from_points: 0,
+                    price_per_point: 10,
+                },
+                PricingTier {
+                    from_points: 100,
+                    price_per_point: 1,
+                },
+            ],
+        });
+        assert_eq!(tiered.quote(1, 0, &likes(300), None).total, 1_200);
+    }
+
+    #[test]
+    fn test_logarithmic_curve_bounds() {
+        // The cost of the knee fits in u128, but not once multiplied by the fixed point doublings
+        let logarithmic = model(PricingCurve::Logarithmic {
+            price_per_point: 1 << 60,
+            linear_up_to: 1 << 40,
+        });
+        assert!(logarithmic.validate().is_ok());
+        let quote = logarithmic.quote(1, 0, &likes(1 << 44), None);
+        assert_eq!(quote.curve_cost, (1 << 100) * 5);
+
+        // A cost too large for u128 saturates, and can still be discounted
+        let linear = PricingModel {
+            age_discount_bps_per_day: 1_000,
+            max_age_discount_bps: 1_000,
+            ..model(PricingCurve::Linear {
+                price_per_point: u128::MAX,
+            })
+        };
+        let quote = linear.quote(1, 1, &likes(2), Some(1));
+        assert_eq!(quote.curve_cost, u128::MAX / POINTS_DENOMINATOR);
+        assert_eq!(quote.age_discount, quote.curve_cost / 10);
+
+        // Curves whose knee cannot be priced are rejected
+        let overflowing = model(PricingCurve::Logarithmic {
+            price_per_point: u128::MAX / 2,
+            linear_up_to: 3,
+        });
+        assert!(overflowing.validate().is_err());
+    }
+
+    #[test]
+    fn test_extreme_models() {
+        let extreme = PublicMetric {
+            bookmark_count: u128::MAX,
+            impression_count: u128::MAX,
+            like_count: u128::MAX,
+            quote_count: u128::MAX,
+            reply_count: u128::MAX,
+            retweet_count: u128::MAX,
+        };
+
+        // Weights are bounded, but a model with larger ones still prices every tweet
+        let heavy = PricingModel::linear(extreme, 1);
+        assert_eq!(heavy.validate(), Err("Metric weights cannot exceed 2^60"));
+        let quote = heavy.quote(1, 1, &extreme, None);
+        assert_eq!(quote.total_points, u128::MAX);
+        assert_eq!(quote.total, u128::MAX / POINTS_DENOMINATOR + 1);
+
+        // Tiers starting from more points than can be priced are rejected
+        let tiered = model(PricingCurve::Tiered {
+            tiers: vec![
+                PricingTier {
+                    from_points: 0,
+                    price_per_point: 1,
+                },
+                PricingTier {
+                    from_points: u128::MAX / 2,
+                    price_per_point: u128::MAX,
+                },
+            ],
+        });
+        assert_eq!(tiered.validate(), Err("Tiers start from too many points"));
+        let quote = tiered.quote(1, 0, &extreme, None);
+        assert_eq!(quote.curve_cost, u128::MAX / POINTS_DENOMINATOR);
+
+        // The logarithm of the largest ratios stays within its shifts
+        assert_eq!(log2_fixed(u128::MAX, 1), 128 * LOG2_ONE - 1);
+        assert_eq!(log2_fixed(u128::MAX, u128::MAX / 2) >> 32, 1);
+        let logarithmic = model(PricingCurve::Logarithmic {
+            price_per_point: 1,
+            linear_up_to: 1,
+        });
+        assert!(logarithmic.validate().is_ok());
+        let quote = logarithmic.quote(1, 0, &extreme, None);
+        assert!(quote.curve_cost > 0);
+    }
+
+    #[test]
+    fn test_age_discount_and_cap() {
+        let model = PricingModel {
+            max_metric_cost: Some(5_000),
+            age_discount_bps_per_day: 1_000,
+            max_age_discount_bps: 5_000,
+            ..model(PricingCurve::Linear {
+                price_per_point: 10,
+            })
+        };
+        let quote = model.quote(2, 7, &likes(600), Some(2));
+        assert_eq!(quote.age_discount, 1_200);
+        assert_eq!(quote.metric_cost, 4_800);
+        assert!(!quote.capped);
+        assert_eq!(quote.total, 4_807);
+
+        // The discount stops at 50%, and the cap applies after it
+        let quote = model.quote(2, 7, &likes(2_000), Some(30));
+        assert_eq!(quote.age_discount, 10_000);
+        assert!(quote.capped);
+        assert_eq!(quote.total, 5_007);
+    }
+}
@@ -0,0 +1,167 @@
+/*!
+Price quotes signed off-chain, binding the cost of a mint request to the metrics of the tweet
+when it was requested.
+
+The quote is signed with an Ethereum key, over its `message`, the way `personal_sign` does. A
+request made with a quote is charged the quoted price at mint time, as long as the cost of the
+proven metrics did not grow past the quote by more than the tolerance.
+
+The quote also carries the author of the tweet, read from the tweet by the quote signer, so the
+consent of the author is checked for the proven author before the tweet is locked.
+*/
+use crate::{PublicMetric, BPS_DENOMINATOR};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::{env, AccountId, Balance};
+use serde::{Deserialize, Serialize};
+
+/// The price of minting a tweet, quoted for a minter until `expires_at_ms`.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MintQuote {
+    pub tweet_id: String,
+    /// The X id of the author of the tweet. Not stored with the quote, the request keeps it.
+    #[borsh_skip]
+    pub author_id: String,
+    pub minter: AccountId,
+    /// The metrics of the tweet when it was quoted.
+    pub public_metric: PublicMetric,
+    pub price: U128,
+    pub expires_at_ms: u64,
+}
+
+/// A quote, along with its signature as a hex encoded `r || s || v`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SignedMintQuote {
+    pub quote: MintQuote,
+    pub signature: String,
+}
+
+impl MintQuote {
+    /// The message signed for the quote, bound to the contract it is used with.
+    pub fn message(&self, contract_id: &AccountId) -> String {
+        let metric = &self.public_metric;
+        format!(
+            "x-twitter-nfts mint quote\ncontract: {}\ntweet_id: {}\nauthor_id: {}\nminter: {}\nmetrics: {},{},{},{},{},{}\nprice: {}\nexpires_at_ms: {}",
+            contract_id,
+            self.tweet_id,
+            self.author_id,
+            self.minter,
+            metric.bookmark_count,
+            metric.impression_count,
+            metric.like_count,
+            metric.quote_count,
+            metric.reply_count,
+            metric.retweet_count,
+            self.price.0,
+            self.expires_at_ms,
+        )
+    }
+
+    /// The price charged for a mint costing `cost`, `None` when the cost grew past the price
+    /// by more than `tolerance_bps`. A lower cost is charged as is.
+    pub fn honoured_price(&self, cost: Balance, tolerance_bps: u16) -> Option<Balance> {
+        let price = self.price.0;
+        let limit = price + price * u128::from(tolerance_bps) / BPS_DENOMINATOR;
+        if cost > limit {
+            None
+        } else {
+            Some(cost.min(price))
+        }
+    }
+}
+
+impl SignedMintQuote {
+    /// Recovers the Ethereum address that signed the quote for `contract_id`.
+    pub fn signer(&self, contract_id: &AccountId) -> Option<[u8; 20]> {
+        let signature = parse_hex(&self.signature)?;
+        if signature.len() != 65 {
+            return None;
+        }
+        let v = match signature[64] {
+            v @ 27..=28 => v - 27,
+            v @ 0..=1 => v,
+            _ => return None,
+        };
+        let hash = hash_eth_message(self.quote.message(contract_id).as_bytes());
+        let public_key = env::ecrecover(&hash, &signature[..64], v, true)?;
+        Some(eth_address(&public_key))
+    }
+}
+
+/// Hashes a message the way `personal_sign` does before signing it.
+fn hash_eth_message(message: &[u8]) -> [u8; 32] {
+    let mut eth_message = format!("\x19Ethereum Signed Message:\n{}", message.len()).into_bytes();
+    eth_message.extend_from_slice(message);
+    env::keccak256_array(&eth_message)
+}
+
+/// The Ethereum address of an uncompressed public key, without its prefix.
+fn eth_address(public_key: &[u8; 64]) -> [u8; 20] {
+    let mut address = [0; 20];
+    address.copy_from_slice(&env::keccak256_array(public_key)[12..]);
+    address
+}
+
+/// Parses a hex string, with or without a `0x` prefix.
+pub fn parse_hex(hex: &str) -> Option<Vec<u8>> {
+    let hex = hex.strip_prefix("0x").unwrap_or(hex);
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Encodes bytes as a `0x` prefixed hex string.
+pub fn to_hex(bytes: &[u8]) -> String {
+    let mut hex = String::from("0x");
+    for byte in bytes {
+        hex.push_str(&format!("{:02x}", byte));
+    }
+    hex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quote(price: Balance) -> MintQuote {
+        MintQuote {
+            tweet_id: "1".to_string(),
+            author_id: "1234".to_string(),
+            minter: "minter.near".parse().unwrap(),
+            public_metric: PublicMetric {
+                bookmark_count: 0,
+                impression_count: 0,
+                like_count: 10,
+                quote_count: 0,
+                reply_count: 0,
+                retweet_count: 0,
+            },
+            price: U128(price),
+            expires_at_ms: 1000,
+        }
+    }
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(parse_hex("0x0aff"), Some(vec![10, 255]));
+        assert_eq!(parse_hex("0aff"), Some(vec![10, 255]));
+        assert_eq!(parse_hex("0af"), None);
+        assert_eq!(parse_hex("zz"), None);
+        assert_eq!(to_hex(&[10, 255]), "0x0aff");
+    }
+
+    #[test]
+    fn test_honoured_price() {
+        let quote = quote(1000);
+        assert_eq!(quote.honoured_price(800, 500), Some(800));
+        assert_eq!(quote.honoured_price(1000, 500), Some(1000));
+        // Growth within the tolerance is not charged
+        assert_eq!(quote.honoured_price(1050, 500), Some(1000));
+        assert_eq!(quote.honoured_price(1051, 500), None);
+        assert_eq!(quote.honoured_price(1001, 0), None);
+    }
+}
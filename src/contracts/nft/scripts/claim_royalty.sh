@@ -0,0 +1,7 @@
+# the signer must be the account linked to the author
+export NEAR_CONTRACT_ACCOUNT="x-bitte-nfts.testnet"
+export NEAR_SIGNER_ACCOUNT=author.testnet
+export AUTHOR_ID=1234567890
+
+
+near contract call-function as-transaction $NEAR_CONTRACT_ACCOUNT claim_royalty json-args '{"author_id": "'$AUTHOR_ID'"}' prepaid-gas '30.0 Tgas' attached-deposit '0 NEAR' sign-as $NEAR_SIGNER_ACCOUNT network-config testnet sign-with-keychain send
@@ -0,0 +1,9 @@
+#!/bin/bash
+set -e
+cd "`dirname $0`"/..
+
+# The contract the migration sandbox tests upgrade from, the version currently deployed
+export NEAR_NFT_ACCOUNT_ID=${NEAR_NFT_ACCOUNT_ID:-x-bitte-nfts.testnet}
+export NEAR_NETWORK=${NEAR_NETWORK:-testnet}
+
+near contract download-wasm $NEAR_NFT_ACCOUNT_ID save-to-file integration-tests/rs/wasm/non_fungible_token_deployed.wasm network-config $NEAR_NETWORK now
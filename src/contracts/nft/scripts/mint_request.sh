@@ -2,8 +2,10 @@
 export NEAR_CONTRACT_ACCOUNT="x-bitte-nfts.testnet"
 export NEAR_SIGNER_ACCOUNT=local-verifier.testnet
 export TWEET_ID=1583095539742408706
+# The X id of the author of TWEET_ID, it must match the proven tweet
+export AUTHOR_ID=${AUTHOR_ID:?set AUTHOR_ID to the X id of the author of TWEET_ID}
 export IMAGE_URL="https://ipfs.io/ipfs/QmXLiZP95g8h71QTqzkPvQjKkc52cus4QnkYYbAmnekmRs"
 export NOTIFY="xlassix"
 
 
-near contract call-function as-transaction $NEAR_CONTRACT_ACCOUNT mint_tweet_request json-args '{"tweet_id": "'$TWEET_ID'","image_url":"'$IMAGE_URL'","notify":"'$NOTIFY'"}' prepaid-gas '100.0 Tgas' attached-deposit '10 NEAR' sign-as $NEAR_SIGNER_ACCOUNT network-config testnet sign-with-keychain send
\ No newline at end of file
+near contract call-function as-transaction $NEAR_CONTRACT_ACCOUNT mint_tweet_request json-args '{"tweet_id": "'$TWEET_ID'","author_id":"'$AUTHOR_ID'","image_url":"'$IMAGE_URL'","notify":"'$NOTIFY'"}' prepaid-gas '100.0 Tgas' attached-deposit '10 NEAR' sign-as $NEAR_SIGNER_ACCOUNT network-config testnet sign-with-keychain send
\ No newline at end of file
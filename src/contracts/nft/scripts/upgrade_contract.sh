@@ -2,4 +2,5 @@ export NEAR_VERIFIER_CONTRACT_ACCOUNT=x-bitte-nfts.testnet
 
 cargo build --target wasm32-unknown-unknown --release
 cp target/wasm32-unknown-unknown/release/*.wasm ./target/
-near contract deploy $NEAR_VERIFIER_CONTRACT_ACCOUNT use-file target/non_fungible_token.wasm without-init-call network-config testnet sign-with-keychain send
+# migrate converts the state written by the previous version of the contract
+near contract deploy $NEAR_VERIFIER_CONTRACT_ACCOUNT use-file target/non_fungible_token.wasm with-init-call migrate json-args '{}' prepaid-gas '100.0 Tgas' attached-deposit '0 NEAR' network-config testnet sign-with-keychain send
@@ -0,0 +1,7 @@
+export NEAR_CONTRACT_ACCOUNT=local-verifier.testnet
+# The account being linked must sign the claim itself
+export NEAR_SIGNER_ACCOUNT=author.testnet
+export PROOF=""
+export SIGNATURE=""
+
+near contract call-function as-transaction $NEAR_CONTRACT_ACCOUNT verify_author_claim json-args '{"proof": "'$PROOF'", "signature": "'$SIGNATURE'", "account_id": "'$NEAR_SIGNER_ACCOUNT'"}' prepaid-gas '100.0 Tgas' attached-deposit '0 NEAR' sign-as $NEAR_SIGNER_ACCOUNT network-config testnet sign-with-keychain send
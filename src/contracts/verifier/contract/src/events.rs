@@ -0,0 +1,52 @@
+//! Events logged by the proxy, in the NEP-297 format the NFT contract emits its events with.
+
+use aurora_sdk::near_sdk::{env, AccountId};
+use serde::Serialize;
+use serde_json::json;
+
+/// `batch_item_minted` is emitted when a tweet of a batch is minted.
+#[derive(Serialize, Debug)]
+pub struct BatchItemMinted {
+    /// The Merkle root of the batch, as a hexadecimal string
+    pub root: String,
+    /// The position of the item in the batch
+    pub index: u32,
+    pub tweet_id: String,
+    pub owner: AccountId,
+}
+
+/// `batch_item_failed` is emitted when a tweet of a batch cannot be minted.
+#[derive(Serialize, Debug)]
+pub struct BatchItemFailed {
+    /// The Merkle root of the batch, as a hexadecimal string
+    pub root: String,
+    /// The position of the item in the batch
+    pub index: u32,
+    /// The tweet of the item, unless its proof could not be read
+    pub tweet_id: Option<String>,
+    pub reason: String,
+}
+
+impl BatchItemMinted {
+    pub fn emit(&self) {
+        emit("batch_item_minted", self);
+    }
+}
+
+impl BatchItemFailed {
+    pub fn emit(&self) {
+        emit("batch_item_failed", self);
+    }
+}
+
+fn emit<T: Serialize>(event: &str, data: &T) {
+    env::log_str(&format!(
+        "EVENT_JSON:{}",
+        json!({
+            "standard": "custom",
+            "version": "1.0.0",
+            "event": event,
+            "data": data,
+        })
+    ));
+}
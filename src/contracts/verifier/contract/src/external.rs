@@ -1,15 +1,27 @@
 use aurora_sdk::near_sdk;
+use aurora_sdk::near_sdk::json_types::Base64VecU8;
 use aurora_sdk::near_sdk::{ext_contract, AccountId};
 // Find all our documentation at https://docs.near.org
 use near_contract_standards::non_fungible_token::metadata::TokenMetadata;
 use near_contract_standards::non_fungible_token::TokenId;
+use serde::Deserialize;
 
 pub const NO_DEPOSIT: u128 = 0;
 pub const XCC_SUCCESS: u64 = 1;
 pub const MINIMIM_DEPOSIT: u128 = 5120000000000000000000;
 
+/// The part of a mint request of the NFT contract the proxy checks before minting
+#[derive(Debug, Deserialize)]
+pub struct MintRequest {
+    /// Account that made the request, which the NFT is minted to
+    pub minter: AccountId,
+}
+
 // Validator interface, for cross-contract calls
 #[ext_contract(nft_contract)]
 pub trait NFTContract {
     fn nft_mint(token_id: TokenId, receiver_id: AccountId, token_metadata: TokenMetadata);
+    fn link_author(author_id: String, account_id: AccountId, proven_at: u64);
+    fn get_request(tweet_id: String) -> Option<MintRequest>;
+    fn get_request_image_hash(tweet_id: String) -> Option<Base64VecU8>;
 }
@@ -1,25 +1,254 @@
-use aurora_sdk::near_sdk::{Gas, PromiseError};
+use aurora_sdk::near_sdk::json_types::Base64VecU8;
+use aurora_sdk::near_sdk::{Gas, PromiseError, PromiseResult};
 use aurora_sdk::{
     ecrecover, ethabi, near_sdk, Address, CallArgs, FunctionCallArgsV1, SubmitResult,
     TransactionStatus, H256,
 };
+use http_transcript::{split_transcript, Request, Response};
 use near_contract_standards::non_fungible_token::metadata::TokenMetadata;
 use near_contract_standards::non_fungible_token::Token;
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::{env, near_bindgen, AccountId, PanicOnDefault, Promise};
-use rs_merkle::{algorithms::Sha256, Hasher, MerkleTree};
+use near_sdk::collections::{LazyOption, LookupMap, UnorderedMap};
+use near_sdk::{
+    env, near_bindgen, AccountId, BorshStorageKey, PanicOnDefault, Promise, PromiseOrValue,
+};
+use rs_merkle::{algorithms::Sha256, Hasher, MerkleProof, MerkleTree};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use sha3::{Digest, Keccak256};
 
+pub mod events;
 pub mod external;
+pub mod notaries;
+pub mod replay;
+use crate::events::{BatchItemFailed, BatchItemMinted};
 pub use crate::external::*;
+use crate::notaries::{count_trusted_signers, NotaryKey};
+use crate::replay::ReplaySettings;
 
 /// Selector for `isJournalVerified(bytes)`.
 /// The value is computed by taking the first 4 bytes of the keccak hash of the type
 /// signature for the function, see https://www.4byte.directory/signatures/?bytes4_signature=0xdb3e2198
 const IS_JOURNAL_VERIFIED_SELECTOR: [u8; 4] = [181, 76, 30, 108];
 const DEPOSIT: u128 = 15020000000000000000000;
+/// The host of the X API, proofs of responses from other hosts are rejected.
+const X_API_HOST: &str = "api.x.com";
+/// The path of the lookup of tweets by ID of the X API.
+const TWEETS_PATH: &str = "/2/tweets";
+/// Most tweets minted by a batch, each needs about 30 TGas of the 300 TGas of a transaction.
+const MAX_BATCH_SIZE: usize = 8;
+
+#[derive(BorshSerialize, BorshStorageKey)]
+enum StorageKey {
+    ConsumedHashes,
+    ReplaySettings,
+    NotaryKeys,
+    NotaryThreshold,
+    NotaryRegistryUsed,
+}
+
+// When each consumed proof and journal expires in ms, keyed by their sha256 hash.
+// Kept outside of the contract struct so the existing state does not need to be migrated.
+fn consumed_hashes() -> LookupMap<Vec<u8>, u64> {
+    LookupMap::new(StorageKey::ConsumedHashes)
+}
+
+// Kept outside of the contract struct for the same reason, `None` until it is first set.
+fn replay_settings() -> LazyOption<ReplaySettings> {
+    LazyOption::new(StorageKey::ReplaySettings, None)
+}
+
+// The trusted remote verifier keys, keyed by their address. Until a key is first added, only
+// `ic_remote_public_key` is trusted. Kept outside of the contract struct for the same reason.
+fn notary_keys() -> UnorderedMap<String, NotaryKey> {
+    UnorderedMap::new(StorageKey::NotaryKeys)
+}
+
+// How many distinct trusted keys must sign a proof, `None` until it is first set.
+fn notary_threshold() -> LazyOption<u8> {
+    LazyOption::new(StorageKey::NotaryThreshold, None)
+}
+
+// Set once a key is first added, after which `ic_remote_public_key` is never trusted on its own.
+fn notary_registry_used() -> LazyOption<bool> {
+    LazyOption::new(StorageKey::NotaryRegistryUsed, None)
+}
+
+/// Normalizes an Ethereum address to the lowercase hexadecimal form keys are stored with
+///
+/// # Arguments
+///
+/// * `address` - An address, optionally prefixed with '0x'
+///
+/// # Returns
+///
+/// * `String` - The address, lowercase and without '0x'
+fn normalize_address(address: &str) -> String {
+    aurora_sdk::parse_address(address)
+        .unwrap_or_else(|_| env::panic_str("INVALID ADDRESS"))
+        .encode()
+        .to_lowercase()
+}
+
+/// Checks that a proof or journal is not consumed
+///
+/// # Arguments
+///
+/// * `hash` - The sha256 hash of the proof or journal
+///
+/// # Returns
+///
+/// * `Result<(), String>` - An error if the hash is already consumed
+fn check_unused(hash: &[u8]) -> Result<(), String> {
+    let now = env::block_timestamp_ms();
+    if consumed_hashes()
+        .get(&hash.to_vec())
+        .map_or(false, |until| now < until)
+    {
+        return Err(format!("PROOF ALREADY USED: {}", hex::encode(hash)));
+    }
+    Ok(())
+}
+
+/// Marks a proof or journal as consumed until `expires_at` ms
+///
+/// # Arguments
+///
+/// * `hash` - The sha256 hash of the proof or journal
+/// * `expires_at` - When the hash can be forgotten, in ms
+///
+/// # Returns
+///
+/// * `Result<(), String>` - An error if the hash is already consumed
+fn consume(hash: Vec<u8>, expires_at: u64) -> Result<(), String> {
+    check_unused(&hash)?;
+    consumed_hashes().insert(&hash, &expires_at);
+    Ok(())
+}
+
+/// Checks the owner and the image of a mint against the mint request of the tweet
+///
+/// # Arguments
+///
+/// * `token_metadata` - Metadata of the token to be minted
+/// * `owner_address` - Account ID the proof was submitted for
+/// * `request` - Result of the call fetching the mint request of the tweet
+/// * `image_hash` - Result of the call fetching the hash of the image of the request
+///
+/// # Returns
+///
+/// * `Result<(), String>` - An error if the mint does not match the request
+fn check_request(
+    token_metadata: &TokenMetadata,
+    owner_address: &AccountId,
+    request: Result<Option<MintRequest>, PromiseError>,
+    image_hash: Result<Option<Base64VecU8>, PromiseError>,
+) -> Result<(), String> {
+    let token_id = token_metadata.title.clone().unwrap_or_default();
+    let request = request
+        .ok()
+        .flatten()
+        .ok_or_else(|| format!("No mint request found for {}", token_id))?;
+    if &request.minter != owner_address {
+        return Err(format!(
+            "OWNER MISMATCH: {} was requested by {}",
+            token_id, request.minter
+        ));
+    }
+
+    let image_hash = image_hash
+        .ok()
+        .flatten()
+        .ok_or_else(|| format!("No image recorded for {}", token_id))?;
+    let image_url = token_metadata.media.clone().unwrap_or_default();
+    if image_hash.0 != env::sha256(image_url.as_bytes()) {
+        return Err(format!(
+            "IMAGE MISMATCH: {} was requested with another image",
+            token_id
+        ));
+    }
+    Ok(())
+}
+
+/// A tweet of a batch, proven under the Merkle root signed for the batch
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BatchItem {
+    /// The proof of the tweet, whose sha256 hash is a leaf of the tree
+    pub proof: String,
+    /// The position of the leaf in the tree
+    pub leaf_index: u32,
+    /// The hashes of the siblings from the leaf up to the root, as hexadecimal strings
+    pub inclusion_path: Vec<String>,
+    /// URL of the image associated with the NFT
+    pub image_url: String,
+    /// Account ID of the owner
+    pub owner_address: AccountId,
+}
+
+/// Checks that the proof of an item is a leaf of a Merkle tree
+///
+/// # Arguments
+///
+/// * `root` - The Merkle root of the tree
+/// * `leaf_count` - The number of leaves of the tree
+/// * `item` - The item whose proof is checked
+///
+/// # Returns
+///
+/// * `Result<(), String>` - An error if the proof is not included in the tree
+fn check_inclusion(root: [u8; 32], leaf_count: u32, item: &BatchItem) -> Result<(), String> {
+    let path = item
+        .inclusion_path
+        .iter()
+        .map(|hash| {
+            hex::decode(hash.trim_start_matches("0x"))
+                .ok()
+                .and_then(|hash| <[u8; 32]>::try_from(hash).ok())
+                .ok_or_else(|| format!("Invalid hash in inclusion path: {}", hash))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let leaf = Sha256::hash(item.proof.as_bytes());
+    if item.leaf_index >= leaf_count
+        || !MerkleProof::<Sha256>::new(path).verify(
+            root,
+            &[item.leaf_index as usize],
+            &[leaf],
+            leaf_count as usize,
+        )
+    {
+        return Err("PROOF NOT IN BATCH".to_string());
+    }
+    Ok(())
+}
+
+/// Checks that the items of a batch are leaves of its tree, each with a proof of its own
+///
+/// A proof repeated in a batch is rejected, as only the first of its mints could consume it.
+///
+/// # Arguments
+///
+/// * `root` - The Merkle root of the tree
+/// * `leaf_count` - The number of leaves of the tree
+/// * `items` - The items of the batch
+///
+/// # Returns
+///
+/// * `Vec<Result<(), String>>` - For each item, an error if it cannot be minted with the batch
+fn check_batch(root: [u8; 32], leaf_count: u32, items: &[BatchItem]) -> Vec<Result<(), String>> {
+    let mut checked: Vec<Result<(), String>> = Vec::with_capacity(items.len());
+    for item in items {
+        let repeated = items
+            .iter()
+            .zip(&checked)
+            .any(|(other, result)| result.is_ok() && other.proof == item.proof);
+        checked.push(if repeated {
+            Err("PROOF REPEATED IN BATCH".to_string())
+        } else {
+            check_inclusion(root, leaf_count, item)
+        });
+    }
+    checked
+}
 
 /// The tweet structure obtained from the API
 ///
@@ -49,6 +278,21 @@ pub struct Data {
     pub author_id: String,
     /// Text content of the tweet
     pub text: String,
+    /// Tweets this tweet retweets, quotes or replies to, when requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub referenced_tweets: Option<Vec<ReferencedTweet>>,
+}
+
+/// The ReferencedTweet substructure of a tweet
+///
+/// Contains how a tweet refers to another tweet
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ReferencedTweet {
+    /// How the tweet is referenced: `retweeted`, `quoted` or `replied_to`
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// Unique identifier for the referenced tweet
+    pub id: String,
 }
 
 /// The PublicMetrics substructure of a tweet
@@ -94,6 +338,133 @@ pub struct User {
     pub created_at: String,
 }
 
+/// The user structure obtained from the users API
+///
+/// Used by authors to prove ownership of an account through their bio
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UserProfile {
+    /// Data related to the user
+    pub data: UserProfileData,
+}
+
+/// The data substructure of a user
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UserProfileData {
+    /// Unique identifier for the user
+    pub id: String,
+    /// Bio of the user
+    pub description: String,
+}
+
+/// Parses the X API response contained in a proof
+///
+/// The proof must contain the request, a GET request to the X API for an expected path, so that
+/// responses from other hosts are rejected. The response must be successful and have a JSON
+/// body, whether it was sent in one piece or in chunks.
+///
+/// # Arguments
+///
+/// * `proof` - A string representing the proof
+/// * `is_expected_path` - Whether the request may be for a path of the X API
+///
+/// # Returns
+///
+/// * `Result<Response, String>` - The response, or why the proof is rejected
+pub fn parse_x_response(
+    proof: &str,
+    is_expected_path: impl Fn(&str) -> bool,
+) -> Result<Response<'_>, String> {
+    let (request, response) =
+        split_transcript(proof).map_err(|e| format!("INVALID RESPONSE: {}", e))?;
+    let request = request.ok_or_else(|| "MISSING REQUEST: the proof has no request".to_string())?;
+    let request = Request::parse(request).map_err(|e| format!("INVALID REQUEST: {}", e))?;
+    let url = request
+        .url()
+        .map_err(|e| format!("INVALID REQUEST: {}", e))?;
+    if request.method != "GET"
+        || !url.host.eq_ignore_ascii_case(X_API_HOST)
+        || !is_expected_path(url.path)
+    {
+        return Err(format!(
+            "UNEXPECTED REQUEST: {} {}",
+            request.method, request.target
+        ));
+    }
+
+    let response = Response::parse(response).map_err(|e| format!("INVALID RESPONSE: {}", e))?;
+    response
+        .expect_status(200)
+        .map_err(|e| format!("INVALID RESPONSE: {}", e))?;
+    response
+        .json_body()
+        .map_err(|e| format!("INVALID RESPONSE: {}", e))?;
+    Ok(response)
+}
+
+/// Checks whether a request is a lookup of tweets by ID
+fn is_tweet_lookup(path: &str) -> bool {
+    path == TWEETS_PATH
+}
+
+/// Checks whether a request is a lookup of tweets or of a user, whose bio an author claim can
+/// be made with
+fn is_author_lookup(path: &str) -> bool {
+    path == TWEETS_PATH || path == "/2/users" || path.starts_with("/2/users/")
+}
+
+/// Reads the author and the text an author claim is made with
+///
+/// The JSON is either a tweet, whose text is used, or a user, whose bio is used. Retweets are
+/// rejected, as their text is the words of the retweeted author.
+///
+/// # Arguments
+///
+/// * `json` - The JSON body of the proof
+///
+/// # Returns
+///
+/// * `Result<(String, String), String>` - The X id of the author and the text, or why the JSON
+///   cannot be used to claim
+pub fn parse_author_claim(json: &str) -> Result<(String, String), String> {
+    if let Ok(tweet) = serde_json::from_str::<Tweet>(json) {
+        let data = tweet
+            .data
+            .into_iter()
+            .next()
+            .ok_or_else(|| "No tweet or user found in proof.".to_string())?;
+        let retweeted = data
+            .referenced_tweets
+            .iter()
+            .flatten()
+            .any(|referenced| referenced.kind == "retweeted");
+        if retweeted || data.text.starts_with("RT @") {
+            return Err("Retweets cannot be used to claim an author.".to_string());
+        }
+        return Ok((data.author_id, data.text));
+    }
+    let profile: UserProfile =
+        serde_json::from_str(json).map_err(|_| "No tweet or user found in proof.".to_string())?;
+    Ok((profile.data.id, profile.data.description))
+}
+
+/// Checks whether a text mentions an account ID as a whole word
+///
+/// A longer account that merely contains `account_id`, such as a sub-account, does not match.
+///
+/// # Arguments
+///
+/// * `text` - The text of a tweet or bio
+/// * `account_id` - The account ID to look for
+///
+/// # Returns
+///
+/// * `bool` - True if the account ID is mentioned
+pub fn mentions_account(text: &str, account_id: &str) -> bool {
+    text.split(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_'))
+        .map(|word| word.trim_matches('.'))
+        .any(|word| word.eq_ignore_ascii_case(account_id))
+}
+
 /// Generates a token metadata payload for a tweet NFT
 ///
 /// # Arguments
@@ -104,17 +475,22 @@ pub struct User {
 ///
 /// # Returns
 ///
-/// * `TokenMetadata` - The generated token metadata
+/// * `Result<TokenMetadata, String>` - The generated token metadata, or an error if the JSON is
+///   not a tweet, such as an error of the X API
 pub fn generate_tweet_nft_payload(
     json_tweet: &str,
     image_url: String,
     owner_account_id: String,
-) -> TokenMetadata {
+) -> Result<TokenMetadata, String> {
     env::log_str(&format!("data:{}", json_tweet));
 
     // Deserialize the tweet JSON and extract the first tweet data and its public metrics
-    let tweet: Tweet = serde_json::from_str(json_tweet).unwrap();
-    let tweet_data = tweet.data.get(0).unwrap();
+    let tweet: Tweet =
+        serde_json::from_str(json_tweet).map_err(|_| "No tweet found in proof.".to_string())?;
+    let tweet_data = tweet
+        .data
+        .first()
+        .ok_or_else(|| "No tweet found in proof.".to_string())?;
     let public_metric = &tweet_data.public_metrics;
 
     // Generate a token metadata object
@@ -126,6 +502,7 @@ pub fn generate_tweet_nft_payload(
                "public_metric": public_metric,
                "minted_to":owner_account_id.clone(),
                "author_id":tweet_data.author_id.clone(),
+               "created_at":tweet_data.created_at.clone(),
                "user": (tweet.includes.users.get(0).unwrap_or(&User{
                     name: "".to_string(),
                     id:"".to_string(),
@@ -146,7 +523,7 @@ pub fn generate_tweet_nft_payload(
         reference_hash: None, // Base64-encoded sha256 hash of JSON referenced by the `reference` field
     };
 
-    token_metadata
+    Ok(token_metadata)
 }
 
 /// Converts a hexadecimal string (optionally prefixed with '0x') to a vector of bytes.
@@ -299,10 +676,25 @@ impl VerifierProxy {
     ///
     /// # Returns
     ///
-    /// * `bool` - True if the verification is successful, false otherwise
+    /// * `bool` - True if the proof is signed by a key trusted now, false otherwise
     pub fn ecdsa_verification(&self, proof: String, signature: String) -> bool {
-        let root_hash = hash_eth_message(self.generate_merkle_tree(proof));
-        let signature = string_to_vec_u8(&signature);
+        let signer = self.recover_signer(&self.generate_merkle_tree(proof), &signature);
+        self.is_trusted_signer(&signer, env::block_timestamp_ms())
+    }
+
+    /// Recovers the address that signed a Merkle root
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The Merkle root as a hexadecimal string
+    /// * `signature` - A string representing the signature
+    ///
+    /// # Returns
+    ///
+    /// * `String` - The address of the signer, lowercase and without '0x'
+    fn recover_signer(&self, root: &str, signature: &str) -> String {
+        let root_hash = hash_eth_message(root);
+        let signature = string_to_vec_u8(signature);
         if signature.len() != 65 {
             env::panic_str("INVALID_ETH_SIGNATURE");
         }
@@ -313,70 +705,307 @@ impl VerifierProxy {
         let add = ecrecover(hash, &signature_bytes)
             .ok()
             .expect("successful ecrecover");
-        add.encode().to_lowercase() == self.ic_remote_public_key.encode().to_lowercase()
+        add.encode().to_lowercase()
+    }
+
+    /// Checks whether an address is a key trusted at `now` ms
+    ///
+    /// Until a key is first added to the registry, only `ic_remote_public_key` is trusted.
+    fn is_trusted_signer(&self, signer: &str, now: u64) -> bool {
+        if !notary_registry_used().get().unwrap_or(false) {
+            return signer == self.ic_remote_public_key.encode().to_lowercase();
+        }
+        notary_keys()
+            .get(&signer.to_string())
+            .map_or(false, |key| key.is_valid_at(now))
+    }
+
+    /// Checks that a Merkle root is signed by as many distinct trusted keys as the threshold
+    /// requires
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The Merkle root as a hexadecimal string
+    /// * `signature` - A string representing the signature
+    /// * `co_signatures` - Signatures of the root by other keys
+    fn assert_signed(&self, root: &str, signature: String, co_signatures: Option<Vec<String>>) {
+        let signers: Vec<String> = std::iter::once(signature)
+            .chain(co_signatures.unwrap_or_default())
+            .map(|signature| self.recover_signer(root, &signature))
+            .collect();
+        let now = env::block_timestamp_ms();
+        let signed = count_trusted_signers(&signers, |signer| self.is_trusted_signer(signer, now));
+        let threshold = usize::from(self.get_notary_threshold());
+        if signed < threshold {
+            env::panic_str(&format!(
+                "INVALID PROOF: signed by {} of the {} trusted keys required",
+                signed, threshold
+            ));
+        }
     }
 
     /// Verifies a proof and mints an NFT if the verification is successful
     ///
+    /// The proof is rejected if it was already used, or if the notarized response is older than
+    /// the freshness bound of the replay settings. The owner and the image are not covered by the
+    /// signature, so they are checked against the mint request of the tweet on the NFT contract
+    /// before minting. The proof is only consumed once they match, so a copy of it submitted with
+    /// another owner cannot prevent the mint.
+    ///
     /// # Arguments
     ///
     /// * `proof` - A string representing the proof
     /// * `signature` - A string representing the signature
     /// * `image_url` - URL of the image associated with the NFT
     /// * `owner_address` - Account ID of the owner
+    /// * `co_signatures` - Signatures of the proof by other trusted keys, when the threshold
+    ///   requires several
     ///
     /// # Returns
     ///
     /// * `Promise` - A promise representing the NFT minting operation
     pub fn verify_proof_v2(
-        &self,
+        &mut self,
         proof: String,
         signature: String,
         image_url: String,
         owner_address: AccountId,
+        co_signatures: Option<Vec<String>>,
     ) -> Promise {
-        if self.ecdsa_verification(proof.clone(), signature) {
-            // Find the start of the JSON
-            if let (Some(start), Some(end)) = (proof.rfind("\r\n{"), proof.rfind("}}\r\n")) {
-                let json_str = &proof[start + 2..=end + 1];
-                env::log_str(&format!("json_str: {:?}", json_str));
+        self.assert_signed(
+            &self.generate_merkle_tree(proof.clone()),
+            signature,
+            co_signatures,
+        );
+        let (token_metadata, expires_at) = self
+            .check_proof(&proof, image_url, &owner_address)
+            .unwrap_or_else(|reason| env::panic_str(&reason));
+        env::log_str(&format!("TokenMetadata: {:?}", token_metadata));
 
-                let token_metadata =
-                    generate_tweet_nft_payload(json_str, image_url, owner_address.to_string());
-                env::log_str(&format!("TokenMetadata: {:?}", token_metadata));
+        // Fetch the request of the tweet, the NFT is minted once it is checked
+        let token_id = token_metadata.title.clone().unwrap();
+        let proof_hash = hex::encode(env::sha256(proof.as_bytes()));
+        self.fetch_request(token_id).then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(Gas(20_000_000_000_000))
+                .mint_requested_tweet(token_metadata, owner_address, proof_hash, expires_at),
+        )
+    }
 
-                #[derive(Deserialize)]
-                struct MetadataExtra {
-                    minted_to: AccountId,
+    /// Verifies the proofs of several tweets under a single signed Merkle root, and mints an NFT
+    /// for each valid one
+    ///
+    /// Each proof is checked like in `verify_proof_v2`. An item that fails does not fail the
+    /// batch, a `batch_item_failed` event is emitted for it instead, and `batch_item_minted` for
+    /// each minted tweet.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The Merkle root of the batch as a hexadecimal string
+    /// * `signature` - A string representing the signature of the root
+    /// * `co_signatures` - Signatures of the root by other trusted keys, when the threshold
+    ///   requires several
+    /// * `leaf_count` - The number of leaves of the tree
+    /// * `items` - The tweets to mint, with the inclusion path of their proof
+    ///
+    /// # Returns
+    ///
+    /// * `u32` - The number of tweets whose mint was started
+    pub fn verify_proofs_batch(
+        &mut self,
+        root: String,
+        signature: String,
+        co_signatures: Option<Vec<String>>,
+        leaf_count: u32,
+        items: Vec<BatchItem>,
+    ) -> u32 {
+        if items.is_empty() || items.len() > MAX_BATCH_SIZE {
+            env::panic_str(&format!(
+                "A batch must have between 1 and {} items",
+                MAX_BATCH_SIZE
+            ));
+        }
+        let root = root.trim_start_matches("0x").to_lowercase();
+        let root_bytes: [u8; 32] = hex::decode(&root)
+            .ok()
+            .and_then(|root| root.try_into().ok())
+            .unwrap_or_else(|| env::panic_str("INVALID MERKLE ROOT"));
+        self.assert_signed(&root, signature, co_signatures);
+
+        let included = check_batch(root_bytes, leaf_count, &items);
+        let mut started = 0;
+        for (index, (item, included)) in items.into_iter().zip(included).enumerate() {
+            let index = index as u32;
+            let checked = included
+                .and_then(|_| self.check_proof(&item.proof, item.image_url, &item.owner_address));
+            let (token_metadata, expires_at) = match checked {
+                Ok(checked) => checked,
+                Err(reason) => {
+                    BatchItemFailed {
+                        root: root.clone(),
+                        index,
+                        tweet_id: parse_x_response(&item.proof, is_tweet_lookup)
+                            .ok()
+                            .and_then(|response| serde_json::from_str::<Tweet>(&response.body).ok())
+                            .and_then(|tweet| tweet.data.into_iter().next())
+                            .map(|data| data.id),
+                        reason,
+                    }
+                    .emit();
+                    continue;
                 }
+            };
 
-                // Mint the NFT here after a successful verification
-                let token_id = token_metadata.title.clone().unwrap();
-                let receiver_id: MetadataExtra =
-                    serde_json::from_str(&token_metadata.clone().extra.unwrap_or(
-                        json!({"minted_to": env::predecessor_account_id()}).to_string(),
-                    ))
-                    .unwrap();
-                env::log_str(&format!("receiver_id: {:?}", receiver_id.minted_to));
-                return nft_contract::ext(self.nft_account_id.clone())
+            let token_id = token_metadata.title.clone().unwrap();
+            let proof_hash = hex::encode(env::sha256(item.proof.as_bytes()));
+            self.fetch_request(token_id).then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(Gas(20_000_000_000_000))
+                    .mint_batch_item(
+                        root.clone(),
+                        index,
+                        token_metadata,
+                        item.owner_address,
+                        proof_hash,
+                        expires_at,
+                    ),
+            );
+            started += 1;
+        }
+        started
+    }
+
+    /// Checks that a proof is fresh and unused, and generates the metadata of its tweet
+    ///
+    /// The proof is not consumed yet, the mint callbacks consume it once the mint request is
+    /// checked.
+    ///
+    /// # Arguments
+    ///
+    /// * `proof` - A string representing the proof
+    /// * `image_url` - URL of the image associated with the NFT
+    /// * `owner_address` - Account ID of the owner
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(TokenMetadata, u64), String>` - The metadata of the tweet and until when the
+    ///   proof has to stay consumed in ms, or why the proof is rejected
+    fn check_proof(
+        &self,
+        proof: &str,
+        image_url: String,
+        owner_address: &AccountId,
+    ) -> Result<(TokenMetadata, u64), String> {
+        let settings = self.get_replay_settings();
+        let now = env::block_timestamp_ms();
+        let response = parse_x_response(proof, is_tweet_lookup)?;
+        let served_at = response
+            .date()
+            .ok_or_else(|| "No Date header found in proof.".to_string())?;
+        settings.check_freshness(served_at, now)?;
+
+        env::log_str(&format!("json_str: {:?}", response.body));
+        let token_metadata =
+            generate_tweet_nft_payload(&response.body, image_url, owner_address.to_string())?;
+
+        // Remembered until the proof is stale, even if the clock of the server was ahead
+        let expires_at = (now + settings.retention).max(served_at + settings.max_proof_age);
+        check_unused(&env::sha256(proof.as_bytes()))?;
+
+        Ok((token_metadata, expires_at))
+    }
+
+    /// Fetches the mint request of a tweet and the hash of its image from the NFT contract
+    ///
+    /// # Arguments
+    ///
+    /// * `token_id` - The tweet ID
+    ///
+    /// # Returns
+    ///
+    /// * `Promise` - A promise resolving to the request and the hash of its image
+    fn fetch_request(&self, token_id: String) -> Promise {
+        nft_contract::ext(self.nft_account_id.clone())
+            .with_static_gas(Gas(5_000_000_000_000))
+            .get_request(token_id.clone())
+            .and(
+                nft_contract::ext(self.nft_account_id.clone())
                     .with_static_gas(Gas(5_000_000_000_000))
-                    .with_attached_deposit(DEPOSIT)
-                    .nft_mint(token_id, receiver_id.minted_to, token_metadata.clone())
-                    .then(
-                        Self::ext(env::current_account_id())
-                            .with_static_gas(Gas(5_000_000_000_000))
-                            .nft_creation_callback(),
-                    );
-            } else {
-                env::panic_str("No JSON found in input string.");
-            }
-        } else {
-            env::panic_str("INVALID PROOF");
+                    .get_request_image_hash(token_id),
+            )
+    }
+
+    /// Verifies a proof of a tweet or bio of an X author that mentions `account_id`, and links
+    /// the author to the account on the NFT contract, so the account can claim their royalties
+    ///
+    /// The proof is rejected like in `verify_proof_v2` if it was already used or is stale. The
+    /// NFT contract only replaces the account of a linked author with a more recent proof.
+    ///
+    /// # Arguments
+    ///
+    /// * `proof` - A string representing the proof
+    /// * `signature` - A string representing the signature
+    /// * `account_id` - Account ID to link, which must submit the proof itself
+    /// * `co_signatures` - Signatures of the proof by other trusted keys, when the threshold
+    ///   requires several
+    ///
+    /// # Returns
+    ///
+    /// * `Promise` - A promise representing the link on the NFT contract
+    pub fn verify_author_claim(
+        &mut self,
+        proof: String,
+        signature: String,
+        account_id: AccountId,
+        co_signatures: Option<Vec<String>>,
+    ) -> Promise {
+        // Proofs of public tweets can be submitted by anyone, the account has to claim for itself
+        if env::predecessor_account_id() != account_id {
+            env::panic_str("CLAIM MUST BE SUBMITTED BY THE ACCOUNT");
+        }
+        self.assert_signed(
+            &self.generate_merkle_tree(proof.clone()),
+            signature,
+            co_signatures,
+        );
+
+        let settings = self.get_replay_settings();
+        let now = env::block_timestamp_ms();
+        let response =
+            parse_x_response(&proof, is_author_lookup).unwrap_or_else(|e| env::panic_str(&e));
+        let served_at = response
+            .date()
+            .unwrap_or_else(|| env::panic_str("No Date header found in proof."));
+        settings
+            .check_freshness(served_at, now)
+            .unwrap_or_else(|e| env::panic_str(&e));
+        let (author_id, text) =
+            parse_author_claim(&response.body).unwrap_or_else(|e| env::panic_str(&e));
+        if !mentions_account(&text, account_id.as_str()) {
+            env::panic_str(&format!("{} is not mentioned by {}", account_id, author_id));
         }
+        let expires_at = (now + settings.retention).max(served_at + settings.max_proof_age);
+        consume(env::sha256(proof.as_bytes()), expires_at).unwrap_or_else(|e| env::panic_str(&e));
+
+        env::log_str(&format!(
+            "author_id: {}, account_id: {}",
+            author_id, account_id
+        ));
+        nft_contract::ext(self.nft_account_id.clone())
+            .with_static_gas(Gas(5_000_000_000_000))
+            .link_author(author_id, account_id, served_at)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(Gas(5_000_000_000_000))
+                    .author_link_callback(),
+            )
     }
 
     /// Verifies a proof and provides the metadata for NFT minting
     ///
+    /// The journal is rejected if it was already used within the retention of the replay
+    /// settings.
+    ///
     /// # Arguments
     ///
     /// * `journal` - A vector of bytes representing the journal
@@ -385,7 +1014,7 @@ impl VerifierProxy {
     /// # Returns
     ///
     /// * `Promise` - A promise representing the NFT minting operation
-    pub fn verify_proof(&self, journal: Vec<u8>, token_metadata: TokenMetadata) -> Promise {
+    pub fn verify_proof(&mut self, journal: Vec<u8>, token_metadata: TokenMetadata) -> Promise {
         let journal_output = ethabi::Token::Bytes(journal.clone().into());
 
         // Assert that the journal is equal to the sha256 hash of the stringified token metadata
@@ -398,6 +1027,10 @@ impl VerifierProxy {
             hex::encode(journal.clone()),
             "invalid token_metadata"
         );
+        let retention = self.get_replay_settings().retention;
+        if let Err(reason) = consume(journal.clone(), env::block_timestamp_ms() + retention) {
+            env::panic_str(&reason);
+        }
 
         let evm_input = ethabi::encode(&[journal_output]);
         let aurora_call_args = CallArgs::V1(FunctionCallArgsV1 {
@@ -415,6 +1048,64 @@ impl VerifierProxy {
             .then(Self::ext(env::current_account_id()).parse_verification_response(token_metadata))
     }
 
+    /// Retrieves how old proofs can be and how long consumed ones are remembered for
+    ///
+    /// # Returns
+    ///
+    /// * `ReplaySettings` - The replay settings, or their defaults if they were never set
+    pub fn get_replay_settings(&self) -> ReplaySettings {
+        replay_settings().get().unwrap_or_default()
+    }
+
+    /// Retrieves until when a proof or journal is consumed
+    ///
+    /// # Arguments
+    ///
+    /// * `hash` - The sha256 hash of the proof, or the journal, as a hexadecimal string
+    ///
+    /// # Returns
+    ///
+    /// * `Option<u64>` - When the hash expires in ms, if it was consumed
+    pub fn get_consumed_until(&self, hash: String) -> Option<u64> {
+        consumed_hashes().get(&string_to_vec_u8(&hash))
+    }
+
+    /// Forgets consumed proofs and journals which have expired, to release their storage
+    ///
+    /// # Arguments
+    ///
+    /// * `hashes` - The sha256 hashes of the proofs, or the journals, as hexadecimal strings
+    ///
+    /// # Returns
+    ///
+    /// * `u32` - The number of hashes forgotten
+    pub fn prune_consumed(&mut self, hashes: Vec<String>) -> u32 {
+        let mut consumed = consumed_hashes();
+        let now = env::block_timestamp_ms();
+        let mut pruned = 0;
+        for hash in hashes {
+            let hash = string_to_vec_u8(&hash);
+            if consumed.get(&hash).map_or(false, |until| until <= now) {
+                consumed.remove(&hash);
+                pruned += 1;
+            }
+        }
+        pruned
+    }
+
+    /// Sets how old proofs can be and how long consumed ones are remembered for
+    ///
+    /// # Arguments
+    ///
+    /// * `settings` - The new replay settings, whose retention is at least `max_proof_age`
+    #[private]
+    pub fn set_replay_settings(&mut self, settings: ReplaySettings) {
+        if let Err(reason) = settings.validate() {
+            env::panic_str(&reason);
+        }
+        replay_settings().set(&settings);
+    }
+
     /// Sets a new contract address for the verifier
     ///
     /// # Arguments
@@ -445,6 +1136,102 @@ impl VerifierProxy {
         self.ic_remote_public_key = aurora_sdk::parse_address(&ic_public_address).unwrap()
     }
 
+    /// Adds a trusted remote verifier key, or changes the validity period of one
+    ///
+    /// Once a key is added, `ic_remote_public_key` is no longer trusted unless it is added too.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - Address of the key as a string
+    /// * `valid_from` - When the key starts being trusted in ms, right away if not set
+    /// * `valid_until` - When the key stops being trusted in ms, never if not set
+    ///
+    /// # Returns
+    ///
+    /// * `NotaryKey` - The key as stored
+    #[private]
+    pub fn add_notary_key(
+        &mut self,
+        address: String,
+        valid_from: Option<u64>,
+        valid_until: Option<u64>,
+    ) -> NotaryKey {
+        if let (Some(from), Some(until)) = (valid_from, valid_until) {
+            if from >= until {
+                env::panic_str("valid_from must be before valid_until");
+            }
+        }
+        let key = NotaryKey {
+            address: normalize_address(&address),
+            valid_from,
+            valid_until,
+        };
+        notary_keys().insert(&key.address, &key);
+        notary_registry_used().set(&true);
+        key
+    }
+
+    /// Removes a trusted remote verifier key
+    ///
+    /// The last key cannot be removed, `ic_remote_public_key` is not trusted again once the
+    /// registry is used.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - Address of the key as a string
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if the key was trusted
+    #[private]
+    pub fn remove_notary_key(&mut self, address: String) -> bool {
+        let mut keys = notary_keys();
+        let removed = keys.remove(&normalize_address(&address)).is_some();
+        if keys.is_empty() {
+            env::panic_str("The last trusted key cannot be removed");
+        }
+        if keys.len() < u64::from(self.get_notary_threshold()) {
+            env::panic_str("Fewer keys would be trusted than the threshold requires");
+        }
+        removed
+    }
+
+    /// Sets how many distinct trusted keys must sign a proof
+    ///
+    /// # Arguments
+    ///
+    /// * `threshold` - The number of signatures required, at most the number of trusted keys
+    #[private]
+    pub fn set_notary_threshold(&mut self, threshold: u8) {
+        let trusted = notary_keys().len().max(1);
+        if threshold == 0 || u64::from(threshold) > trusted {
+            env::panic_str(&format!(
+                "The threshold must be between 1 and the {} trusted keys",
+                trusted
+            ));
+        }
+        notary_threshold().set(&threshold);
+    }
+
+    /// Retrieves the trusted remote verifier keys
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<NotaryKey>` - The keys with their validity periods, empty if only
+    ///   `ic_remote_public_key` is trusted
+    pub fn get_notary_keys(&self) -> Vec<NotaryKey> {
+        notary_keys().values().collect()
+    }
+
+    /// Retrieves how many distinct trusted keys must sign a proof
+    ///
+    /// # Returns
+    ///
+    /// * `u8` - The threshold, 1 if it was never set
+    pub fn get_notary_threshold(&self) -> u8 {
+        notary_threshold().get().unwrap_or(1)
+    }
+
     /// Callback used to parse the output from the call to Aurora made in `exact_output_single`.
     /// TODO: Pass in the NFT payload to this function and mint the NFT after this callback
     ///
@@ -508,26 +1295,404 @@ impl VerifierProxy {
         }
     }
 
-    /// Callback function to handle the result of the NFT creation
+    /// Callback used to mint a tweet verified by `verify_proof_v2` once its owner and image are
+    /// checked against the mint request of the tweet, consuming its proof
+    ///
+    /// # Arguments
+    ///
+    /// * `token_metadata` - Metadata of the token to be minted
+    /// * `owner_address` - Account ID the proof was submitted for
+    /// * `proof_hash` - The sha256 hash of the proof as a hexadecimal string
+    /// * `expires_at` - Until when the proof stays consumed, in ms
+    /// * `request` - Result of the call fetching the mint request of the tweet
+    /// * `image_hash` - Result of the call fetching the hash of the image of the request
+    ///
+    /// # Returns
+    ///
+    /// * `Promise` - A promise representing the NFT minting operation
+    #[private]
+    pub fn mint_requested_tweet(
+        &mut self,
+        token_metadata: TokenMetadata,
+        owner_address: AccountId,
+        proof_hash: String,
+        expires_at: u64,
+        #[callback_result] request: Result<Option<MintRequest>, PromiseError>,
+        #[callback_result] image_hash: Result<Option<Base64VecU8>, PromiseError>,
+    ) -> Promise {
+        // A proof submitted twice is only consumed by the first mint reaching this point
+        if let Err(reason) = check_request(&token_metadata, &owner_address, request, image_hash)
+            .and_then(|_| consume(string_to_vec_u8(&proof_hash), expires_at))
+        {
+            env::panic_str(&reason);
+        }
+
+        let token_id = token_metadata.title.clone().unwrap();
+        env::log_str(&format!("receiver_id: {:?}", owner_address));
+        nft_contract::ext(self.nft_account_id.clone())
+            .with_static_gas(Gas(5_000_000_000_000))
+            .with_attached_deposit(DEPOSIT)
+            .nft_mint(token_id, owner_address, token_metadata)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(Gas(5_000_000_000_000))
+                    .nft_creation_callback(),
+            )
+    }
+
+    /// Callback used to mint a tweet of a batch verified by `verify_proofs_batch` once its owner
+    /// and image are checked against the mint request of the tweet, consuming its proof
+    ///
+    /// Unlike `mint_requested_tweet` it does not panic, so the failure event of the item is
+    /// kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The Merkle root of the batch
+    /// * `index` - The position of the item in the batch
+    /// * `token_metadata` - Metadata of the token to be minted
+    /// * `owner_address` - Account ID the proof was submitted for
+    /// * `proof_hash` - The sha256 hash of the proof as a hexadecimal string
+    /// * `expires_at` - Until when the proof stays consumed, in ms
+    /// * `request` - Result of the call fetching the mint request of the tweet
+    /// * `image_hash` - Result of the call fetching the hash of the image of the request
+    ///
+    /// # Returns
+    ///
+    /// * `PromiseOrValue<bool>` - The NFT minting operation, or false if the item was rejected
+    #[private]
+    pub fn mint_batch_item(
+        &mut self,
+        root: String,
+        index: u32,
+        token_metadata: TokenMetadata,
+        owner_address: AccountId,
+        proof_hash: String,
+        expires_at: u64,
+        #[callback_result] request: Result<Option<MintRequest>, PromiseError>,
+        #[callback_result] image_hash: Result<Option<Base64VecU8>, PromiseError>,
+    ) -> PromiseOrValue<bool> {
+        let token_id = token_metadata.title.clone().unwrap();
+        if let Err(reason) = check_request(&token_metadata, &owner_address, request, image_hash)
+            .and_then(|_| consume(string_to_vec_u8(&proof_hash), expires_at))
+        {
+            BatchItemFailed {
+                root,
+                index,
+                tweet_id: Some(token_id),
+                reason,
+            }
+            .emit();
+            return PromiseOrValue::Value(false);
+        }
+
+        nft_contract::ext(self.nft_account_id.clone())
+            .with_static_gas(Gas(5_000_000_000_000))
+            .with_attached_deposit(DEPOSIT)
+            .nft_mint(token_id.clone(), owner_address.clone(), token_metadata)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(Gas(5_000_000_000_000))
+                    .batch_item_callback(root, index, token_id, owner_address),
+            )
+            .into()
+    }
+
+    /// Callback function to emit the outcome of the mint of a tweet of a batch
     ///
     /// # Arguments
     ///
+    /// * `root` - The Merkle root of the batch
+    /// * `index` - The position of the item in the batch
+    /// * `tweet_id` - The tweet ID
+    /// * `owner_address` - Account ID the NFT was minted to
     /// * `call_result` - Result of the NFT creation call
     ///
     /// # Returns
     ///
     /// * `bool` - True if the NFT creation was successful, false otherwise
     #[private]
+    pub fn batch_item_callback(
+        &mut self,
+        root: String,
+        index: u32,
+        tweet_id: String,
+        owner_address: AccountId,
+        #[callback_result] call_result: Result<Option<Token>, PromiseError>,
+    ) -> bool {
+        match call_result {
+            Ok(Some(_)) => {
+                BatchItemMinted {
+                    root,
+                    index,
+                    tweet_id,
+                    owner: owner_address,
+                }
+                .emit();
+                true
+            }
+            Ok(None) => {
+                BatchItemFailed {
+                    root,
+                    index,
+                    tweet_id: Some(tweet_id),
+                    reason: "nft_creation refunded the mint request".to_string(),
+                }
+                .emit();
+                false
+            }
+            Err(err) => {
+                BatchItemFailed {
+                    root,
+                    index,
+                    tweet_id: Some(tweet_id),
+                    reason: format!("nft_creation failed:{:?}", err),
+                }
+                .emit();
+                false
+            }
+        }
+    }
+
+    /// Callback function to handle the result of the NFT creation
+    ///
+    /// # Arguments
+    ///
+    /// * `call_result` - Result of the NFT creation call, `None` if the NFT contract refunded
+    ///   the mint request instead of minting
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if the NFT creation was successful, false otherwise
+    #[private]
     pub fn nft_creation_callback(
         &mut self,
-        #[callback_result] call_result: Result<Token, PromiseError>,
+        #[callback_result] call_result: Result<Option<Token>, PromiseError>,
     ) -> bool {
         // Return whether or not the promise succeeded using the method outlined in external.rs
-        if call_result.is_err() {
-            env::panic_str(format!("nft_creation failed:{:?}", call_result.err()).as_str());
-        } else {
-            env::log_str(format!("nft_creation was successful!").as_str());
-            return true;
+        match call_result {
+            Ok(Some(_)) => {
+                env::log_str(format!("nft_creation was successful!").as_str());
+                true
+            }
+            // The refund was made by the NFT contract, failing here only fails the transaction
+            Ok(None) => env::panic_str("nft_creation refunded the mint request"),
+            Err(err) => env::panic_str(format!("nft_creation failed:{:?}", err).as_str()),
+        }
+    }
+
+    /// Callback function to handle the result of an author link
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if the author was linked, false otherwise
+    #[private]
+    pub fn author_link_callback(&mut self) -> bool {
+        // `link_author` returns nothing, so only the status of the promise is looked at
+        match env::promise_result(0) {
+            PromiseResult::Successful(_) => {
+                env::log_str("author link was successful!");
+                true
+            }
+            _ => env::panic_str("author link failed"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REQUEST: &str = include_str!("../../../../http_transcript/fixtures/tweet_request.txt");
+    const RESPONSE: &str = include_str!("../../../../http_transcript/fixtures/tweet_response.txt");
+
+    /// A batch of the proofs, as built by the indexer, with the root of its tree
+    fn batch(proofs: &[&str]) -> ([u8; 32], Vec<BatchItem>) {
+        let leaves: Vec<[u8; 32]> = proofs
+            .iter()
+            .map(|proof| Sha256::hash(proof.as_bytes()))
+            .collect();
+        let tree = MerkleTree::<Sha256>::from_leaves(&leaves);
+        let items = proofs
+            .iter()
+            .enumerate()
+            .map(|(index, proof)| BatchItem {
+                proof: proof.to_string(),
+                leaf_index: index as u32,
+                inclusion_path: tree.proof(&[index]).proof_hashes_hex(),
+                image_url: "ipfs://".to_string(),
+                owner_address: "alice.testnet".parse().unwrap(),
+            })
+            .collect();
+        (tree.root().unwrap(), items)
+    }
+
+    #[test]
+    fn test_parse_x_response() {
+        let proof = format!("{}{}\r\n", REQUEST, RESPONSE);
+        let response = parse_x_response(&proof, is_tweet_lookup).unwrap();
+        assert_eq!(response.status, 200);
+
+        // A response without its request could come from any host
+        assert_eq!(
+            parse_x_response(RESPONSE, is_tweet_lookup).err(),
+            Some("MISSING REQUEST: the proof has no request".to_string())
+        );
+
+        let other_host = proof.replacen("api.x.com", "api.example.com", 1);
+        assert!(parse_x_response(&other_host, is_tweet_lookup)
+            .unwrap_err()
+            .starts_with("UNEXPECTED REQUEST"));
+        assert!(parse_x_response(&proof, |path| path == "/2/users")
+            .unwrap_err()
+            .starts_with("UNEXPECTED REQUEST"));
+    }
+
+    #[test]
+    fn test_is_author_lookup() {
+        assert!(is_author_lookup("/2/tweets"));
+        assert!(is_author_lookup("/2/users"));
+        assert!(is_author_lookup("/2/users/by/username/author"));
+        assert!(!is_author_lookup("/2/tweets/search/recent"));
+        assert!(!is_author_lookup("/2/usersearch"));
+        assert!(!is_tweet_lookup("/2/users"));
+    }
+
+    #[test]
+    fn test_mentions_account() {
+        assert!(mentions_account(
+            "My NEAR account is author.near.",
+            "author.near"
+        ));
+        assert!(mentions_account("(Author.Near)", "author.near"));
+        assert!(mentions_account("author.near", "author.near"));
+        assert!(!mentions_account("sub.author.near", "author.near"));
+        assert!(!mentions_account("author.near.org", "author.near"));
+        assert!(!mentions_account("coauthor.near", "author.near"));
+        assert!(!mentions_account("author-near", "author.near"));
+    }
+
+    #[test]
+    fn test_parse_author_claim() {
+        fn tweet(text: &str, referenced_tweets: &str) -> String {
+            format!(
+                r#"{{"data":[{{"created_at":"2024-11-21T12:00:00.000Z","id":"1","public_metrics":{{"retweet_count":0,"reply_count":0,"like_count":0,"quote_count":0,"bookmark_count":0,"impression_count":0}},"edit_history_tweet_ids":["1"],"author_id":"42","text":"{}"{}}}],"includes":{{"users":[]}}}}"#,
+                text, referenced_tweets
+            )
+        }
+
+        assert_eq!(
+            parse_author_claim(&tweet("I am author.near", "")),
+            Ok(("42".to_string(), "I am author.near".to_string()))
+        );
+        assert_eq!(
+            parse_author_claim(&tweet(
+                "Quoting author.near",
+                r#","referenced_tweets":[{"type":"quoted","id":"2"}]"#
+            )),
+            Ok(("42".to_string(), "Quoting author.near".to_string()))
+        );
+        assert_eq!(
+            parse_author_claim(r#"{"data":{"id":"42","description":"Bio of author.near"}}"#),
+            Ok(("42".to_string(), "Bio of author.near".to_string()))
+        );
+
+        // The text of a retweet is the words of the retweeted author
+        let retweet = Err("Retweets cannot be used to claim an author.".to_string());
+        assert_eq!(
+            parse_author_claim(&tweet("RT @other: I am other.near", "")),
+            retweet
+        );
+        assert_eq!(
+            parse_author_claim(&tweet(
+                "I am other.near",
+                r#","referenced_tweets":[{"type":"retweeted","id":"2"}]"#
+            )),
+            retweet
+        );
+
+        let not_found = Err("No tweet or user found in proof.".to_string());
+        assert_eq!(
+            parse_author_claim(r#"{"data":[],"includes":{"users":[]}}"#),
+            not_found
+        );
+        assert_eq!(
+            parse_author_claim(r#"{"errors":[{"title":"Not Found Error"}]}"#),
+            not_found
+        );
+    }
+
+    #[test]
+    fn test_check_inclusion() {
+        let (root, mut items) = batch(&["proof 1", "proof 2", "proof 3"]);
+        for item in &items {
+            assert_eq!(check_inclusion(root, 3, item), Ok(()));
         }
+        let not_in_batch = Err("PROOF NOT IN BATCH".to_string());
+
+        items[0].leaf_index = 1;
+        assert_eq!(check_inclusion(root, 3, &items[0]), not_in_batch);
+        items[0].leaf_index = 3;
+        assert_eq!(check_inclusion(root, 3, &items[0]), not_in_batch);
+        items[0].leaf_index = u32::MAX;
+        assert_eq!(check_inclusion(root, 3, &items[0]), not_in_batch);
+        items[0].leaf_index = 0;
+
+        items[0].proof = "proof 4".to_string();
+        assert_eq!(check_inclusion(root, 3, &items[0]), not_in_batch);
+
+        // Paths are accepted with or without a 0x prefix, but only of 32-byte hashes
+        items[1].inclusion_path[0] = format!("0x{}", items[1].inclusion_path[0]);
+        assert_eq!(check_inclusion(root, 3, &items[1]), Ok(()));
+        items[1].inclusion_path[0] = "0xabcd".to_string();
+        assert_eq!(
+            check_inclusion(root, 3, &items[1]),
+            Err("Invalid hash in inclusion path: 0xabcd".to_string())
+        );
+        items[2].inclusion_path[0] = "not hex".to_string();
+        assert_eq!(
+            check_inclusion(root, 3, &items[2]),
+            Err("Invalid hash in inclusion path: not hex".to_string())
+        );
+    }
+
+    #[test]
+    fn test_check_batch() {
+        let (root, items) = batch(&["proof 1", "proof 2"]);
+        assert_eq!(check_batch(root, 2, &items), vec![Ok(()), Ok(())]);
+
+        // A proof can be a leaf of the tree twice, but is only minted once
+        let (root, items) = batch(&["proof 1", "proof 1", "proof 2"]);
+        let repeated = Err("PROOF REPEATED IN BATCH".to_string());
+        assert_eq!(
+            check_batch(root, 3, &items),
+            vec![Ok(()), repeated.clone(), Ok(())]
+        );
+
+        // As is an item submitted twice
+        let (root, mut items) = batch(&["proof 1", "proof 2"]);
+        items.push(BatchItem {
+            proof: "proof 2".to_string(),
+            leaf_index: 1,
+            inclusion_path: items[1].inclusion_path.clone(),
+            image_url: "ipfs://".to_string(),
+            owner_address: "bob.testnet".parse().unwrap(),
+        });
+        assert_eq!(check_batch(root, 2, &items), vec![Ok(()), Ok(()), repeated]);
+
+        // A copy is not repeated when the first of them is rejected
+        let (root, mut items) = batch(&["proof 1", "proof 2"]);
+        items[0].leaf_index = 1;
+        items.push(BatchItem {
+            leaf_index: 0,
+            inclusion_path: batch(&["proof 1", "proof 2"]).1[0].inclusion_path.clone(),
+            proof: "proof 1".to_string(),
+            image_url: "ipfs://".to_string(),
+            owner_address: "alice.testnet".parse().unwrap(),
+        });
+        assert_eq!(
+            check_batch(root, 2, &items),
+            vec![Err("PROOF NOT IN BATCH".to_string()), Ok(()), Ok(())]
+        );
     }
 }
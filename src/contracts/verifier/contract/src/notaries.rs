@@ -0,0 +1,85 @@
+//! The remote verifier keys trusted to sign proofs, and how many of them must sign each proof.
+//!
+//! Keys can be given a validity period, so a new key is added ahead of a rotation and the old
+//! one expires on its own. Requiring the signatures of several distinct keys means a single
+//! compromised remote verifier cannot get proofs accepted.
+
+use aurora_sdk::near_sdk;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// A trusted remote verifier key
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct NotaryKey {
+    /// The Ethereum address of the key, lowercase and without `0x`
+    pub address: String,
+    /// When the key starts being trusted, in ms
+    pub valid_from: Option<u64>,
+    /// When the key stops being trusted, in ms
+    pub valid_until: Option<u64>,
+}
+
+impl NotaryKey {
+    /// Checks whether the key is trusted at `now` ms
+    pub fn is_valid_at(&self, now: u64) -> bool {
+        self.valid_from.map_or(true, |from| from <= now)
+            && self.valid_until.map_or(true, |until| now < until)
+    }
+}
+
+/// Counts the distinct signers which are trusted, a key signing twice is counted once
+///
+/// # Arguments
+///
+/// * `signers` - The addresses recovered from the signatures
+/// * `is_trusted` - Whether an address is a trusted key
+///
+/// # Returns
+///
+/// * `usize` - The number of distinct trusted signers
+pub fn count_trusted_signers(signers: &[String], is_trusted: impl Fn(&str) -> bool) -> usize {
+    signers
+        .iter()
+        .map(|signer| signer.to_lowercase())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter(|signer| is_trusted(signer))
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_at() {
+        let key = NotaryKey {
+            address: "19e7e376e7c213b7e7e7e46cc70a5dd086daff2a".to_string(),
+            valid_from: Some(1000),
+            valid_until: Some(2000),
+        };
+        assert!(!key.is_valid_at(999));
+        assert!(key.is_valid_at(1000));
+        assert!(key.is_valid_at(1999));
+        assert!(!key.is_valid_at(2000));
+
+        let key = NotaryKey {
+            valid_from: None,
+            valid_until: None,
+            ..key
+        };
+        assert!(key.is_valid_at(0));
+        assert!(key.is_valid_at(u64::MAX));
+    }
+
+    #[test]
+    fn test_count_trusted_signers() {
+        let trusted = |signer: &str| signer == "aa" || signer == "bb";
+        let signers = |signers: &[&str]| signers.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(count_trusted_signers(&signers(&["aa", "bb"]), trusted), 2);
+        assert_eq!(count_trusted_signers(&signers(&["aa", "AA"]), trusted), 1);
+        assert_eq!(count_trusted_signers(&signers(&["aa", "cc"]), trusted), 1);
+        assert_eq!(count_trusted_signers(&[], trusted), 0);
+    }
+}
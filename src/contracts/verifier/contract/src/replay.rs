@@ -0,0 +1,78 @@
+//! Protection against proofs and journals being submitted more than once, or long after the
+//! response they prove was served.
+//!
+//! A consumed proof or journal is remembered until it expires. Proofs are also rejected once the
+//! `Date` header of the notarized HTTP response is older than the freshness bound, so keeping a
+//! consumed proof for at least as long as that bound is enough to reject every replay of it.
+
+use aurora_sdk::near_sdk;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use serde::{Deserialize, Serialize};
+
+/// How old proofs can be, and how long consumed proofs and journals are remembered for.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ReplaySettings {
+    /// How long after its response was served a proof can be submitted, in ms.
+    pub max_proof_age: u64,
+    /// How long a consumed proof or journal is remembered for, in ms. It is never shorter than
+    /// `max_proof_age`.
+    pub retention: u64,
+}
+
+impl Default for ReplaySettings {
+    fn default() -> Self {
+        Self {
+            max_proof_age: 15 * 60 * 1000,
+            retention: 24 * 60 * 60 * 1000,
+        }
+    }
+}
+
+impl ReplaySettings {
+    /// Checks that the settings reject every replay of a consumed proof.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_proof_age == 0 {
+            return Err("max_proof_age must be positive".to_string());
+        }
+        if self.retention < self.max_proof_age {
+            return Err("retention must not be shorter than max_proof_age".to_string());
+        }
+        Ok(())
+    }
+
+    /// Checks that a response served at `served_at` ms is fresh enough at `now` ms.
+    pub fn check_freshness(&self, served_at: u64, now: u64) -> Result<(), String> {
+        let age = now.saturating_sub(served_at);
+        if age > self.max_proof_age {
+            return Err(format!(
+                "PROOF IS STALE: served {} ms ago, at most {} ms allowed",
+                age, self.max_proof_age
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_freshness() {
+        let settings = ReplaySettings {
+            max_proof_age: 1000,
+            retention: 1000,
+        };
+        assert!(settings.validate().is_ok());
+        assert!(settings.check_freshness(5000, 6000).is_ok());
+        assert!(settings.check_freshness(5000, 6001).is_err());
+        // Clocks of the notary and the chain can drift, a response from the future is fresh
+        assert!(settings.check_freshness(5000, 4000).is_ok());
+
+        let settings = ReplaySettings {
+            max_proof_age: 1000,
+            retention: 999,
+        };
+        assert!(settings.validate().is_err());
+    }
+}
@@ -101,10 +101,24 @@ mod tests {
         // perform the verification/minting on near
         let test_token_metadata = Verifier::generate_default_metadata();
         near_verifier_contract_proxy
-            .verify_proof(journal_output, test_token_metadata.clone())
+            .verify_proof(journal_output.clone(), test_token_metadata.clone())
             .await
             .unwrap();
 
+        // a journal cannot be used twice
+        let replay = near_verifier_contract_proxy
+            .contract
+            .call("verify_proof")
+            .args_json(near_interface::VerifyProofArgs {
+                journal: journal_output,
+                token_metadata: test_token_metadata.clone(),
+            })
+            .max_gas()
+            .transact()
+            .await
+            .unwrap();
+        assert!(replay.is_failure(), "replayed journal was accepted");
+
         // fetch the nft and verify it exists
         let newly_minted_token = near_nft_contract_proxy
             .nft_token_by_id(test_token_metadata.clone().title.unwrap())
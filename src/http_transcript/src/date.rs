@@ -0,0 +1,74 @@
+/// Parses an HTTP date in the IMF-fixdate format, e.g. `Sun, 17 Nov 2024 16:26:14 GMT`, into ms.
+///
+/// The X API writes the zone as `UTC` rather than `GMT`, both are accepted.
+pub fn parse_http_date(date: &str) -> Option<u64> {
+    let mut parts = date.split_whitespace();
+    let _weekday = parts.next()?;
+    let day: u64 = parts.next()?.parse().ok()?;
+    let month = match parts.next()? {
+        "Jan" => 1,
+        "Feb" => 2,
+        "Mar" => 3,
+        "Apr" => 4,
+        "May" => 5,
+        "Jun" => 6,
+        "Jul" => 7,
+        "Aug" => 8,
+        "Sep" => 9,
+        "Oct" => 10,
+        "Nov" => 11,
+        "Dec" => 12,
+        _ => return None,
+    };
+    let year: u64 = parts.next()?.parse().ok()?;
+    let mut time = parts.next()?.split(':');
+    let hours: u64 = time.next()?.parse().ok()?;
+    let minutes: u64 = time.next()?.parse().ok()?;
+    let seconds: u64 = time.next()?.parse().ok()?;
+    if !matches!(parts.next()?, "GMT" | "UTC") || year < 1970 || !(1..=31).contains(&day) {
+        return None;
+    }
+    if hours > 23 || minutes > 59 || seconds > 60 {
+        return None;
+    }
+
+    let days = days_from_civil(year, month, day);
+    Some((((days * 24 + hours) * 60 + minutes) * 60 + seconds) * 1000)
+}
+
+/// The number of days from 1970-01-01 to a date of the proleptic Gregorian calendar.
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    // Years start in March, so the leap day is the last day of the year
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let month_from_march = (month + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_http_date() {
+        assert_eq!(parse_http_date("Thu, 01 Jan 1970 00:00:00 GMT"), Some(0));
+        assert_eq!(
+            parse_http_date("Sun, 17 Nov 2024 16:26:14 GMT"),
+            Some(1_731_860_774_000)
+        );
+        assert_eq!(
+            parse_http_date("Thu, 29 Feb 2024 00:00:00 GMT"),
+            Some(1_709_164_800_000)
+        );
+        assert_eq!(
+            parse_http_date("Fri, 22 Nov 2024 01:23:03 UTC"),
+            Some(1_732_238_583_000)
+        );
+        assert_eq!(parse_http_date("Sun, 17 Nov 2024 16:26:14 CET"), None);
+        assert_eq!(parse_http_date("Sunday, 17-Nov-24 16:26:14 GMT"), None);
+        assert_eq!(parse_http_date(""), None);
+    }
+}
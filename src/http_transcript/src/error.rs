@@ -0,0 +1,76 @@
+use alloc::string::String;
+use core::fmt;
+
+/// Errors raised while parsing a notarized HTTP transcript.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HttpError {
+    /// The transcript has no HTTP/1.x status line.
+    MissingStatusLine,
+    /// The status line is not of the form `HTTP/1.1 200 OK`.
+    InvalidStatusLine(String),
+    /// The request line is not of the form `GET /path HTTP/1.1`.
+    InvalidRequestLine(String),
+    /// The HTTP version is not 1.0 or 1.1.
+    UnsupportedVersion(String),
+    /// The response does not have the expected status code.
+    UnexpectedStatus(u16),
+    /// The headers are not followed by an empty line.
+    IncompleteHead,
+    /// A header line is not of the form `name: value`.
+    InvalidHeader(String),
+    /// The `Content-Length` header is not a number, or is given twice with different values.
+    InvalidContentLength(String),
+    /// The body uses a transfer encoding other than chunked.
+    UnsupportedTransferEncoding(String),
+    /// The body is compressed.
+    UnsupportedContentEncoding(String),
+    /// A chunk of a chunked body is malformed.
+    InvalidChunk(String),
+    /// The body is shorter than announced.
+    TruncatedBody { expected: usize, received: usize },
+    /// The body is not of the expected media type.
+    UnexpectedContentType(String),
+    /// The request target is relative and the request has no `Host` header.
+    MissingHost,
+    /// The request target is neither an https URL nor a path.
+    InvalidTarget(String),
+    /// The request was not made to the expected host and path.
+    UnexpectedUrl(String),
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HttpError::MissingStatusLine => write!(f, "no HTTP status line found"),
+            HttpError::InvalidStatusLine(line) => write!(f, "invalid status line: {}", line),
+            HttpError::InvalidRequestLine(line) => write!(f, "invalid request line: {}", line),
+            HttpError::UnsupportedVersion(version) => {
+                write!(f, "unsupported HTTP version: {}", version)
+            }
+            HttpError::UnexpectedStatus(status) => write!(f, "unexpected status: {}", status),
+            HttpError::IncompleteHead => write!(f, "headers are not terminated"),
+            HttpError::InvalidHeader(line) => write!(f, "invalid header: {}", line),
+            HttpError::InvalidContentLength(value) => {
+                write!(f, "invalid content-length: {}", value)
+            }
+            HttpError::UnsupportedTransferEncoding(encoding) => {
+                write!(f, "unsupported transfer-encoding: {}", encoding)
+            }
+            HttpError::UnsupportedContentEncoding(encoding) => {
+                write!(f, "unsupported content-encoding: {}", encoding)
+            }
+            HttpError::InvalidChunk(chunk) => write!(f, "invalid chunk: {}", chunk),
+            HttpError::TruncatedBody { expected, received } => write!(
+                f,
+                "body is truncated: expected {} bytes, received {}",
+                expected, received
+            ),
+            HttpError::UnexpectedContentType(content_type) => {
+                write!(f, "unexpected content-type: {}", content_type)
+            }
+            HttpError::MissingHost => write!(f, "request has no host"),
+            HttpError::InvalidTarget(target) => write!(f, "invalid request target: {}", target),
+            HttpError::UnexpectedUrl(url) => write!(f, "unexpected request URL: {}", url),
+        }
+    }
+}
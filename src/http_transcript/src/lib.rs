@@ -0,0 +1,121 @@
+//! Parsing of the HTTP/1.1 transcripts notarized by the Verity prover.
+//!
+//! The verifier contract, the zk guest and the indexer read the X API response of a TLS proof
+//! with this crate, so they agree on what a proof contains. It has no dependencies and is
+//! `no_std`, so it builds for both the NEAR and the RISC Zero targets.
+//!
+//! Only what the X API sends is supported: bodies delimited by `Content-Length`, by the end of
+//! the connection or by chunked transfer encoding. Compressed bodies are rejected, the prover
+//! asks for `accept-encoding: identity`.
+
+#![no_std]
+
+extern crate alloc;
+
+mod date;
+mod error;
+mod request;
+mod response;
+
+pub use date::parse_http_date;
+pub use error::HttpError;
+pub use request::{Request, Url};
+pub use response::Response;
+
+use alloc::vec::Vec;
+
+/// A header of a request or a response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header<'a> {
+    pub name: &'a str,
+    pub value: &'a str,
+}
+
+/// Finds the value of a header, header names are case insensitive.
+fn find_header<'a>(headers: &[Header<'a>], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|header| header.name.eq_ignore_ascii_case(name))
+        .map(|header| header.value)
+}
+
+/// Parses the header lines of a request or a response.
+fn parse_headers(lines: &str) -> Result<Vec<Header<'_>>, HttpError> {
+    lines
+        .split("\r\n")
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let (name, value) = line
+                .split_once(':')
+                .ok_or_else(|| HttpError::InvalidHeader(line.into()))?;
+            // Folded lines are obsolete, and whitespace before the colon is forbidden
+            let is_token = !name.is_empty()
+                && name
+                    .bytes()
+                    .all(|byte| byte.is_ascii_graphic() && !b"()<>@,;:\\\"/[]?={}".contains(&byte));
+            if !is_token {
+                return Err(HttpError::InvalidHeader(line.into()));
+            }
+            Ok(Header {
+                name,
+                value: value.trim_matches(|c| c == ' ' || c == '\t'),
+            })
+        })
+        .collect()
+}
+
+/// Splits the data of a session into the request and the response.
+///
+/// The remote verifier returns the sent and the received data of a session one after the
+/// other. The response starts at the first line that is an HTTP/1.x status line.
+///
+/// # Returns
+///
+/// The request if the transcript has one, and the response.
+pub fn split_transcript(transcript: &str) -> Result<(Option<&str>, &str), HttpError> {
+    if transcript.starts_with("HTTP/") {
+        return Ok((None, transcript));
+    }
+    let start = transcript
+        .find("\nHTTP/")
+        .ok_or(HttpError::MissingStatusLine)?
+        + 1;
+    let request = transcript[..start].trim();
+    let request = if request.is_empty() {
+        None
+    } else {
+        Some(request)
+    };
+    Ok((request, &transcript[start..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REQUEST: &str = include_str!("../fixtures/tweet_request.txt");
+    const RESPONSE: &str = include_str!("../fixtures/tweet_response.txt");
+
+    #[test]
+    fn test_split_transcript() {
+        let transcript = alloc::format!("{}{}\r\n", REQUEST, RESPONSE);
+        let (request, response) = split_transcript(&transcript).unwrap();
+        assert_eq!(request, Some(REQUEST.trim()));
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+
+        assert_eq!(split_transcript(RESPONSE).unwrap(), (None, RESPONSE));
+        assert_eq!(split_transcript(REQUEST), Err(HttpError::MissingStatusLine));
+    }
+
+    #[test]
+    fn test_parse_headers() {
+        let headers = parse_headers("host: api.x.com\r\nContent-Length:\t740 \r\n").unwrap();
+        assert_eq!(find_header(&headers, "Host"), Some("api.x.com"));
+        assert_eq!(find_header(&headers, "content-length"), Some("740"));
+        assert_eq!(find_header(&headers, "date"), None);
+
+        assert!(parse_headers("host : api.x.com").is_err());
+        assert!(parse_headers(" folded").is_err());
+        assert!(parse_headers("no colon").is_err());
+    }
+}
@@ -0,0 +1,200 @@
+use alloc::format;
+use alloc::vec::Vec;
+
+use crate::{find_header, parse_headers, Header, HttpError};
+
+/// An HTTP/1.x request, as sent by the prover.
+///
+/// The prover redacts secrets such as the `authorization` header from the request, so only its
+/// request line and headers are read and the request may end without an empty line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request<'a> {
+    pub method: &'a str,
+    /// The target of the request line, either an absolute URL or a path with a query.
+    pub target: &'a str,
+    pub version: &'a str,
+    pub headers: Vec<Header<'a>>,
+}
+
+/// The URL a request was made to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Url<'a> {
+    pub host: &'a str,
+    pub path: &'a str,
+    pub query: Option<&'a str>,
+}
+
+impl<'a> Url<'a> {
+    /// Finds the value of a query parameter, as it appears in the URL.
+    pub fn query_param(&self, name: &str) -> Option<&'a str> {
+        self.query?
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    }
+}
+
+impl<'a> Request<'a> {
+    /// Parses a request, starting at its request line.
+    pub fn parse(transcript: &'a str) -> Result<Self, HttpError> {
+        let head = transcript
+            .split_once("\r\n\r\n")
+            .map_or(transcript, |(head, _body)| head);
+        let (request_line, header_lines) = head.split_once("\r\n").unwrap_or((head, ""));
+
+        let invalid = || HttpError::InvalidRequestLine(request_line.into());
+        let mut parts = request_line.split(' ');
+        let (method, target, version) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(method), Some(target), Some(version)) if parts.next().is_none() => {
+                (method, target, version)
+            }
+            _ => return Err(invalid()),
+        };
+        if method.is_empty() || !method.bytes().all(|byte| byte.is_ascii_uppercase()) {
+            return Err(invalid());
+        }
+        if version != "HTTP/1.1" && version != "HTTP/1.0" {
+            return Err(HttpError::UnsupportedVersion(version.into()));
+        }
+
+        Ok(Request {
+            method,
+            target,
+            version,
+            headers: parse_headers(header_lines)?,
+        })
+    }
+
+    /// Finds the value of a header, header names are case insensitive.
+    pub fn header(&self, name: &str) -> Option<&'a str> {
+        find_header(&self.headers, name)
+    }
+
+    /// The URL the request was made to.
+    ///
+    /// Only https URLs are accepted, the host of a path target is read from the `Host` header.
+    pub fn url(&self) -> Result<Url<'a>, HttpError> {
+        let (host, path_and_query) = if let Some(url) = self.target.strip_prefix("https://") {
+            let path_start = url.find(['/', '?']).unwrap_or(url.len());
+            (&url[..path_start], &url[path_start..])
+        } else if self.target.starts_with('/') {
+            let host = self.header("host").ok_or(HttpError::MissingHost)?;
+            (host, self.target)
+        } else {
+            return Err(HttpError::InvalidTarget(self.target.into()));
+        };
+        if host.is_empty() || host.contains('@') {
+            return Err(HttpError::InvalidTarget(self.target.into()));
+        }
+
+        // The default port is the only one the prover connects to
+        let host = host.strip_suffix(":443").unwrap_or(host);
+        let (path, query) = match path_and_query.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (path_and_query, None),
+        };
+        Ok(Url {
+            host,
+            path: if path.is_empty() { "/" } else { path },
+            query,
+        })
+    }
+
+    /// Checks that the request is a GET request for `path` on `host`.
+    ///
+    /// # Returns
+    ///
+    /// The URL of the request.
+    pub fn check_url(&self, host: &str, path: &str) -> Result<Url<'a>, HttpError> {
+        let url = self.url()?;
+        if self.method != "GET" || !url.host.eq_ignore_ascii_case(host) || url.path != path {
+            return Err(HttpError::UnexpectedUrl(format!(
+                "{} {}",
+                self.method, self.target
+            )));
+        }
+        Ok(url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REQUEST: &str = include_str!("../fixtures/tweet_request.txt");
+
+    #[test]
+    fn test_parse_notarized_request() {
+        let request = Request::parse(REQUEST).unwrap();
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.version, "HTTP/1.1");
+        assert_eq!(request.header("Host"), Some("api.x.com"));
+        assert_eq!(request.header("accept-encoding"), Some("identity"));
+        assert_eq!(request.header("authorization"), Some("Bearer [redacted]"));
+
+        let url = request.check_url("api.x.com", "/2/tweets").unwrap();
+        assert_eq!(url.query_param("ids"), Some("1859567727305949191"));
+        assert_eq!(url.query_param("expansions"), Some("author_id"));
+        assert_eq!(url.query_param("id"), None);
+
+        assert!(matches!(
+            request.check_url("api.x.com", "/2/users"),
+            Err(HttpError::UnexpectedUrl(_))
+        ));
+        assert!(matches!(
+            request.check_url("api.twitter.com", "/2/tweets"),
+            Err(HttpError::UnexpectedUrl(_))
+        ));
+
+        // The redacted value may run until the end of the transcript
+        let truncated = REQUEST.trim_end();
+        assert_eq!(Request::parse(truncated).unwrap(), request);
+    }
+
+    #[test]
+    fn test_request_url() {
+        fn url(request: &str) -> Result<Url<'_>, HttpError> {
+            Request::parse(request)?.url()
+        }
+
+        let path = url("GET /2/tweets?ids=1 HTTP/1.1\r\nhost: api.x.com:443\r\n\r\n").unwrap();
+        assert_eq!(
+            path,
+            Url {
+                host: "api.x.com",
+                path: "/2/tweets",
+                query: Some("ids=1"),
+            }
+        );
+        assert_eq!(
+            url("GET https://api.x.com HTTP/1.1\r\n\r\n").unwrap().path,
+            "/"
+        );
+        assert_eq!(
+            url("GET /2/tweets HTTP/1.1\r\n\r\n"),
+            Err(HttpError::MissingHost)
+        );
+        assert!(matches!(
+            url("GET http://api.x.com/2/tweets HTTP/1.1\r\n\r\n"),
+            Err(HttpError::InvalidTarget(_))
+        ));
+        assert!(matches!(
+            url("GET https://evil.com@api.x.com/2/tweets HTTP/1.1\r\n\r\n"),
+            Err(HttpError::InvalidTarget(_))
+        ));
+
+        // A POST to the same URL is not a lookup
+        let post = Request::parse("POST https://api.x.com/2/tweets HTTP/1.1\r\n\r\n").unwrap();
+        assert!(post.check_url("api.x.com", "/2/tweets").is_err());
+
+        assert!(matches!(
+            Request::parse("GET /2/tweets\r\n\r\n"),
+            Err(HttpError::InvalidRequestLine(_))
+        ));
+        assert!(matches!(
+            Request::parse("GET /2/tweets HTTP/2\r\n\r\n"),
+            Err(HttpError::UnsupportedVersion(_))
+        ));
+    }
+}
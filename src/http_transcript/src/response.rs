@@ -0,0 +1,342 @@
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::{find_header, parse_headers, parse_http_date, Header, HttpError};
+
+/// An HTTP/1.x response, with its body decoded from its transfer encoding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response<'a> {
+    pub version: &'a str,
+    pub status: u16,
+    pub reason: &'a str,
+    pub headers: Vec<Header<'a>>,
+    /// The body, borrowed from the transcript unless it was chunked.
+    pub body: Cow<'a, str>,
+}
+
+impl<'a> Response<'a> {
+    /// Parses a response, starting at its status line.
+    ///
+    /// Anything after the end of the body, as given by `Content-Length` or the last chunk, is
+    /// not part of the response and is ignored.
+    pub fn parse(transcript: &'a str) -> Result<Self, HttpError> {
+        let (head, rest) = transcript
+            .split_once("\r\n\r\n")
+            .ok_or(HttpError::IncompleteHead)?;
+        let (status_line, header_lines) = head.split_once("\r\n").unwrap_or((head, ""));
+        let (version, status, reason) = parse_status_line(status_line)?;
+        let headers = parse_headers(header_lines)?;
+
+        if let Some(encoding) = find_header(&headers, "content-encoding") {
+            if !encoding.eq_ignore_ascii_case("identity") {
+                return Err(HttpError::UnsupportedContentEncoding(encoding.into()));
+            }
+        }
+
+        let body = match find_header(&headers, "transfer-encoding") {
+            Some(encoding) if encoding.eq_ignore_ascii_case("chunked") => {
+                Cow::Owned(decode_chunked(rest)?)
+            }
+            Some(encoding) => return Err(HttpError::UnsupportedTransferEncoding(encoding.into())),
+            None => Cow::Borrowed(match content_length(&headers)? {
+                Some(length) => rest.get(..length).ok_or(HttpError::TruncatedBody {
+                    expected: length,
+                    received: rest.len(),
+                })?,
+                // The body lasts until the connection is closed
+                None => rest,
+            }),
+        };
+
+        Ok(Response {
+            version,
+            status,
+            reason,
+            headers,
+            body,
+        })
+    }
+
+    /// Finds the value of a header, header names are case insensitive.
+    pub fn header(&self, name: &str) -> Option<&'a str> {
+        find_header(&self.headers, name)
+    }
+
+    /// Checks the status code of the response.
+    pub fn expect_status(&self, status: u16) -> Result<(), HttpError> {
+        if self.status != status {
+            return Err(HttpError::UnexpectedStatus(self.status));
+        }
+        Ok(())
+    }
+
+    /// Reads when the response was served, from its `Date` header, in ms.
+    pub fn date(&self) -> Option<u64> {
+        self.header("date").and_then(parse_http_date)
+    }
+
+    /// Returns the body, checking that it is JSON.
+    pub fn json_body(&self) -> Result<&str, HttpError> {
+        let content_type = self.header("content-type").unwrap_or_default();
+        let media_type = content_type.split(';').next().unwrap_or_default().trim();
+        if !media_type.eq_ignore_ascii_case("application/json") {
+            return Err(HttpError::UnexpectedContentType(content_type.into()));
+        }
+        Ok(&self.body)
+    }
+}
+
+/// Parses a status line such as `HTTP/1.1 200 OK`.
+fn parse_status_line(line: &str) -> Result<(&str, u16, &str), HttpError> {
+    let invalid = || HttpError::InvalidStatusLine(line.into());
+    let (version, rest) = line.split_once(' ').ok_or_else(invalid)?;
+    if !version.starts_with("HTTP/") {
+        return Err(invalid());
+    }
+    if version != "HTTP/1.1" && version != "HTTP/1.0" {
+        return Err(HttpError::UnsupportedVersion(version.into()));
+    }
+    let (code, reason) = rest.split_once(' ').unwrap_or((rest, ""));
+    if code.len() != 3 || !code.bytes().all(|byte| byte.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let status = code.parse().map_err(|_| invalid())?;
+    Ok((version, status, reason))
+}
+
+/// Reads the `Content-Length` header, which may be repeated with the same value.
+fn content_length(headers: &[Header<'_>]) -> Result<Option<usize>, HttpError> {
+    let mut length = None;
+    for header in headers {
+        if !header.name.eq_ignore_ascii_case("content-length") {
+            continue;
+        }
+        let value = header.value;
+        let parsed = value
+            .bytes()
+            .all(|byte| byte.is_ascii_digit())
+            .then(|| value.parse::<usize>().ok())
+            .flatten()
+            .ok_or_else(|| HttpError::InvalidContentLength(value.into()))?;
+        if length.is_some_and(|length| length != parsed) {
+            return Err(HttpError::InvalidContentLength(value.into()));
+        }
+        length = Some(parsed);
+    }
+    Ok(length)
+}
+
+/// Decodes a chunked body, up to and including its last chunk and trailers.
+///
+/// Chunk sizes count bytes rather than characters.
+fn decode_chunked(rest: &str) -> Result<String, HttpError> {
+    let mut rest = rest.as_bytes();
+    let mut body = Vec::new();
+    loop {
+        let (size_line, after) = split_line(rest)
+            .ok_or_else(|| HttpError::InvalidChunk(String::from_utf8_lossy(rest).into()))?;
+        let size_line = core::str::from_utf8(size_line).unwrap_or_default();
+        // Chunk extensions follow the size and are ignored
+        let size = size_line.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size, 16)
+            .map_err(|_| HttpError::InvalidChunk(size_line.into()))?;
+        if size == 0 {
+            // Trailers are not used by the X API, they are skipped up to the final empty line
+            let mut trailers = after;
+            while !trailers.starts_with(b"\r\n") {
+                trailers = split_line(trailers)
+                    .ok_or(HttpError::TruncatedBody {
+                        expected: 2,
+                        received: trailers.len(),
+                    })?
+                    .1;
+            }
+            return String::from_utf8(body)
+                .map_err(|_| HttpError::InvalidChunk("body is not UTF-8".into()));
+        }
+
+        let data = after.get(..size).ok_or(HttpError::TruncatedBody {
+            expected: size,
+            received: after.len(),
+        })?;
+        body.extend_from_slice(data);
+        rest = after[size..]
+            .strip_prefix(b"\r\n")
+            .ok_or_else(|| HttpError::InvalidChunk(size_line.into()))?;
+    }
+}
+
+/// Splits the first line off, without its CRLF.
+fn split_line(bytes: &[u8]) -> Option<(&[u8], &[u8])> {
+    let end = bytes.windows(2).position(|window| window == b"\r\n")?;
+    Some((&bytes[..end], &bytes[end + 2..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::format;
+
+    const RESPONSE: &str = include_str!("../fixtures/tweet_response.txt");
+
+    /// A response with the head of the fixture and a body sent in `chunks`.
+    fn chunked_body(chunks: &[&str]) -> String {
+        let (head, _) = RESPONSE.split_once("\r\n\r\n").unwrap();
+        let head = head.replace("content-length: 740", "transfer-encoding: chunked");
+        let mut chunked = format!("{}\r\n\r\n", head);
+        for chunk in chunks {
+            chunked.push_str(&format!("{:x}\r\n{}\r\n", chunk.len(), chunk));
+        }
+        chunked.push_str("0\r\n\r\n");
+        chunked
+    }
+
+    /// The fixture response with its body sent in chunks of `size` bytes.
+    fn chunked(size: usize) -> String {
+        let body = RESPONSE.split_once("\r\n\r\n").unwrap().1;
+        let chunks: Vec<&str> = body
+            .as_bytes()
+            .chunks(size)
+            .map(|chunk| core::str::from_utf8(chunk).unwrap())
+            .collect();
+        chunked_body(&chunks)
+    }
+
+    #[test]
+    fn test_parse_notarized_response() {
+        let response = Response::parse(RESPONSE).unwrap();
+        assert_eq!(response.version, "HTTP/1.1");
+        assert_eq!(response.status, 200);
+        assert_eq!(response.reason, "OK");
+        assert_eq!(response.header("Content-Length"), Some("740"));
+        assert_eq!(
+            response
+                .headers
+                .iter()
+                .filter(|h| h.name == "set-cookie")
+                .count(),
+            4
+        );
+        assert_eq!(response.date(), Some(1_732_238_583_000));
+        assert!(response.expect_status(200).is_ok());
+
+        let body = response.json_body().unwrap();
+        assert_eq!(body.len(), 740);
+        assert!(body.starts_with("{\"data\":[{\"id\":\"1859567727305949191\""));
+        assert!(body.ends_with("\"name\":\"Stefan Smulders\"}]}}"));
+
+        // Data following the response is not part of its body
+        let trailing = format!("{}\r\n", RESPONSE);
+        assert_eq!(Response::parse(&trailing).unwrap().body, body);
+    }
+
+    #[test]
+    fn test_parse_chunked_response() {
+        let expected = Response::parse(RESPONSE).unwrap().body;
+        for size in [1, 7, 64, 740] {
+            let chunked = chunked(size);
+            let response = Response::parse(&chunked).unwrap();
+            assert_eq!(response.body, expected);
+            assert_eq!(response.json_body().unwrap(), expected);
+        }
+
+        // Sizes count bytes, not characters
+        let chunks = ["{\"text\":\"caf\u{e9}", " \u{1f9f5}", "\"}"];
+        let transcript = chunked_body(&chunks);
+        assert_eq!(Response::parse(&transcript).unwrap().body, chunks.concat());
+
+        let with_extension = chunked(740).replacen("2e4\r\n", "2e4;name=value\r\n", 1);
+        assert_eq!(Response::parse(&with_extension).unwrap().body, expected);
+        let with_trailer = chunked(740).replace("0\r\n\r\n", "0\r\nx-trailer: 1\r\n\r\n");
+        assert_eq!(Response::parse(&with_trailer).unwrap().body, expected);
+
+        let full = chunked(740);
+        assert!(matches!(
+            Response::parse(&full[..full.len() - 2]),
+            Err(HttpError::TruncatedBody { .. })
+        ));
+        assert!(matches!(
+            Response::parse(&full.replace("2e4\r\n", "2e5\r\n")),
+            Err(HttpError::InvalidChunk(_))
+        ));
+        assert!(matches!(
+            Response::parse(&full.replace("2e4\r\n", "zz\r\n")),
+            Err(HttpError::InvalidChunk(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_error_response() {
+        let not_found = "HTTP/1.1 200 OK\r\ncontent-type: application/json; charset=utf-8\r\ncontent-length: 75\r\n\r\n{\"errors\":[{\"detail\":\"Could not find tweet with ids: [1].\",\"title\":\"Not\"}]}";
+        let response = Response::parse(not_found).unwrap();
+        assert!(response.json_body().unwrap().starts_with("{\"errors\""));
+
+        let rate_limited = "HTTP/1.1 429 Too Many Requests\r\ncontent-type: application/json\r\ncontent-length: 2\r\n\r\n{}";
+        let response = Response::parse(rate_limited).unwrap();
+        assert_eq!(response.reason, "Too Many Requests");
+        assert_eq!(
+            response.expect_status(200),
+            Err(HttpError::UnexpectedStatus(429))
+        );
+
+        let html =
+            "HTTP/1.1 503 Service Unavailable\r\ncontent-type: text/html\r\n\r\n<html></html>";
+        let response = Response::parse(html).unwrap();
+        assert_eq!(response.body, "<html></html>");
+        assert_eq!(
+            response.json_body(),
+            Err(HttpError::UnexpectedContentType("text/html".into()))
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_response() {
+        let gzip = RESPONSE.replace(
+            "content-length: 740",
+            "content-encoding: gzip\r\ncontent-length: 740",
+        );
+        assert_eq!(
+            Response::parse(&gzip),
+            Err(HttpError::UnsupportedContentEncoding("gzip".into()))
+        );
+        let compressed =
+            RESPONSE.replace("content-length: 740", "transfer-encoding: gzip, chunked");
+        assert_eq!(
+            Response::parse(&compressed),
+            Err(HttpError::UnsupportedTransferEncoding(
+                "gzip, chunked".into()
+            ))
+        );
+        assert_eq!(
+            Response::parse(&RESPONSE.replace("content-length: 740", "content-length: 741")),
+            Err(HttpError::TruncatedBody {
+                expected: 741,
+                received: 740
+            })
+        );
+        assert_eq!(
+            Response::parse(&RESPONSE.replace("content-length: 740", "content-length: -1")),
+            Err(HttpError::InvalidContentLength("-1".into()))
+        );
+        assert_eq!(
+            Response::parse(&RESPONSE.replace(
+                "content-length: 740",
+                "content-length: 740\r\ncontent-length: 2"
+            )),
+            Err(HttpError::InvalidContentLength("2".into()))
+        );
+        assert_eq!(
+            Response::parse(&RESPONSE.replace("HTTP/1.1", "HTTP/2")),
+            Err(HttpError::UnsupportedVersion("HTTP/2".into()))
+        );
+        assert_eq!(
+            Response::parse(&RESPONSE.replace("200 OK", "2000 OK")),
+            Err(HttpError::InvalidStatusLine("HTTP/1.1 2000 OK".into()))
+        );
+        assert_eq!(
+            Response::parse(RESPONSE.split("\r\n\r\n").next().unwrap()),
+            Err(HttpError::IncompleteHead)
+        );
+    }
+}
@@ -1,12 +1,28 @@
 pub use sea_orm_migration::prelude::*;
 
 mod m20220101_000001_create_table;
+mod m20250201_000002_add_mint_request_state;
+mod m20250210_000003_add_notification_outcome;
+mod m20250215_000004_add_refund;
+mod m20250220_000005_create_event_tables;
+mod m20250220_000006_add_minter_account_id;
+mod m20250301_000007_create_indexer_checkpoint;
+mod m20250310_000008_key_requests_by_event;
 
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m20220101_000001_create_table::Migration)]
+        vec![
+            Box::new(m20220101_000001_create_table::Migration),
+            Box::new(m20250201_000002_add_mint_request_state::Migration),
+            Box::new(m20250210_000003_add_notification_outcome::Migration),
+            Box::new(m20250215_000004_add_refund::Migration),
+            Box::new(m20250220_000005_create_event_tables::Migration),
+            Box::new(m20250220_000006_add_minter_account_id::Migration),
+            Box::new(m20250301_000007_create_indexer_checkpoint::Migration),
+            Box::new(m20250310_000008_key_requests_by_event::Migration),
+        ]
     }
 }
@@ -0,0 +1,69 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Rows written before this migration were only inserted once the whole
+        // prove -> verify -> mint -> notify flow had succeeded.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(NearTransaction::Table)
+                    .add_column(string(NearTransaction::Status).default("notified"))
+                    .add_column(string_null(NearTransaction::FailureReason))
+                    .add_column(integer(NearTransaction::Attempts).default(0))
+                    .add_column(string_null(NearTransaction::NotaryPublicKey))
+                    .add_column(string_null(NearTransaction::VerifiedProof))
+                    .add_column(string_null(NearTransaction::VerifiedSignature))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx-near-transaction-status")
+                    .table(NearTransaction::Table)
+                    .col(NearTransaction::Status)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(Index::drop().name("idx-near-transaction-status").to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(NearTransaction::Table)
+                    .drop_column(NearTransaction::Status)
+                    .drop_column(NearTransaction::FailureReason)
+                    .drop_column(NearTransaction::Attempts)
+                    .drop_column(NearTransaction::NotaryPublicKey)
+                    .drop_column(NearTransaction::VerifiedProof)
+                    .drop_column(NearTransaction::VerifiedSignature)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum NearTransaction {
+    Table,
+    Status,
+    FailureReason,
+    Attempts,
+    NotaryPublicKey,
+    VerifiedProof,
+    VerifiedSignature,
+}
@@ -0,0 +1,41 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(NearTransaction::Table)
+                    .add_column(string_null(NearTransaction::NotificationChannel))
+                    .add_column(string_null(NearTransaction::NotificationStatus))
+                    .add_column(text_null(NearTransaction::NotificationDetail))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(NearTransaction::Table)
+                    .drop_column(NearTransaction::NotificationChannel)
+                    .drop_column(NearTransaction::NotificationStatus)
+                    .drop_column(NearTransaction::NotificationDetail)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum NearTransaction {
+    Table,
+    NotificationChannel,
+    NotificationStatus,
+    NotificationDetail,
+}
@@ -0,0 +1,39 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Amounts are in yoctoNEAR, which do not fit in a bigint
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(NearTransaction::Table)
+                    .add_column(string_null(NearTransaction::RefundTransactionHash))
+                    .add_column(string_null(NearTransaction::RefundedAmount))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(NearTransaction::Table)
+                    .drop_column(NearTransaction::RefundTransactionHash)
+                    .drop_column(NearTransaction::RefundedAmount)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum NearTransaction {
+    Table,
+    RefundTransactionHash,
+    RefundedAmount,
+}
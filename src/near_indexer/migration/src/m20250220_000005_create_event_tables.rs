@@ -0,0 +1,172 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Every table holds the events of one type, identified by the receipt that emitted them
+        // and the position of their log. Amounts are in yoctoNEAR, which do not fit in a bigint.
+        manager
+            .create_table(
+                Table::create()
+                    .table(TweetMintRequestEvent::Table)
+                    .if_not_exists()
+                    .col(pk_auto(TweetMintRequestEvent::Id))
+                    .col(string(TweetMintRequestEvent::ReceiptId))
+                    .col(integer(TweetMintRequestEvent::LogIndex))
+                    .col(string(TweetMintRequestEvent::TransactionHash))
+                    .col(big_integer(TweetMintRequestEvent::BlockHeight))
+                    .col(string(TweetMintRequestEvent::BlockTimestamp))
+                    .col(string(TweetMintRequestEvent::TweetId))
+                    .col(string(TweetMintRequestEvent::Account))
+                    .col(string(TweetMintRequestEvent::Deposit))
+                    .col(string(TweetMintRequestEvent::ImageUrl))
+                    .col(string(TweetMintRequestEvent::Notify))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx-tweet-mint-request-event-log")
+                    .table(TweetMintRequestEvent::Table)
+                    .col(TweetMintRequestEvent::ReceiptId)
+                    .col(TweetMintRequestEvent::LogIndex)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(CancelMintRequestEvent::Table)
+                    .if_not_exists()
+                    .col(pk_auto(CancelMintRequestEvent::Id))
+                    .col(string(CancelMintRequestEvent::ReceiptId))
+                    .col(integer(CancelMintRequestEvent::LogIndex))
+                    .col(string(CancelMintRequestEvent::TransactionHash))
+                    .col(big_integer(CancelMintRequestEvent::BlockHeight))
+                    .col(string(CancelMintRequestEvent::BlockTimestamp))
+                    .col(string(CancelMintRequestEvent::TweetId))
+                    .col(string(CancelMintRequestEvent::Account))
+                    .col(string(CancelMintRequestEvent::Withdraw))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx-cancel-mint-request-event-log")
+                    .table(CancelMintRequestEvent::Table)
+                    .col(CancelMintRequestEvent::ReceiptId)
+                    .col(CancelMintRequestEvent::LogIndex)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // An `nft_mint` event can hold several tokens, each one is stored in its own row
+        manager
+            .create_table(
+                Table::create()
+                    .table(NftMintEvent::Table)
+                    .if_not_exists()
+                    .col(pk_auto(NftMintEvent::Id))
+                    .col(string(NftMintEvent::ReceiptId))
+                    .col(integer(NftMintEvent::LogIndex))
+                    .col(string(NftMintEvent::TransactionHash))
+                    .col(big_integer(NftMintEvent::BlockHeight))
+                    .col(string(NftMintEvent::BlockTimestamp))
+                    .col(string(NftMintEvent::OwnerId))
+                    .col(string(NftMintEvent::TokenId))
+                    .col(string_null(NftMintEvent::Memo))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx-nft-mint-event-log")
+                    .table(NftMintEvent::Table)
+                    .col(NftMintEvent::ReceiptId)
+                    .col(NftMintEvent::LogIndex)
+                    .col(NftMintEvent::TokenId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(NftMintEvent::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(CancelMintRequestEvent::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(TweetMintRequestEvent::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum TweetMintRequestEvent {
+    Table,
+    Id,
+    ReceiptId,
+    LogIndex,
+    TransactionHash,
+    BlockHeight,
+    BlockTimestamp,
+    TweetId,
+    Account,
+    Deposit,
+    ImageUrl,
+    Notify,
+}
+
+#[derive(DeriveIden)]
+enum CancelMintRequestEvent {
+    Table,
+    Id,
+    ReceiptId,
+    LogIndex,
+    TransactionHash,
+    BlockHeight,
+    BlockTimestamp,
+    TweetId,
+    Account,
+    Withdraw,
+}
+
+#[derive(DeriveIden)]
+enum NftMintEvent {
+    Table,
+    Id,
+    ReceiptId,
+    LogIndex,
+    TransactionHash,
+    BlockHeight,
+    BlockTimestamp,
+    OwnerId,
+    TokenId,
+    Memo,
+}
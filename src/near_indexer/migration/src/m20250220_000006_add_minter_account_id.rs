@@ -0,0 +1,36 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Rows written before this migration were direct calls, where the minter is the signer
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(NearTransaction::Table)
+                    .add_column(string_null(NearTransaction::MinterAccountId))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(NearTransaction::Table)
+                    .drop_column(NearTransaction::MinterAccountId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum NearTransaction {
+    Table,
+    MinterAccountId,
+}
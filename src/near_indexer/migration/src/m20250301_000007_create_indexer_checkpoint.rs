@@ -0,0 +1,56 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // One row per processed block and transaction source, the latest one is where the
+        // indexer resumes from. Earlier rows are kept to rewind to when a block is orphaned.
+        manager
+            .create_table(
+                Table::create()
+                    .table(IndexerCheckpoint::Table)
+                    .if_not_exists()
+                    .col(pk_auto(IndexerCheckpoint::Id))
+                    .col(string(IndexerCheckpoint::Source))
+                    .col(big_integer(IndexerCheckpoint::Cursor))
+                    .col(big_integer(IndexerCheckpoint::BlockHeight))
+                    .col(string(IndexerCheckpoint::BlockHash))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx-indexer-checkpoint-block")
+                    .table(IndexerCheckpoint::Table)
+                    .col(IndexerCheckpoint::Source)
+                    .col(IndexerCheckpoint::BlockHeight)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(IndexerCheckpoint::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum IndexerCheckpoint {
+    Table,
+    Id,
+    Source,
+    Cursor,
+    BlockHeight,
+    BlockHash,
+}
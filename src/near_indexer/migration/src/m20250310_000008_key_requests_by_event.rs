@@ -0,0 +1,82 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // A transaction can make several mint requests, each one is recorded with the position
+        // of its event in the transaction. Rows written before this migration were the first.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(NearTransaction::Table)
+                    .add_column(integer(NearTransaction::EventIndex).default(0))
+                    .to_owned(),
+            )
+            .await?;
+
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "ALTER TABLE near_transaction DROP CONSTRAINT IF EXISTS near_transaction_transaction_hash_key",
+        )
+        .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx-near-transaction-event")
+                    .table(NearTransaction::Table)
+                    .col(NearTransaction::TransactionHash)
+                    .col(NearTransaction::EventIndex)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // Ids were the id of the transaction, requests now get one from the database, after the
+        // ids already recorded
+        db.execute_unprepared(
+            "ALTER TABLE near_transaction ALTER COLUMN id ADD GENERATED BY DEFAULT AS IDENTITY",
+        )
+        .await?;
+        db.execute_unprepared(
+            "SELECT setval(pg_get_serial_sequence('near_transaction', 'id'), COALESCE(MAX(id), 0) + 1, false) FROM near_transaction",
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Only the first request of each transaction fits the previous layout
+        let db = manager.get_connection();
+        db.execute_unprepared("DELETE FROM near_transaction WHERE event_index > 0")
+            .await?;
+        db.execute_unprepared("ALTER TABLE near_transaction ALTER COLUMN id DROP IDENTITY")
+            .await?;
+        manager
+            .drop_index(Index::drop().name("idx-near-transaction-event").to_owned())
+            .await?;
+        db.execute_unprepared(
+            "ALTER TABLE near_transaction ADD CONSTRAINT near_transaction_transaction_hash_key UNIQUE (transaction_hash)",
+        )
+        .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(NearTransaction::Table)
+                    .drop_column(NearTransaction::EventIndex)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum NearTransaction {
+    Table,
+    TransactionHash,
+    EventIndex,
+}
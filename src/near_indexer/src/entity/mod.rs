@@ -2,4 +2,8 @@
 
 pub mod prelude;
 
+pub mod cancel_mint_request_event;
+pub mod indexer_checkpoint;
 pub mod near_transaction;
+pub mod nft_mint_event;
+pub mod tweet_mint_request_event;
@@ -5,9 +5,8 @@ use sea_orm::entity::prelude::*;
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
 #[sea_orm(schema_name = "public", table_name = "near_transaction")]
 pub struct Model {
-    #[sea_orm(primary_key, auto_increment = false)]
+    #[sea_orm(primary_key)]
     pub id: i64,
-    #[sea_orm(unique)]
     pub transaction_hash: String,
     pub signer_account_id: String,
     pub receiver_account_id: String,
@@ -22,6 +21,20 @@ pub struct Model {
     pub user_to_notify: Option<String>,
     pub notarized_proof: Option<String>,
     pub zk_proof: Option<String>,
+    pub status: String,
+    pub failure_reason: Option<String>,
+    pub attempts: i32,
+    pub notary_public_key: Option<String>,
+    pub verified_proof: Option<String>,
+    pub verified_signature: Option<String>,
+    pub notification_channel: Option<String>,
+    pub notification_status: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub notification_detail: Option<String>,
+    pub refund_transaction_hash: Option<String>,
+    pub refunded_amount: Option<String>,
+    pub minter_account_id: Option<String>,
+    pub event_index: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -1,3 +1,7 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.0.1
 
+pub use super::cancel_mint_request_event::Entity as CancelMintRequestEvent;
+pub use super::indexer_checkpoint::Entity as IndexerCheckpoint;
 pub use super::near_transaction::Entity as NearTransaction;
+pub use super::nft_mint_event::Entity as NftMintEvent;
+pub use super::tweet_mint_request_event::Entity as TweetMintRequestEvent;
@@ -0,0 +1,640 @@
+use near_jsonrpc_client::JsonRpcClient;
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::serde::json::Json;
+use rocket::{get, post, routes, State};
+use sea_orm::sea_query::Expr;
+use sea_orm::{ColumnTrait, DbConn, DbErr, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tracing::{error, info};
+
+use super::job_queue::{record_notification, retry_status, JobContext, MintStatus};
+use super::notifier::{format_near, Notification};
+use crate::entity::{indexer_checkpoint, near_transaction};
+
+/// Number of requests returned by `GET /requests` when no `limit` is given.
+pub const DEFAULT_PAGE_SIZE: u64 = 50;
+
+/// Largest `limit` accepted by `GET /requests`.
+pub const MAX_PAGE_SIZE: u64 = 500;
+
+/// Time given to the prover to answer the health check.
+const PROVER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Reason stored on requests skipped through the admin API.
+pub const SKIPPED_REASON: &str = "skipped by an operator";
+
+/// The groups mint requests are listed and counted by on the admin API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestGroup {
+    /// Indexed, but not picked up by a worker yet.
+    Pending,
+    /// Between the first and the last stage.
+    InFlight,
+    /// Marked as failed, they can be retried or re-notified.
+    Failed,
+    /// The NFT has been minted, whether or not the user has been notified yet.
+    Minted,
+    /// The deposit has been refunded by the contract.
+    Refunded,
+}
+
+impl RequestGroup {
+    pub const ALL: [RequestGroup; 5] = [
+        RequestGroup::Pending,
+        RequestGroup::InFlight,
+        RequestGroup::Failed,
+        RequestGroup::Minted,
+        RequestGroup::Refunded,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RequestGroup::Pending => "pending",
+            RequestGroup::InFlight => "in_flight",
+            RequestGroup::Failed => "failed",
+            RequestGroup::Minted => "minted",
+            RequestGroup::Refunded => "refunded",
+        }
+    }
+
+    /// The values of the `status` column in the group.
+    pub fn statuses(&self) -> Vec<&'static str> {
+        match self {
+            RequestGroup::Pending => vec![MintStatus::Discovered.as_str()],
+            RequestGroup::InFlight => vec![
+                MintStatus::Notarized.as_str(),
+                MintStatus::RemotelyVerified.as_str(),
+                MintStatus::MintSubmitted.as_str(),
+            ],
+            RequestGroup::Failed => vec![MintStatus::Failed(String::new()).as_str()],
+            RequestGroup::Minted => {
+                vec![MintStatus::Minted.as_str(), MintStatus::Notified.as_str()]
+            }
+            RequestGroup::Refunded => vec![MintStatus::Refunded(String::new()).as_str()],
+        }
+    }
+
+    /// The group of a value of the `status` column.
+    pub fn of(status: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|group| group.statuses().contains(&status))
+    }
+}
+
+impl FromStr for RequestGroup {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|group| group.as_str() == s)
+            .ok_or(format!("Invalid request group: {}", s))
+    }
+}
+
+/// Whether an `Authorization` header carries the admin token.
+///
+/// The token is compared in constant time.
+pub fn is_authorized(token: &str, header: &str) -> bool {
+    let provided = match header.strip_prefix("Bearer ") {
+        Some(provided) => provided.trim().as_bytes(),
+        None => return false,
+    };
+    let expected = token.as_bytes();
+    if token.is_empty() || provided.len() != expected.len() {
+        return false;
+    }
+    provided
+        .iter()
+        .zip(expected)
+        .fold(0, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
+
+/// What the admin API reads and acts on, shared with the indexer loop.
+pub struct AdminState {
+    pub db: DbConn,
+    pub rpc_client: JsonRpcClient,
+    pub context: Arc<JobContext>,
+    /// The transaction source whose checkpoint is reported as the cursor.
+    pub source: String,
+    pub prover_url: String,
+    token: Option<String>,
+}
+
+impl AdminState {
+    /// Creates the state of the admin API, reading the token from `ADMIN_API_TOKEN`.
+    ///
+    /// Without a token the actions are rejected and only the read endpoints are available.
+    pub fn new(
+        db: DbConn,
+        rpc_client: JsonRpcClient,
+        context: Arc<JobContext>,
+        source: &str,
+    ) -> Self {
+        AdminState {
+            db,
+            rpc_client,
+            context,
+            source: source.to_string(),
+            prover_url: env::var("VERITY_PROVER_URL").expect("VERITY_PROVER_URL must be set"),
+            token: env::var("ADMIN_API_TOKEN")
+                .ok()
+                .filter(|token| !token.is_empty()),
+        }
+    }
+}
+
+/// Guards the actions of the admin API with the `ADMIN_API_TOKEN` bearer token.
+pub struct AdminToken;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AdminToken {
+    type Error = &'static str;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let token = request
+            .rocket()
+            .state::<AdminState>()
+            .and_then(|state| state.token.as_deref());
+        let header = request.headers().get_one("Authorization");
+
+        match (token, header) {
+            (Some(token), Some(header)) if is_authorized(token, header) => {
+                Outcome::Success(AdminToken)
+            }
+            (None, _) => Outcome::Error((Status::Unauthorized, "ADMIN_API_TOKEN is not set")),
+            _ => Outcome::Error((Status::Unauthorized, "Invalid admin token")),
+        }
+    }
+}
+
+type ApiResult<T> = Result<Json<T>, (Status, String)>;
+
+fn internal_error(err: DbErr) -> (Status, String) {
+    error!("admin-api-error: {:?}", err);
+    (Status::InternalServerError, err.to_string())
+}
+
+/// A mint request as returned by the admin API, without its proofs.
+#[derive(Debug, Serialize)]
+pub struct RequestSummary {
+    pub id: i64,
+    pub transaction_hash: String,
+    pub block_height: i32,
+    pub block_timestamp: String,
+    pub tweet_id: String,
+    pub minter_account_id: Option<String>,
+    pub group: Option<&'static str>,
+    pub status: String,
+    pub failure_reason: Option<String>,
+    pub attempts: i32,
+    pub mint_transaction_hash: Option<String>,
+    pub refund_transaction_hash: Option<String>,
+    pub refunded_amount: Option<String>,
+    pub user_to_notify: Option<String>,
+    pub notification_channel: Option<String>,
+    pub notification_status: Option<String>,
+    pub notification_detail: Option<String>,
+}
+
+impl From<near_transaction::Model> for RequestSummary {
+    fn from(model: near_transaction::Model) -> Self {
+        RequestSummary {
+            id: model.id,
+            transaction_hash: model.transaction_hash,
+            block_height: model.block_height,
+            block_timestamp: model.block_timestamp,
+            tweet_id: model.tweet_id,
+            minter_account_id: model.minter_account_id,
+            group: RequestGroup::of(&model.status).map(|group| group.as_str()),
+            status: model.status,
+            failure_reason: model.failure_reason,
+            attempts: model.attempts,
+            mint_transaction_hash: model.mint_transaction_hash,
+            refund_transaction_hash: model.refund_transaction_hash,
+            refunded_amount: model.refunded_amount,
+            user_to_notify: model.user_to_notify,
+            notification_channel: model.notification_channel,
+            notification_status: model.notification_status,
+            notification_detail: model.notification_detail,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CursorStatus {
+    pub source: String,
+    pub cursor: Option<i64>,
+    pub block_height: Option<i64>,
+    pub block_hash: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SignerStatus {
+    pub account_id: String,
+    /// The balance in yoctoNEAR, as a string to keep its precision.
+    pub balance: Option<String>,
+    pub balance_near: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProverStatus {
+    pub url: String,
+    pub healthy: bool,
+    pub detail: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IndexerStatus {
+    pub cursor: CursorStatus,
+    /// Number of mint requests per group.
+    pub requests: BTreeMap<&'static str, u64>,
+    pub signer: SignerStatus,
+    pub prover: ProverStatus,
+}
+
+async fn prover_status(url: &str) -> ProverStatus {
+    let response = reqwest::Client::new()
+        .get(url)
+        .timeout(PROVER_TIMEOUT)
+        .send()
+        .await;
+
+    // The prover has no dedicated health route, any answer but a server error will do
+    let (healthy, detail) = match response {
+        Ok(response) => (
+            !response.status().is_server_error(),
+            response.status().to_string(),
+        ),
+        Err(err) => (false, err.to_string()),
+    };
+    ProverStatus {
+        url: url.to_string(),
+        healthy,
+        detail,
+    }
+}
+
+/// Reports the cursor of the indexer, the number of requests per group, the balance of the
+/// signer account and whether the prover is reachable.
+#[get("/status")]
+async fn status(state: &State<AdminState>) -> ApiResult<IndexerStatus> {
+    let checkpoint = indexer_checkpoint::Entity::find()
+        .filter(indexer_checkpoint::Column::Source.eq(state.source.as_str()))
+        .order_by_desc(indexer_checkpoint::Column::BlockHeight)
+        .one(&state.db)
+        .await
+        .map_err(internal_error)?;
+
+    let counts: Vec<(String, i64)> = near_transaction::Entity::find()
+        .select_only()
+        .column(near_transaction::Column::Status)
+        .column_as(near_transaction::Column::Id.count(), "count")
+        .group_by(near_transaction::Column::Status)
+        .into_tuple()
+        .all(&state.db)
+        .await
+        .map_err(internal_error)?;
+    let mut requests: BTreeMap<&'static str, u64> = RequestGroup::ALL
+        .iter()
+        .map(|group| (group.as_str(), 0))
+        .collect();
+    for (status, count) in counts {
+        if let Some(group) = RequestGroup::of(&status) {
+            *requests.entry(group.as_str()).or_default() += count as u64;
+        }
+    }
+
+    let signer = &state.context.signer;
+    let signer = match signer.balance(&state.rpc_client).await {
+        Ok(balance) => SignerStatus {
+            account_id: signer.account_id.to_string(),
+            balance: Some(balance.to_string()),
+            balance_near: Some(format_near(balance)),
+            error: None,
+        },
+        Err(err) => SignerStatus {
+            account_id: signer.account_id.to_string(),
+            balance: None,
+            balance_near: None,
+            error: Some(err.to_string()),
+        },
+    };
+
+    Ok(Json(IndexerStatus {
+        cursor: CursorStatus {
+            source: state.source.clone(),
+            cursor: checkpoint.as_ref().map(|checkpoint| checkpoint.cursor),
+            block_height: checkpoint
+                .as_ref()
+                .map(|checkpoint| checkpoint.block_height),
+            block_hash: checkpoint.map(|checkpoint| checkpoint.block_hash),
+        },
+        requests,
+        signer,
+        prover: prover_status(&state.prover_url).await,
+    }))
+}
+
+/// Lists mint requests, newest first, optionally restricted to a group.
+#[get("/requests?<group>&<limit>&<offset>")]
+async fn list_requests(
+    state: &State<AdminState>,
+    group: Option<&str>,
+    limit: Option<u64>,
+    offset: Option<u64>,
+) -> ApiResult<Vec<RequestSummary>> {
+    let mut query = near_transaction::Entity::find();
+    if let Some(group) = group {
+        let group = group
+            .parse::<RequestGroup>()
+            .map_err(|e| (Status::BadRequest, e))?;
+        query = query.filter(near_transaction::Column::Status.is_in(group.statuses()));
+    }
+
+    let requests = query
+        .order_by_desc(near_transaction::Column::Id)
+        .limit(limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE))
+        .offset(offset.unwrap_or(0))
+        .all(&state.db)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(
+        requests.into_iter().map(RequestSummary::from).collect(),
+    ))
+}
+
+async fn find_request(
+    state: &AdminState,
+    id: i64,
+) -> Result<near_transaction::Model, (Status, String)> {
+    near_transaction::Entity::find_by_id(id)
+        .one(&state.db)
+        .await
+        .map_err(internal_error)?
+        .ok_or((Status::NotFound, format!("Mint request {} not found", id)))
+}
+
+#[get("/requests/<id>")]
+async fn get_request(state: &State<AdminState>, id: i64) -> ApiResult<RequestSummary> {
+    Ok(Json(find_request(state, id).await?.into()))
+}
+
+/// Moves a request to `status`, provided it is still in the status it was read in.
+///
+/// This keeps two operators, or an operator and a worker, from both acting on a request.
+async fn transition(
+    state: &AdminState,
+    request: &near_transaction::Model,
+    status: &MintStatus,
+) -> ApiResult<RequestSummary> {
+    let failure_reason = match status {
+        MintStatus::Failed(reason) => Some(reason.clone()),
+        _ => None,
+    };
+    let updated = near_transaction::Entity::update_many()
+        .col_expr(
+            near_transaction::Column::Status,
+            Expr::value(status.as_str()),
+        )
+        .col_expr(near_transaction::Column::Attempts, Expr::value(0))
+        .col_expr(
+            near_transaction::Column::FailureReason,
+            Expr::value(failure_reason),
+        )
+        .filter(near_transaction::Column::Id.eq(request.id))
+        .filter(near_transaction::Column::Status.eq(request.status.as_str()))
+        .exec(&state.db)
+        .await
+        .map_err(internal_error)?;
+    if updated.rows_affected == 0 {
+        return Err((
+            Status::Conflict,
+            format!("Mint request {} changed, try again", request.id),
+        ));
+    }
+
+    info!(
+        "admin: mint request {} of {}: {} -> {}",
+        request.id,
+        request.tweet_id,
+        request.status,
+        status.as_str()
+    );
+    Ok(Json(find_request(state, request.id).await?.into()))
+}
+
+/// Puts a failed request back in the queue, from the last stage it completed.
+///
+/// It is picked up by the worker pool on the next pass of the indexer.
+#[post("/requests/<id>/retry")]
+async fn retry_request(
+    _token: AdminToken,
+    state: &State<AdminState>,
+    id: i64,
+) -> ApiResult<RequestSummary> {
+    let request = find_request(state, id).await?;
+    match MintStatus::from_model(&request).map_err(|e| (Status::InternalServerError, e))? {
+        MintStatus::Failed(_) => transition(state, &request, &retry_status(&request)).await,
+        status => Err((
+            Status::Conflict,
+            format!(
+                "Only failed requests can be retried, {} is {}",
+                id,
+                status.as_str()
+            ),
+        )),
+    }
+}
+
+/// Marks a request that has not completed as failed, so the workers leave it alone.
+///
+/// The user is not notified.
+#[post("/requests/<id>/skip")]
+async fn skip_request(
+    _token: AdminToken,
+    state: &State<AdminState>,
+    id: i64,
+) -> ApiResult<RequestSummary> {
+    let request = find_request(state, id).await?;
+    let status = MintStatus::from_model(&request).map_err(|e| (Status::InternalServerError, e))?;
+    if status.is_terminal() {
+        return Err((
+            Status::Conflict,
+            format!("Mint request {} is already {}", id, status.as_str()),
+        ));
+    }
+    transition(
+        state,
+        &request,
+        &MintStatus::Failed(SKIPPED_REASON.to_string()),
+    )
+    .await
+}
+
+/// The notification matching the status of a request, if the user was told about it.
+pub fn notification_for(
+    context: &JobContext,
+    request: &near_transaction::Model,
+) -> Result<Option<Notification>, String> {
+    let templates = &context.notifiers.templates;
+    Ok(match MintStatus::from_model(request)? {
+        MintStatus::Minted | MintStatus::Notified => Some(templates.minted(&request.tweet_id)),
+        MintStatus::Failed(reason) => Some(templates.failed(&request.tweet_id, &reason)),
+        MintStatus::Refunded(reason) => {
+            let amount = request
+                .refunded_amount
+                .as_deref()
+                .unwrap_or("0")
+                .parse::<u128>()
+                .map_err(|e| format!("Invalid refunded amount: {}", e))?;
+            Some(templates.refunded(&request.tweet_id, amount, &reason))
+        }
+        _ => None,
+    })
+}
+
+/// Sends the notification matching the status of a request again.
+///
+/// The outcome is recorded on the request. The status is not changed, so a minted request
+/// whose notification kept failing is still notified by the workers if the user is reached.
+#[post("/requests/<id>/renotify")]
+async fn renotify_request(
+    _token: AdminToken,
+    state: &State<AdminState>,
+    id: i64,
+) -> ApiResult<RequestSummary> {
+    let request = find_request(state, id).await?;
+    let notify = request
+        .user_to_notify
+        .clone()
+        .filter(|notify| !notify.is_empty())
+        .ok_or((
+            Status::Conflict,
+            format!("Mint request {} has no user to notify", id),
+        ))?;
+    let notification = notification_for(&state.context, &request)
+        .map_err(|e| (Status::InternalServerError, e))?
+        .ok_or((
+            Status::Conflict,
+            format!(
+                "Mint request {} is {}, there is nothing to notify yet",
+                id, request.status
+            ),
+        ))?;
+
+    let outcome = state.context.notifiers.notify(&notify, &notification).await;
+    record_notification(&state.db, id, &outcome)
+        .await
+        .map_err(internal_error)?;
+    info!(
+        "admin: re-notified {} of {}: {}",
+        id,
+        request.tweet_id,
+        outcome.status()
+    );
+    Ok(Json(find_request(state, id).await?.into()))
+}
+
+/// Where the admin API listens, read from `ADMIN_API_PORT` and `ADMIN_API_ADDRESS`.
+#[derive(Debug, Clone)]
+pub struct AdminApi {
+    pub address: IpAddr,
+    pub port: u16,
+}
+
+impl AdminApi {
+    /// Reads the address to listen on, returning `None` when `ADMIN_API_PORT` is not set.
+    ///
+    /// `ADMIN_API_ADDRESS` defaults to `127.0.0.1`, so the API is only reachable from the host.
+    pub fn from_env() -> Option<Self> {
+        let port = env::var("ADMIN_API_PORT").ok()?;
+        Some(AdminApi {
+            address: env::var("ADMIN_API_ADDRESS")
+                .unwrap_or("127.0.0.1".to_owned())
+                .parse()
+                .expect("ADMIN_API_ADDRESS must be an IP address"),
+            port: port.parse().expect("ADMIN_API_PORT must be a number"),
+        })
+    }
+
+    /// Builds the Rocket application serving the admin API.
+    pub fn build(&self, state: AdminState) -> rocket::Rocket<rocket::Build> {
+        let config = rocket::Config {
+            address: self.address,
+            port: self.port,
+            // The indexer handles signals itself
+            shutdown: rocket::config::Shutdown {
+                ctrlc: false,
+                #[cfg(unix)]
+                signals: Default::default(),
+                ..Default::default()
+            },
+            ..rocket::Config::release_default()
+        };
+
+        rocket::custom(config).manage(state).mount(
+            "/",
+            routes![
+                status,
+                list_requests,
+                get_request,
+                retry_request,
+                skip_request,
+                renotify_request
+            ],
+        )
+    }
+
+    /// Serves the admin API on a thread of its own, next to the indexer loop.
+    pub fn spawn(self, state: AdminState) -> thread::JoinHandle<()> {
+        info!("Serving the admin API on {}:{}", self.address, self.port);
+        let rocket = self.build(state);
+        thread::spawn(move || {
+            if let Err(err) = rocket::execute(rocket.launch()) {
+                error!("admin-api-error: {:?}", err);
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_groups() {
+        for group in RequestGroup::ALL {
+            assert_eq!(group.as_str().parse::<RequestGroup>(), Ok(group));
+            for status in group.statuses() {
+                assert_eq!(RequestGroup::of(status), Some(group));
+            }
+        }
+        assert_eq!(RequestGroup::of("notified"), Some(RequestGroup::Minted));
+        assert_eq!(
+            RequestGroup::of("mint_submitted"),
+            Some(RequestGroup::InFlight)
+        );
+        assert!("done".parse::<RequestGroup>().is_err());
+    }
+
+    #[test]
+    fn test_is_authorized() {
+        assert!(is_authorized("secret", "Bearer secret"));
+        assert!(!is_authorized("secret", "Bearer secreT"));
+        assert!(!is_authorized("secret", "Bearer secret2"));
+        assert!(!is_authorized("secret", "secret"));
+        assert!(!is_authorized("", "Bearer "));
+    }
+}
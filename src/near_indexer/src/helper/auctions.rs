@@ -0,0 +1,115 @@
+use near_client::prelude::{AccountId, Finality};
+use near_jsonrpc_client::JsonRpcClient;
+use serde::Deserialize;
+use serde_json::json;
+use std::error::Error;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::info;
+
+use super::job_queue::JobContext;
+
+/// Number of auctions fetched per `get_auctions` call.
+const PAGE_SIZE: u64 = 50;
+
+/// An auction as returned by the `get_auctions` view of the NFT contract.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct OpenAuction {
+    pub tweet_id: String,
+    pub bidder: String,
+    /// The highest bid, in yoctoNEAR.
+    pub deposit: u128,
+    /// When the bidding window closes, in ms.
+    pub ends_at: u64,
+}
+
+/// The auctions whose bidding window has closed at `now_ms`, and can be settled.
+pub fn closed_auctions(auctions: Vec<OpenAuction>, now_ms: u64) -> Vec<OpenAuction> {
+    auctions
+        .into_iter()
+        .filter(|auction| auction.ends_at <= now_ms)
+        .collect()
+}
+
+/// Settles the auctions of the NFT contract whose bidding window has closed.
+///
+/// Settling turns the highest bid into a mint request, which is then indexed like any other
+/// through the `tweet_mint_request` event of the settlement transaction.
+///
+/// # Returns
+///
+/// The number of settlement transactions submitted.
+pub async fn settle_closed_auctions(
+    context: &JobContext,
+    rpc_client: &JsonRpcClient,
+    contract_id: &str,
+) -> Result<usize, Box<dyn Error>> {
+    let view_contract_id = AccountId::from_str(contract_id)?;
+    let mut auctions = Vec::new();
+    loop {
+        let page = context
+            .client
+            .view::<Vec<OpenAuction>>(
+                &view_contract_id,
+                Finality::Final,
+                "get_auctions",
+                Some(json!({
+                    "from_index": auctions.len().to_string(),
+                    "limit": PAGE_SIZE,
+                })),
+            )
+            .await
+            .map_err(|e| format!("Failed to fetch auctions: {:?}", e))?
+            .data();
+        let fetched = page.len() as u64;
+        auctions.extend(page);
+        if fetched < PAGE_SIZE {
+            break;
+        }
+    }
+
+    let now_ms = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64;
+    let closed = closed_auctions(auctions, now_ms);
+    for auction in &closed {
+        let submitted = context
+            .signer
+            .submit_function_call(
+                rpc_client,
+                contract_id.parse()?,
+                "settle_auction",
+                json!({ "tweet_id": auction.tweet_id })
+                    .to_string()
+                    .into_bytes(),
+                100_000_000_000_000, // 100 TeraGas
+                0,
+            )
+            .await?;
+        info!(
+            "Settling the auction of {} won by {}: {}",
+            auction.tweet_id, auction.bidder, submitted.hash
+        );
+    }
+
+    Ok(closed.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_closed_auctions() {
+        let auctions: Vec<OpenAuction> = serde_json::from_str(
+            r#"[
+                {"tweet_id":"1","bidder":"alice.testnet","deposit":1000,"image_url":"","notify":"","ends_at":1000,"bid_count":1},
+                {"tweet_id":"2","bidder":"bob.testnet","deposit":2000,"image_url":"","notify":"","ends_at":2000,"bid_count":3}
+            ]"#,
+        )
+        .unwrap();
+
+        let closed = closed_auctions(auctions.clone(), 1000);
+        assert_eq!(closed, vec![auctions[0].clone()]);
+        assert_eq!(closed_auctions(auctions.clone(), 999), vec![]);
+        assert_eq!(closed_auctions(auctions, 2000).len(), 2);
+    }
+}
@@ -0,0 +1,366 @@
+use rs_merkle::{algorithms::Sha256, Hasher, MerkleTree};
+use sea_orm::ActiveValue::Set;
+use sea_orm::{ActiveModelTrait, ColumnTrait, DbConn, DbErr, EntityTrait, QueryFilter, QueryOrder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::env;
+use tracing::{info, warn};
+
+use super::cktls::{notary_threshold, submit_mint_batch, verify_remotely_batch};
+use super::cktls::{NotarizedTweet, VerifyError};
+use super::events::EVENT_LOG_PREFIX;
+use super::job_queue::{mint_owner, record_failure, JobContext, MintStatus};
+use crate::entity::near_transaction;
+
+/// Most tweets the verifier contract mints in one batch.
+pub const MAX_BATCH_SIZE: usize = 8;
+
+/// Reads how many notarized requests are verified and minted together from `MINT_BATCH_SIZE`.
+///
+/// Defaults to 1, which mints every request with its own transaction.
+pub fn batch_size_from_env() -> usize {
+    env::var("MINT_BATCH_SIZE")
+        .map(|value| {
+            value
+                .parse::<usize>()
+                .expect("MINT_BATCH_SIZE must be a number")
+        })
+        .unwrap_or(1)
+        .clamp(1, MAX_BATCH_SIZE)
+}
+
+/// The batch size to mint with under the notary threshold of the verifier contract.
+///
+/// The remote verifier signs the root of a batch with a single key, and batches carry no
+/// co-signatures. Once the contract requires more than one signature, every batch would be
+/// rejected, so requests are minted one by one instead.
+pub fn batch_size_for_threshold(batch_size: usize, threshold: u8) -> usize {
+    if threshold > 1 {
+        1
+    } else {
+        batch_size
+    }
+}
+
+/// Checks the batch size against the notary threshold of the verifier contract.
+///
+/// Batching is refused when the threshold is above 1, or cannot be read.
+///
+/// # Returns
+///
+/// The batch size to mint with.
+pub async fn check_batch_size(batch_size: usize) -> usize {
+    if batch_size == 1 {
+        return 1;
+    }
+    match notary_threshold().await {
+        Ok(threshold) if threshold > 1 => {
+            warn!(
+                "The verifier contract requires {} signatures, which batches do not carry, minting requests one by one",
+                threshold
+            );
+            batch_size_for_threshold(batch_size, threshold)
+        }
+        Ok(threshold) => batch_size_for_threshold(batch_size, threshold),
+        Err(err) => {
+            warn!(
+                "Failed to read the notary threshold, minting requests one by one: {}",
+                err
+            );
+            1
+        }
+    }
+}
+
+/// A tweet of a batch, as taken by `verify_proofs_batch`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MintBatchItem {
+    pub proof: String,
+    pub leaf_index: u32,
+    /// The hashes of the siblings from the leaf of the proof up to the root.
+    pub inclusion_path: Vec<String>,
+    pub image_url: String,
+    pub owner_address: String,
+}
+
+/// The arguments of `verify_proofs_batch`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MintBatch {
+    /// The Merkle root of the proofs, signed by the remote verifier.
+    pub root: String,
+    pub signature: String,
+    pub leaf_count: u32,
+    pub items: Vec<MintBatchItem>,
+}
+
+/// A tweet to mint in a batch.
+#[derive(Debug, Clone)]
+pub struct BatchEntry {
+    /// The proof verified by the remote verifier.
+    pub proof: String,
+    pub image_url: String,
+    pub owner_address: String,
+}
+
+/// Builds a batch from proofs verified together, computing the inclusion path of each proof.
+///
+/// The tree is built like the verifier contract builds it, with the sha256 hash of each proof
+/// as a leaf, in the order the remote verifier returned them.
+///
+/// # Parameters
+///
+/// - `signature`: The signature of the remote verifier over the root.
+/// - `entries`: The tweets to mint.
+///
+/// # Returns
+///
+/// The arguments of `verify_proofs_batch`.
+pub fn build_batch(signature: String, entries: Vec<BatchEntry>) -> Result<MintBatch, String> {
+    let leaves: Vec<[u8; 32]> = entries
+        .iter()
+        .map(|entry| Sha256::hash(entry.proof.as_bytes()))
+        .collect();
+    let tree = MerkleTree::<Sha256>::from_leaves(&leaves);
+    let root = tree
+        .root_hex()
+        .ok_or("cannot build a batch without proofs")?;
+
+    let items = entries
+        .into_iter()
+        .enumerate()
+        .map(|(index, entry)| MintBatchItem {
+            proof: entry.proof,
+            leaf_index: index as u32,
+            inclusion_path: tree.proof(&[index]).proof_hashes_hex(),
+            image_url: entry.image_url,
+            owner_address: entry.owner_address,
+        })
+        .collect();
+    Ok(MintBatch {
+        root,
+        signature,
+        leaf_count: leaves.len() as u32,
+        items,
+    })
+}
+
+/// Groups notarized requests into batches of up to `batch_size` requests.
+///
+/// Requests are only batched with requests proven by the same notary, which the remote verifier
+/// checks them against. Only the first pending request of a tweet is batched, later ones are
+/// left for the next round so requests for the same tweet are still processed in order.
+pub fn group_batches(
+    jobs: Vec<near_transaction::Model>,
+    batch_size: usize,
+) -> Vec<Vec<near_transaction::Model>> {
+    let mut tweets = HashSet::new();
+    let mut batches: Vec<Vec<near_transaction::Model>> = vec![];
+    for job in jobs {
+        if !tweets.insert(job.tweet_id.clone()) {
+            continue;
+        }
+        let batch = batches.iter_mut().find(|batch| {
+            batch.len() < batch_size && batch[0].notary_public_key == job.notary_public_key
+        });
+        match batch {
+            Some(batch) => batch.push(job),
+            None => batches.push(vec![job]),
+        }
+    }
+    batches
+}
+
+#[derive(Deserialize)]
+struct BatchItemEvent {
+    event: String,
+    data: BatchItemData,
+}
+
+#[derive(Deserialize)]
+struct BatchItemData {
+    tweet_id: Option<String>,
+    #[serde(default)]
+    reason: Option<String>,
+}
+
+/// Finds why a tweet was not minted by a batch transaction, from its `batch_item_failed` event.
+///
+/// # Returns
+///
+/// The reason, or `None` if the tweet was minted or the logs are not of a batch.
+pub fn batch_item_failure(logs: &[String], tweet_id: &str) -> Option<String> {
+    logs.iter()
+        .filter_map(|log| log.strip_prefix(EVENT_LOG_PREFIX))
+        .filter_map(|event| serde_json::from_str::<BatchItemEvent>(event).ok())
+        .filter(|event| event.data.tweet_id.as_deref() == Some(tweet_id))
+        .find_map(|event| match event.event.as_str() {
+            "batch_item_failed" => Some(event.data.reason.unwrap_or_default()),
+            _ => None,
+        })
+}
+
+/// Verifies a batch of notarized requests remotely and submits it to the verifier contract.
+///
+/// # Returns
+///
+/// The submitted batch and the hash of its transaction.
+async fn submit_batch(
+    jobs: &[near_transaction::Model],
+    context: &JobContext,
+) -> Result<(MintBatch, String), VerifyError> {
+    let notarized = jobs
+        .iter()
+        .map(|job| {
+            Ok(NotarizedTweet {
+                proof: job.notarized_proof.clone().ok_or_else(|| {
+                    VerifyError::Notary(format!("{} has no notarized proof", job.tweet_id))
+                })?,
+                notary_pub_key: job.notary_public_key.clone().ok_or_else(|| {
+                    VerifyError::Notary(format!("{} has no notary key", job.tweet_id))
+                })?,
+            })
+        })
+        .collect::<Result<Vec<_>, VerifyError>>()?;
+
+    let verified = verify_remotely_batch(&notarized).await?;
+    let entries = jobs
+        .iter()
+        .zip(verified.proofs)
+        .map(|(job, proof)| BatchEntry {
+            proof,
+            image_url: job.image_url.clone(),
+            owner_address: mint_owner(job),
+        })
+        .collect();
+    let batch = build_batch(verified.signature, entries).map_err(VerifyError::RemoteVerifier)?;
+    let tx_hash = submit_mint_batch(&context.signer, &batch).await?;
+    Ok((batch, tx_hash))
+}
+
+/// Verifies and mints the notarized requests in batches.
+///
+/// Each request of a submitted batch moves to `MintSubmitted` with the batch transaction, and
+/// is then awaited like any other. When a batch cannot be submitted, the attempt is recorded on
+/// each of its requests.
+///
+/// # Returns
+///
+/// The requests whose batch was submitted.
+pub async fn submit_batches(
+    db: &DbConn,
+    context: &JobContext,
+) -> Result<Vec<near_transaction::Model>, DbErr> {
+    let notarized = near_transaction::Entity::find()
+        .filter(near_transaction::Column::Status.eq(MintStatus::Notarized.as_str()))
+        .order_by_asc(near_transaction::Column::Id)
+        .all(db)
+        .await?;
+
+    let mut submitted = vec![];
+    for jobs in group_batches(notarized, context.batch_size) {
+        match submit_batch(&jobs, context).await {
+            Ok((batch, tx_hash)) => {
+                info!(
+                    "Submitted a batch of {} mint requests under {}: {}",
+                    jobs.len(),
+                    batch.root,
+                    tx_hash
+                );
+                for (job, item) in jobs.into_iter().zip(batch.items) {
+                    let mut update: near_transaction::ActiveModel = job.into();
+                    update.verified_proof = Set(Some(item.proof));
+                    update.verified_signature = Set(Some(batch.signature.clone()));
+                    update.mint_transaction_hash = Set(Some(tx_hash.clone()));
+                    update.status = Set(MintStatus::MintSubmitted.as_str().to_string());
+                    update.attempts = Set(0);
+                    update.failure_reason = Set(None);
+                    submitted.push(update.update(db).await?);
+                }
+            }
+            Err(err) => {
+                for job in jobs {
+                    record_failure(
+                        db,
+                        job,
+                        &MintStatus::Notarized,
+                        Box::new(err.clone()),
+                        context,
+                    )
+                    .await?;
+                }
+            }
+        }
+    }
+    Ok(submitted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rs_merkle::MerkleProof;
+
+    fn entry(proof: &str) -> BatchEntry {
+        BatchEntry {
+            proof: proof.to_string(),
+            image_url: "ipfs://".to_string(),
+            owner_address: "alice.testnet".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_build_batch() {
+        let proofs = ["proof 1", "proof 2", "proof 3"];
+        let batch = build_batch(
+            "signature".to_string(),
+            proofs.iter().map(|p| entry(p)).collect(),
+        )
+        .unwrap();
+        assert_eq!(batch.leaf_count, 3);
+
+        // Every proof is checked against the root like the verifier contract does
+        let root: [u8; 32] = hex::decode(&batch.root).unwrap().try_into().unwrap();
+        for item in &batch.items {
+            let path = item
+                .inclusion_path
+                .iter()
+                .map(|hash| hex::decode(hash).unwrap().try_into().unwrap())
+                .collect();
+            assert!(MerkleProof::<Sha256>::new(path).verify(
+                root,
+                &[item.leaf_index as usize],
+                &[Sha256::hash(item.proof.as_bytes())],
+                3,
+            ));
+        }
+
+        // A batch of one is signed like a single proof
+        let batch = build_batch("signature".to_string(), vec![entry("proof 1")]).unwrap();
+        assert_eq!(batch.root, hex::encode(Sha256::hash(b"proof 1")));
+        assert!(batch.items[0].inclusion_path.is_empty());
+
+        assert!(build_batch("signature".to_string(), vec![]).is_err());
+    }
+
+    #[test]
+    fn test_batch_size_for_threshold() {
+        assert_eq!(batch_size_for_threshold(8, 1), 8);
+        assert_eq!(batch_size_for_threshold(1, 1), 1);
+        // Batches are signed by the remote verifier alone
+        assert_eq!(batch_size_for_threshold(8, 2), 1);
+    }
+
+    #[test]
+    fn test_batch_item_failure() {
+        let logs = vec![
+            r#"EVENT_JSON:{"standard":"custom","version":"1.0.0","event":"batch_item_minted","data":{"root":"00","index":0,"tweet_id":"1","owner":"alice.testnet"}}"#.to_string(),
+            r#"EVENT_JSON:{"standard":"custom","version":"1.0.0","event":"batch_item_failed","data":{"root":"00","index":1,"tweet_id":"2","reason":"PROOF ALREADY USED: 00"}}"#.to_string(),
+            "nft_creation was successful!".to_string(),
+        ];
+        assert_eq!(batch_item_failure(&logs, "1"), None);
+        assert_eq!(
+            batch_item_failure(&logs, "2"),
+            Some("PROOF ALREADY USED: 00".to_string())
+        );
+        assert_eq!(batch_item_failure(&logs, "3"), None);
+    }
+}
@@ -0,0 +1,207 @@
+use near_jsonrpc_client::{methods, JsonRpcClient};
+use near_jsonrpc_primitives::types::blocks::RpcBlockError;
+use near_primitives::types::{BlockId, BlockReference, Finality};
+use sea_orm::sea_query::OnConflict;
+use sea_orm::ActiveValue::Set;
+use sea_orm::{ColumnTrait, DbConn, DbErr, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
+use std::error::Error;
+use tracing::{debug, warn};
+
+use super::indexer::TransactionSourceKind;
+use crate::entity::{indexer_checkpoint, near_transaction};
+
+/// Number of blocks of checkpoint history kept behind the latest checkpoint.
+pub const CHECKPOINT_RETENTION_BLOCKS: i64 = 10_000;
+
+/// Number of checkpoints looked at when searching for one on the canonical chain.
+const MAX_REWIND: u64 = 100;
+
+/// Returns the height of the latest final block.
+pub async fn final_height(client: &JsonRpcClient) -> Result<u64, Box<dyn Error>> {
+    let block = client
+        .call(methods::block::RpcBlockRequest {
+            block_reference: BlockReference::Finality(Finality::Final),
+        })
+        .await?;
+    Ok(block.header.height)
+}
+
+/// Returns the hash of the block at `height` on the canonical chain.
+///
+/// `None` is returned when the node does not know of a block at that height, either because
+/// the height was skipped or because the block has been garbage collected.
+async fn canonical_hash(
+    client: &JsonRpcClient,
+    height: u64,
+) -> Result<Option<String>, Box<dyn Error>> {
+    let response = client
+        .call(methods::block::RpcBlockRequest {
+            block_reference: BlockReference::BlockId(BlockId::Height(height)),
+        })
+        .await;
+
+    match response {
+        Ok(block) => Ok(Some(block.header.hash.to_string())),
+        Err(err) => match err.handler_error() {
+            Some(RpcBlockError::UnknownBlock { .. }) => Ok(None),
+            _ => Err(err.into()),
+        },
+    }
+}
+
+/// Finds the checkpoint to resume a transaction source from.
+///
+/// The latest checkpoint is checked against the canonical chain. If its block has been
+/// orphaned, the checkpoints after the last one still on the canonical chain are removed and
+/// the indexer rewinds to it. Checkpoints whose block the node does not know of are trusted,
+/// as they were final when saved.
+///
+/// # Parameters
+///
+/// - `db`: The database connection.
+/// - `client`: The NEAR JSON-RPC client.
+/// - `source`: The name of the transaction source.
+///
+/// # Returns
+///
+/// The checkpoint to resume from, or `None` if the source has never been checkpointed.
+pub async fn resume(
+    db: &DbConn,
+    client: &JsonRpcClient,
+    source: &str,
+) -> Result<Option<indexer_checkpoint::Model>, Box<dyn Error>> {
+    let checkpoints = indexer_checkpoint::Entity::find()
+        .filter(indexer_checkpoint::Column::Source.eq(source))
+        .order_by_desc(indexer_checkpoint::Column::BlockHeight)
+        .limit(MAX_REWIND)
+        .all(db)
+        .await?;
+    if checkpoints.is_empty() {
+        return Ok(None);
+    }
+
+    for checkpoint in checkpoints {
+        let hash = canonical_hash(client, checkpoint.block_height as u64).await?;
+        if hash.is_none() || hash.as_deref() == Some(checkpoint.block_hash.as_str()) {
+            let orphaned = indexer_checkpoint::Entity::delete_many()
+                .filter(indexer_checkpoint::Column::Source.eq(source))
+                .filter(indexer_checkpoint::Column::BlockHeight.gt(checkpoint.block_height))
+                .exec(db)
+                .await?;
+            if orphaned.rows_affected > 0 {
+                warn!(
+                    "Rewound {} checkpoints of {} to block {}",
+                    orphaned.rows_affected, source, checkpoint.block_height
+                );
+            }
+            return Ok(Some(checkpoint));
+        }
+        warn!(
+            "Checkpoint {} of {} at block {} is not on the canonical chain",
+            checkpoint.block_hash, source, checkpoint.block_height
+        );
+    }
+
+    Err(format!("No checkpoint of {} is on the canonical chain", source).into())
+}
+
+/// Finds where a source that has never been checkpointed starts from.
+///
+/// Transaction ids are only comparable within a source, so the ids recorded by another source
+/// are never used as a cursor. The RPC source rescans the highest block a mint request was
+/// recorded at, whichever source recorded it, as a transaction is only recorded once.
+/// Nearblocks resumes from the last recorded transaction, since deployments that predate
+/// checkpoints only used nearblocks, unless another source has been checkpointed since. It then
+/// starts over from the first transaction.
+///
+/// # Parameters
+///
+/// - `db`: The database connection.
+/// - `source`: The transaction source.
+///
+/// # Returns
+///
+/// The cursor and the first block to scan, `None` when the source starts from the latest block.
+pub async fn initial_position(
+    db: &DbConn,
+    source: TransactionSourceKind,
+) -> Result<(Option<i64>, Option<u64>), DbErr> {
+    let last_recorded = near_transaction::Entity::find()
+        .order_by_desc(near_transaction::Column::BlockHeight)
+        .order_by_desc(near_transaction::Column::Id)
+        .one(db)
+        .await?;
+    match source {
+        TransactionSourceKind::Rpc => Ok((
+            None,
+            last_recorded.map(|transaction| transaction.block_height as u64),
+        )),
+        TransactionSourceKind::NearBlocks => {
+            let other_source = indexer_checkpoint::Entity::find()
+                .filter(indexer_checkpoint::Column::Source.ne(source.as_str()))
+                .one(db)
+                .await?;
+            let cursor = match (other_source, last_recorded) {
+                (None, Some(transaction)) => transaction.id,
+                _ => 0,
+            };
+            Ok((Some(cursor), None))
+        }
+    }
+}
+
+/// Records that a transaction source has been processed up to a block.
+///
+/// # Parameters
+///
+/// - `db`: The database connection.
+/// - `source`: The name of the transaction source.
+/// - `cursor`: The id of the last transaction processed.
+/// - `block_height`: The height of the last block processed.
+/// - `block_hash`: The hash of the last block processed.
+pub async fn save(
+    db: &DbConn,
+    source: &str,
+    cursor: i64,
+    block_height: u64,
+    block_hash: &str,
+) -> Result<(), DbErr> {
+    let block_height: i64 = block_height
+        .try_into()
+        .map_err(|e| DbErr::Custom(format!("Invalid block height: {}", e)))?;
+
+    indexer_checkpoint::Entity::insert(indexer_checkpoint::ActiveModel {
+        source: Set(source.to_string()),
+        cursor: Set(cursor),
+        block_height: Set(block_height),
+        block_hash: Set(block_hash.to_string()),
+        ..Default::default()
+    })
+    .on_conflict(
+        OnConflict::columns([
+            indexer_checkpoint::Column::Source,
+            indexer_checkpoint::Column::BlockHeight,
+        ])
+        .update_columns([
+            indexer_checkpoint::Column::Cursor,
+            indexer_checkpoint::Column::BlockHash,
+        ])
+        .to_owned(),
+    )
+    .exec_without_returning(db)
+    .await?;
+
+    indexer_checkpoint::Entity::delete_many()
+        .filter(indexer_checkpoint::Column::Source.eq(source))
+        .filter(
+            indexer_checkpoint::Column::BlockHeight.lt(block_height - CHECKPOINT_RETENTION_BLOCKS),
+        )
+        .exec(db)
+        .await?;
+
+    debug!(
+        "Checkpoint of {} at block {} cursor {}",
+        source, block_height, cursor
+    );
+    Ok(())
+}
@@ -1,13 +1,16 @@
+use http_transcript::{HttpError, Request, Response};
 use near_jsonrpc_client::methods::query::RpcQueryRequest;
 use near_jsonrpc_client::methods::tx::RpcTransactionResponse;
-use near_jsonrpc_client::{methods, JsonRpcClient};
+use near_jsonrpc_client::JsonRpcClient;
 use near_jsonrpc_primitives::types::query::QueryResponseKind;
-use near_jsonrpc_primitives::types::transactions::TransactionInfo;
-use near_primitives::transaction::{Action, FunctionCallAction, Transaction};
-use near_primitives::types::BlockReference;
-use near_primitives::views::{QueryRequest, TxExecutionStatus};
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::{AccountId, BlockReference, Finality, FunctionArgs};
+use near_primitives::views::{ExecutionStatusView, FinalExecutionStatus, QueryRequest};
 use serde_json::json;
 use std::env;
+use std::error::Error;
+use std::fmt;
+use tracing::debug;
 use verity_verify_remote::{
     config::Config,
     ic::{Verifier, DEFAULT_IC_GATEWAY_LOCAL},
@@ -15,15 +18,114 @@ use verity_verify_remote::{
 
 use verity_verify_tls::verify_proof;
 
+use crate::helper::batch::MintBatch;
 use crate::helper::proof::get_verity_client;
+use crate::helper::signer::{wait_for_transaction, NearSigner};
+use crate::helper::TweetResponse;
 
 pub const DEFAULT_VERITY_VERIFIER_ID: &str = "bkyz2-fmaaa-aaaaa-qaaaq-cai";
 
-pub async fn verify_near_proof_v2(
-    tweet_id: String,
-    image_url: String,
-    nft_owner: String,
-) -> Result<(RpcTransactionResponse, String), Box<dyn std::error::Error>> {
+/// Errors raised while proving a tweet and minting it through the verifier contract.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyError {
+    /// The indexer is missing or has an invalid setting.
+    Config(String),
+    /// The Verity prover failed to notarize the request to the X API.
+    Prover(String),
+    /// The notarized transcript is not a successful lookup of the tweet.
+    InvalidTranscript(String),
+    /// The notary information could not be retrieved, or the proof does not match it.
+    Notary(String),
+    /// The remote verifier could not be reached or did not verify the proof.
+    RemoteVerifier(String),
+    /// A NEAR JSON-RPC call failed, or the transaction has not been executed yet.
+    NearRpc(String),
+    /// The mint transaction was executed and rejected by the contract.
+    ContractRejected(String),
+}
+
+impl VerifyError {
+    /// Whether the failed step may succeed if it is tried again.
+    ///
+    /// A rejection by the contract and a transcript which is not of the tweet are final, every
+    /// other error can be transient.
+    pub fn is_retryable(&self) -> bool {
+        !matches!(
+            self,
+            VerifyError::ContractRejected(_) | VerifyError::InvalidTranscript(_)
+        )
+    }
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::Config(err) => write!(f, "configuration error: {}", err),
+            VerifyError::Prover(err) => write!(f, "prover error: {}", err),
+            VerifyError::InvalidTranscript(err) => write!(f, "invalid transcript: {}", err),
+            VerifyError::Notary(err) => write!(f, "notary error: {}", err),
+            VerifyError::RemoteVerifier(err) => write!(f, "remote verifier error: {}", err),
+            VerifyError::NearRpc(err) => write!(f, "NEAR RPC error: {}", err),
+            VerifyError::ContractRejected(err) => write!(f, "rejected by the contract: {}", err),
+        }
+    }
+}
+
+impl Error for VerifyError {}
+
+/// Reads a setting of the indexer from the environment.
+fn env_setting(name: &str) -> Result<String, VerifyError> {
+    env::var(name).map_err(|_| VerifyError::Config(format!("{} is not set", name)))
+}
+
+/// The account of the verifier contract, read from `NEAR_VERIFIER_CONTRACT_ACCOUNT_ID`.
+fn verifier_contract_id() -> Result<AccountId, VerifyError> {
+    let account_id = env_setting("NEAR_VERIFIER_CONTRACT_ACCOUNT_ID")?;
+    account_id.parse().map_err(|e| {
+        VerifyError::Config(format!(
+            "NEAR_VERIFIER_CONTRACT_ACCOUNT_ID {}: {}",
+            account_id, e
+        ))
+    })
+}
+
+/// A tweet fetched through the Verity prover, along with the TLS proof of the response.
+#[derive(Debug, Clone)]
+pub struct NotarizedTweet {
+    /// The TLS proof produced by the prover.
+    pub proof: String,
+    /// The public key of the notary that signed the proof.
+    pub notary_pub_key: String,
+}
+
+/// The result of verifying a notarized tweet against the remote verifier.
+#[derive(Debug, Clone)]
+pub struct RemoteVerification {
+    /// The verified content of the proof.
+    pub proof: String,
+    /// The signature of the remote verifier over the proof.
+    pub signature: String,
+}
+
+/// The result of verifying several notarized tweets at once against the remote verifier.
+#[derive(Debug, Clone)]
+pub struct BatchVerification {
+    /// The verified content of each proof.
+    pub proofs: Vec<String>,
+    /// The signature of the remote verifier over the Merkle root of the proofs.
+    pub signature: String,
+}
+
+/// Proves a GET request for a tweet and its metrics using the Verity prover.
+///
+/// # Parameters
+///
+/// - `tweet_id`: The ID of the tweet to notarize.
+///
+/// # Returns
+///
+/// The TLS proof of the response and the public key of the notary.
+pub async fn notarize_tweet(tweet_id: String) -> Result<NotarizedTweet, VerifyError> {
     println!("Proving a GET request using VerityClient...");
 
     let client = get_verity_client();
@@ -38,9 +140,13 @@ pub async fn verify_near_proof_v2(
         ).redact("req:header:authorization".to_string())
         .send().await;
 
-    let response = result.expect("successful response");
+    let response = result.map_err(|e| VerifyError::Prover(format!("{:?}", e)))?;
 
-    let json: serde_json::Value = response.subject.json().await.unwrap();
+    let json: serde_json::Value = response
+        .subject
+        .json()
+        .await
+        .map_err(|e| VerifyError::Prover(format!("{:?}", e)))?;
     println!("json: {:#?}", json);
     println!("response.proof.len(): {:#?}", response.proof.len());
 
@@ -48,13 +154,125 @@ pub async fn verify_near_proof_v2(
     let notaryinfo = client.get_notary_info().await;
     println!("notaryinfo: {:#?}", notaryinfo);
 
-    let notary_pub_key = notaryinfo.expect("success").public_key;
+    let notary_pub_key = notaryinfo
+        .map_err(|e| VerifyError::Notary(format!("{:?}", e)))?
+        .public_key;
 
-    let verified_by_host: (String, String) =
-        verify_proof(&response.proof, &notary_pub_key).unwrap();
+    let verified_by_host: (String, String) = verify_proof(&response.proof, &notary_pub_key)
+        .map_err(|e| VerifyError::Notary(format!("{:?}", e)))?;
 
     println!("verified_by_host: {:#?}", verified_by_host);
-    // Perform the partial remote verification against decentralised compute
+    let (notarized_response, notarized_request) = &verified_by_host;
+    check_notarized_tweet(&tweet_id, notarized_request, notarized_response)?;
+
+    Ok(NotarizedTweet {
+        proof: response.proof,
+        notary_pub_key,
+    })
+}
+
+/// Checks that a notarized transcript is a successful lookup of a tweet on the X API.
+///
+/// The verifier contract rejects proofs of any other response, such as an error of the X API,
+/// so they are not submitted.
+///
+/// # Parameters
+///
+/// - `tweet_id`: The ID of the tweet that was looked up.
+/// - `request`: The notarized request.
+/// - `response`: The notarized response.
+pub fn check_notarized_tweet(
+    tweet_id: &str,
+    request: &str,
+    response: &str,
+) -> Result<(), VerifyError> {
+    let invalid = |e: HttpError| VerifyError::InvalidTranscript(e.to_string());
+    let request = Request::parse(request).map_err(invalid)?;
+    let url = request
+        .check_url("api.x.com", "/2/tweets")
+        .map_err(invalid)?;
+    if url.query_param("ids") != Some(tweet_id) {
+        return Err(VerifyError::InvalidTranscript(format!(
+            "transcript is not of tweet {}",
+            tweet_id
+        )));
+    }
+
+    let response = Response::parse(response).map_err(invalid)?;
+    response.expect_status(200).map_err(invalid)?;
+    let body = response.json_body().map_err(invalid)?;
+    let found = serde_json::from_str::<TweetResponse>(body)
+        .ok()
+        .and_then(|tweet| tweet.data)
+        .is_some_and(|data| !data.is_empty());
+    if !found {
+        return Err(VerifyError::InvalidTranscript(format!(
+            "no tweet in response: {}",
+            body
+        )));
+    }
+    Ok(())
+}
+
+/// Performs the partial remote verification of a notarized tweet against decentralised compute.
+///
+/// # Parameters
+///
+/// - `notarized`: The notarized tweet to verify.
+///
+/// # Returns
+///
+/// The verified proof and the signature of the remote verifier.
+pub async fn verify_remotely(
+    notarized: &NotarizedTweet,
+) -> Result<RemoteVerification, VerifyError> {
+    let verified = verify_remotely_batch(std::slice::from_ref(notarized)).await?;
+    let proof = verified
+        .proofs
+        .into_iter()
+        .next()
+        .ok_or(VerifyError::RemoteVerifier("no proof verified".to_string()))?;
+
+    Ok(RemoteVerification {
+        proof,
+        signature: verified.signature,
+    })
+}
+
+/// Performs the partial remote verification of several notarized tweets at once.
+///
+/// The tweets must have been notarized by the same notary. The remote verifier signs the Merkle
+/// root of the verified proofs, so a single signature covers all of them.
+///
+/// # Parameters
+///
+/// - `notarized`: The notarized tweets to verify.
+///
+/// # Returns
+///
+/// The verified proofs, in the order of `notarized`, and the signature of the remote verifier.
+pub async fn verify_remotely_batch(
+    notarized: &[NotarizedTweet],
+) -> Result<BatchVerification, VerifyError> {
+    let notary_pub_key = match notarized {
+        [first, rest @ ..]
+            if rest
+                .iter()
+                .all(|n| n.notary_pub_key == first.notary_pub_key) =>
+        {
+            first.notary_pub_key.clone()
+        }
+        [] => {
+            return Err(VerifyError::RemoteVerifier(
+                "no proof to verify".to_string(),
+            ))
+        }
+        _ => {
+            return Err(VerifyError::Notary(
+                "proofs of a batch must come from the same notary".to_string(),
+            ))
+        }
+    };
 
     // 1. Create a config file by specifying the params
     // ? To optain this identity.pem, use `dfx identity export` - https://internetcomputer.org/docs/current/developer-docs/developer-tools/cli-tools/cli-reference/dfx-parent
@@ -69,130 +287,293 @@ pub async fn verify_near_proof_v2(
     );
 
     // 2. Create verifier from a config file
-    let remote_verifier = Verifier::from_config(&rv_config).await.unwrap();
+    let remote_verifier = Verifier::from_config(&rv_config)
+        .await
+        .map_err(|e| VerifyError::RemoteVerifier(format!("{:?}", e)))?;
 
     // 3. Extract our the public/private sub-proofs
-    let proof_value: serde_json::Value = serde_json::from_str(&response.proof).unwrap();
-    let session = proof_value.to_string();
+    let sessions = notarized
+        .iter()
+        .map(|notarized| {
+            let proof_value: serde_json::Value = serde_json::from_str(&notarized.proof)
+                .map_err(|e| VerifyError::Prover(format!("invalid proof: {}", e)))?;
+            Ok(proof_value.to_string())
+        })
+        .collect::<Result<Vec<_>, VerifyError>>()?;
 
-    // 4. Verify a proof and get the response
+    // 4. Verify the proofs and get the responses
     let verified_by_remote = remote_verifier
-        .verify_proof(
-            // You can verify multiple proofs at once
-            vec![session],
-            notary_pub_key,
+        .verify_proof(sessions, notary_pub_key)
+        .await
+        .map_err(|e| VerifyError::RemoteVerifier(format!("{:?}", e)))?;
+
+    if verified_by_remote.results.len() != notarized.len() {
+        return Err(VerifyError::RemoteVerifier(format!(
+            "{} of {} proofs verified",
+            verified_by_remote.results.len(),
+            notarized.len()
+        )));
+    }
+    debug!("proofs: {:?}", verified_by_remote.results);
+
+    Ok(BatchVerification {
+        proofs: verified_by_remote
+            .results
+            .iter()
+            .map(|result| result.get_content().to_string())
+            .collect(),
+        signature: verified_by_remote.signature.to_string(),
+    })
+}
+
+/// Submits a remotely verified proof to the verifier contract, which mints the NFT.
+///
+/// The transaction is only broadcasted, use `await_mint` to wait for its outcome.
+///
+/// # Parameters
+///
+/// - `signer`: The signer of the transaction.
+/// - `verified`: The remotely verified proof.
+/// - `image_url`: The image of the NFT.
+/// - `nft_owner`: The account the NFT is minted to.
+///
+/// # Returns
+///
+/// The hash of the broadcasted transaction.
+pub async fn submit_mint(
+    signer: &NearSigner,
+    verified: &RemoteVerification,
+    image_url: String,
+    nft_owner: String,
+) -> Result<String, VerifyError> {
+    let rpc_url = env_setting("NEAR_RPC_URL")?;
+    let contract_account_id = verifier_contract_id()?;
+
+    let client = JsonRpcClient::connect(rpc_url);
+    let submitted = signer
+        .submit_function_call(
+            &client,
+            contract_account_id,
+            "verify_proof_v2",
+            json!({
+                "proof": verified.proof,
+                "signature": verified.signature,
+                "image_url": image_url,
+                "owner_address":nft_owner
+            })
+            .to_string()
+            .into_bytes(),
+            100_000_000_000_000, // 100 TeraGas
+            0,
         )
         .await
-        .unwrap();
+        .map_err(|e| VerifyError::NearRpc(e.to_string()))?;
+    debug!("signer: {} tx: {}", submitted.signer_id, submitted.hash);
 
-    let rpc_url = env::var("NEAR_RPC_URL").expect("RPC_URL_NOT_PRESENT");
-    let account_id = env::var("NEAR_SIGNER_ACCOUNT_ID").expect("ACCOUNT_ID_NOT_PRESENT");
-    let secret_key = env::var("NEAR_ACCOUNT_SECRET_KEY").expect("SECRET_KEY_NOT_PRESENT");
-    let contract_account_id = env::var("NEAR_VERIFIER_CONTRACT_ACCOUNT_ID")
-        .expect("NEAR_VERIFIER_CONTRACT_ACCOUNT_ID_NOT_PRESENT");
+    Ok(submitted.hash.to_string())
+}
 
-    let signer_account_id: near_primitives::types::AccountId =
-        account_id.parse().expect("signer_account_id");
-    let signer_secret_key: near_crypto::SecretKey = secret_key.parse().expect("signer_secret_key");
+/// Submits a batch of remotely verified proofs to the verifier contract, which mints each NFT.
+///
+/// The transaction is only broadcasted, use `await_mint` to wait for its outcome and
+/// `transaction_logs` to find which tweets were not minted.
+///
+/// # Parameters
+///
+/// - `signer`: The signer of the transaction.
+/// - `batch`: The arguments of `verify_proofs_batch`.
+///
+/// # Returns
+///
+/// The hash of the broadcasted transaction.
+pub async fn submit_mint_batch(
+    signer: &NearSigner,
+    batch: &MintBatch,
+) -> Result<String, VerifyError> {
+    let rpc_url = env_setting("NEAR_RPC_URL")?;
+    let contract_account_id = verifier_contract_id()?;
 
-    let signer = near_crypto::InMemorySigner::from_secret_key(signer_account_id, signer_secret_key);
+    let args = serde_json::to_vec(batch).map_err(|e| VerifyError::NearRpc(e.to_string()))?;
+    let client = JsonRpcClient::connect(rpc_url);
+    let submitted = signer
+        .submit_function_call(
+            &client,
+            contract_account_id,
+            "verify_proofs_batch",
+            args,
+            300_000_000_000_000, // 300 TeraGas
+            0,
+        )
+        .await
+        .map_err(|e| VerifyError::NearRpc(e.to_string()))?;
+    debug!("signer: {} tx: {}", submitted.signer_id, submitted.hash);
 
-    println!(
-        "signer:{:?} public_key:{:?}",
-        signer.account_id.clone(),
-        signer.public_key.clone()
-    );
+    Ok(submitted.hash.to_string())
+}
+
+/// Reads how many trusted keys must sign a proof or a Merkle root for the verifier contract.
+///
+/// # Returns
+///
+/// The notary threshold of the verifier contract.
+pub async fn notary_threshold() -> Result<u8, VerifyError> {
+    let rpc_url = env_setting("NEAR_RPC_URL")?;
+    let contract_account_id = verifier_contract_id()?;
 
     let client = JsonRpcClient::connect(rpc_url);
-    let access_key_query_response = client
+    let response = client
         .call(RpcQueryRequest {
-            block_reference: BlockReference::latest(),
-            request: QueryRequest::ViewAccessKey {
-                account_id: signer.account_id.clone(),
-                public_key: signer.public_key.clone(),
+            block_reference: BlockReference::Finality(Finality::Final),
+            request: QueryRequest::CallFunction {
+                account_id: contract_account_id,
+                method_name: "get_notary_threshold".to_string(),
+                args: FunctionArgs::from(b"{}".to_vec()),
             },
         })
         .await
-        .expect("access_key_query_response error");
+        .map_err(|e| VerifyError::NearRpc(e.to_string()))?;
 
-    let current_nonce = match access_key_query_response.kind {
-        QueryResponseKind::AccessKey(access_key) => access_key.nonce,
-        _ => 0,
-    };
+    match response.kind {
+        QueryResponseKind::CallResult(result) => serde_json::from_slice(&result.result)
+            .map_err(|e| VerifyError::NearRpc(format!("invalid notary threshold: {}", e))),
+        _ => Err(VerifyError::NearRpc(
+            "failed to read the notary threshold".to_string(),
+        )),
+    }
+}
 
-    println!("proof:{:?}", &verified_by_remote.results[0]);
-
-    let transaction = Transaction {
-        signer_id: signer.account_id.clone(),
-        public_key: signer.public_key.clone(),
-        nonce: current_nonce + 1,
-        receiver_id: contract_account_id.parse().expect("contract_account_id"),
-        block_hash: access_key_query_response.block_hash,
-        actions: vec![Action::FunctionCall(Box::new(FunctionCallAction {
-            method_name: "verify_proof_v2".to_string(),
-            args: json!({
-                "proof": verified_by_remote.results[0].get_content(),
-                "signature": verified_by_remote.signature,
-                "image_url": image_url,
-                "owner_address":nft_owner
-            })
-            .to_string()
-            .into_bytes(),
-            gas: 100_000_000_000_000, // 100 TeraGas
-            deposit: 0,
-        }))],
-    };
+/// Collects the logs of the receipts of an executed transaction which did not fail.
+pub fn transaction_logs(response: &RpcTransactionResponse) -> Vec<String> {
+    match &response.final_execution_outcome {
+        Some(outcome) => outcome
+            .clone()
+            .into_outcome()
+            .receipts_outcome
+            .into_iter()
+            .filter(|receipt| !matches!(receipt.outcome.status, ExecutionStatusView::Failure(_)))
+            .flat_map(|receipt| receipt.outcome.logs)
+            .collect(),
+        None => vec![],
+    }
+}
 
-    let request = methods::broadcast_tx_async::RpcBroadcastTxAsyncRequest {
-        signed_transaction: transaction.sign(&signer),
+/// Waits for a mint transaction submitted with `submit_mint` to be executed.
+///
+/// # Parameters
+///
+/// - `signer`: The signer of the transaction.
+/// - `tx_hash`: The hash of the mint transaction.
+///
+/// # Returns
+///
+/// The transaction response if the NFT was minted, `VerifyError::ContractRejected` if the
+/// transaction failed, or `VerifyError::NearRpc` if its outcome could not be retrieved in time.
+pub async fn await_mint(
+    signer: &NearSigner,
+    tx_hash: &str,
+) -> Result<RpcTransactionResponse, VerifyError> {
+    let rpc_url = env_setting("NEAR_RPC_URL")?;
+
+    let tx_hash: CryptoHash = tx_hash
+        .parse()
+        .map_err(|e| VerifyError::NearRpc(format!("invalid transaction hash: {:?}", e)))?;
+
+    let client = JsonRpcClient::connect(rpc_url);
+    let response = wait_for_transaction(&client, tx_hash, signer.account_id.clone())
+        .await
+        .map_err(|e| VerifyError::NearRpc(e.to_string()))?;
+
+    let status = match &response.final_execution_outcome {
+        Some(outcome) => outcome.clone().into_outcome().status,
+        None => {
+            return Err(VerifyError::NearRpc(format!(
+                "{} has no outcome yet",
+                tx_hash
+            )))
+        }
     };
-    let tx_hash = client.call(request).await?;
+    match status {
+        FinalExecutionStatus::SuccessValue(_) => Ok(response),
+        FinalExecutionStatus::Failure(err) => Err(VerifyError::ContractRejected(format!(
+            "{}: {:?}",
+            tx_hash, err
+        ))),
+        status => Err(VerifyError::NearRpc(format!("{} is {:?}", tx_hash, status))),
+    }
+}
 
-    let response = client
-        .call(methods::tx::RpcTransactionStatusRequest {
-            transaction_info: TransactionInfo::TransactionId {
-                tx_hash: tx_hash,
-                sender_account_id: signer.account_id.clone(),
-            },
-            wait_until: TxExecutionStatus::Executed,
-        })
-        .await;
-
-    // loop {
-    //     let response = client
-    //         .call(methods::tx::RpcTransactionStatusRequest {
-    //             transaction_info: TransactionInfo::TransactionId {
-    //                 tx_hash,
-    //                 sender_account_id: signer.account_id.clone(),
-    //             },
-    //             wait_until: TxExecutionStatus::Executed,
-    //         })
-    //         .await;
-    //     let received_at = time::Instant::now();
-    //     let delta = (received_at - sent_at).as_secs();
-
-    //     if delta > 60 {
-    //         Err("time limit exceeded for the transaction to be recognized")?;
-    //     }
-
-    //     match response {
-    //         Err(err) => match err.handler_error() {
-    //             Some(
-    //                 RpcTransactionError::TimeoutError
-    //                 | RpcTransactionError::UnknownTransaction { .. },
-    //             ) => {
-    //                 time::sleep(time::Duration::from_secs(2)).await;
-    //                 continue;
-    //             }
-    //             _ => Err(err)?,
-    //         },
-    //         Ok(response) => {
-    //             debug!("response gotten after: {}s", delta);
-    //             res = Some(response);
-    //             break;
-    //         }
-    //     }
-    // }
-
-    Ok((response.unwrap(), tx_hash.to_string()))
+/// Notarizes a tweet, verifies it remotely and submits it to the verifier contract in one go.
+///
+/// # Returns
+///
+/// The mint transaction response and its hash.
+pub async fn verify_near_proof_v2(
+    signer: &NearSigner,
+    tweet_id: String,
+    image_url: String,
+    nft_owner: String,
+) -> Result<(RpcTransactionResponse, String), VerifyError> {
+    let notarized = notarize_tweet(tweet_id).await?;
+    let verified = verify_remotely(&notarized).await?;
+    let tx_hash = submit_mint(signer, &verified, image_url, nft_owner).await?;
+    let response = await_mint(signer, &tx_hash).await?;
+
+    Ok((response, tx_hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_error() {
+        let err = VerifyError::ContractRejected("Proof already consumed".to_string());
+        assert!(!err.is_retryable());
+        assert_eq!(
+            err.to_string(),
+            "rejected by the contract: Proof already consumed"
+        );
+
+        assert!(VerifyError::Prover("timeout".to_string()).is_retryable());
+        assert!(!VerifyError::InvalidTranscript("no tweet in response".to_string()).is_retryable());
+        assert!(VerifyError::NearRpc("timeout".to_string()).is_retryable());
+    }
+
+    #[test]
+    fn test_check_notarized_tweet() {
+        let request = "GET https://api.x.com/2/tweets?ids=1859567727305949191&expansions=author_id HTTP/1.1\r\nhost: api.x.com\r\nauthorization: Bearer [redacted]\r\n\r\n";
+        let tweet = r#"{"data":[{"id":"1859567727305949191","created_at":"2024-11-21T12:01:09.000Z","public_metrics":{"retweet_count":141,"reply_count":19,"like_count":494,"quote_count":5,"bookmark_count":912,"impression_count":291367},"text":"gm","author_id":"1268659639933194247","edit_history_tweet_ids":["1859567727305949191"]}],"includes":{"users":[{"created_at":"2020-06-04T21:43:29.000Z","id":"1268659639933194247","username":"SmuldersStefan","name":"Stefan Smulders"}]}}"#;
+        let response = |status: &str, body: &str| {
+            format!(
+                "HTTP/1.1 {}\r\ncontent-type: application/json; charset=utf-8\r\ntransfer-encoding: chunked\r\n\r\n{:x}\r\n{}\r\n0\r\n\r\n",
+                status,
+                body.len(),
+                body
+            )
+        };
+
+        assert_eq!(
+            check_notarized_tweet("1859567727305949191", request, &response("200 OK", tweet)),
+            Ok(())
+        );
+        // The transcript must be of the tweet of the mint request
+        assert!(matches!(
+            check_notarized_tweet("1", request, &response("200 OK", tweet)),
+            Err(VerifyError::InvalidTranscript(_))
+        ));
+        let not_found =
+            r#"{"errors":[{"detail":"Could not find tweet with ids: [1859567727305949191]."}]}"#;
+        assert!(check_notarized_tweet(
+            "1859567727305949191",
+            request,
+            &response("200 OK", not_found)
+        )
+        .is_err());
+        assert!(check_notarized_tweet(
+            "1859567727305949191",
+            request,
+            &response("429 Too Many Requests", "{}")
+        )
+        .is_err());
+    }
 }
@@ -0,0 +1,409 @@
+use near_jsonrpc_client::JsonRpcClient;
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::AccountId;
+use near_primitives::views::ExecutionStatusView;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::ActiveValue::Set;
+use sea_orm::{DbConn, DbErr, EntityTrait};
+use serde::Deserialize;
+use std::error::Error;
+use std::str::FromStr;
+use tracing::{debug, warn};
+
+use super::signer::wait_for_transaction;
+use super::JSONTransaction;
+use crate::entity::{cancel_mint_request_event, nft_mint_event, tweet_mint_request_event};
+
+/// Prefix of the logs carrying NEP-297 events.
+pub const EVENT_LOG_PREFIX: &str = "EVENT_JSON:";
+
+/// Data of the `tweet_mint_request` event, emitted by `mint_tweet_request`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct TweetMintRequestEvent {
+    pub tweet_id: String,
+    /// The account that made the request, which the NFT is minted to.
+    pub account: String,
+    /// The deposit attached to the request, in yoctoNEAR.
+    pub deposit: u128,
+    pub image_url: String,
+    pub notify: String,
+}
+
+/// Data of the `cancel_mint_request` event, emitted whenever the deposit of a request is refunded.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CancelMintRequestEvent {
+    pub tweet_id: String,
+    pub account: String,
+    /// The refunded amount, in yoctoNEAR.
+    pub withdraw: u128,
+}
+
+/// Data of the `auction_opened` event, emitted by the first `bid_tweet_request` for a tweet.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct AuctionOpenedEvent {
+    pub tweet_id: String,
+    pub account: String,
+    /// The first bid, in yoctoNEAR.
+    pub deposit: u128,
+    /// When the bidding window closes, in ms.
+    pub ends_at: u64,
+}
+
+/// Data of the `auction_bid` event, emitted when a bid outbids the highest one.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct AuctionBidEvent {
+    pub tweet_id: String,
+    pub account: String,
+    /// The bid, in yoctoNEAR.
+    pub deposit: u128,
+    /// The account whose bid was refunded.
+    pub outbid: String,
+    /// The refunded bid, in yoctoNEAR.
+    pub refund: u128,
+}
+
+/// Data of the `auction_settled` event, emitted along with the `tweet_mint_request` event of
+/// the winning bid.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct AuctionSettledEvent {
+    pub tweet_id: String,
+    pub account: String,
+    /// The winning bid, in yoctoNEAR.
+    pub deposit: u128,
+    pub bid_count: u32,
+}
+
+/// One entry of the NEP-171 `nft_mint` event.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct NftMintEvent {
+    pub owner_id: String,
+    pub token_ids: Vec<String>,
+    #[serde(default)]
+    pub memo: Option<String>,
+}
+
+/// The events emitted by the NFT contract.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContractEvent {
+    TweetMintRequest(TweetMintRequestEvent),
+    CancelMintRequest(CancelMintRequestEvent),
+    NftMint(Vec<NftMintEvent>),
+    AuctionOpened(AuctionOpenedEvent),
+    AuctionBid(AuctionBidEvent),
+    AuctionSettled(AuctionSettledEvent),
+}
+
+#[derive(Deserialize)]
+struct EventHeader {
+    standard: String,
+    event: String,
+}
+
+#[derive(Deserialize)]
+struct EventLog<T> {
+    data: T,
+}
+
+fn parse_data<T: serde::de::DeserializeOwned>(event: &str) -> Option<T> {
+    serde_json::from_str::<EventLog<T>>(event)
+        .map_err(|e| warn!("Invalid event {}: {}", event, e))
+        .ok()
+        .map(|log| log.data)
+}
+
+impl ContractEvent {
+    /// Parses a log, returning `None` if it is not an event of the NFT contract.
+    pub fn parse(log: &str) -> Option<Self> {
+        let event = log.strip_prefix(EVENT_LOG_PREFIX)?;
+        let header: EventHeader = serde_json::from_str(event).ok()?;
+
+        match (header.standard.as_str(), header.event.as_str()) {
+            ("custom", "tweet_mint_request") => {
+                parse_data(event).map(ContractEvent::TweetMintRequest)
+            }
+            ("custom", "cancel_mint_request") => {
+                parse_data(event).map(ContractEvent::CancelMintRequest)
+            }
+            ("nep171", "nft_mint") => parse_data(event).map(ContractEvent::NftMint),
+            ("custom", "auction_opened") => parse_data(event).map(ContractEvent::AuctionOpened),
+            ("custom", "auction_bid") => parse_data(event).map(ContractEvent::AuctionBid),
+            ("custom", "auction_settled") => parse_data(event).map(ContractEvent::AuctionSettled),
+            _ => None,
+        }
+    }
+}
+
+/// An event along with the receipt that emitted it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmittedEvent {
+    pub receipt_id: String,
+    /// The position of the log among the logs of the receipt.
+    pub log_index: i32,
+    pub event: ContractEvent,
+}
+
+/// Extracts the events of the NFT contract from the logs of a receipt.
+pub fn parse_receipt_logs(receipt_id: &str, logs: &[String]) -> Vec<EmittedEvent> {
+    logs.iter()
+        .enumerate()
+        .filter_map(|(index, log)| {
+            ContractEvent::parse(log).map(|event| EmittedEvent {
+                receipt_id: receipt_id.to_string(),
+                log_index: index as i32,
+                event,
+            })
+        })
+        .collect()
+}
+
+/// Lists the mint requests made in a transaction, in the order they were made.
+///
+/// A request is identified by the transaction and its position in this list.
+pub fn mint_requests(events: &[EmittedEvent]) -> Vec<&TweetMintRequestEvent> {
+    events
+        .iter()
+        .filter_map(|emitted| match &emitted.event {
+            ContractEvent::TweetMintRequest(event) => Some(event),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Fetches the events emitted by the NFT contract during a transaction.
+///
+/// Every receipt of the transaction is looked at, so events are found whichever action of a
+/// batch transaction, or whichever cross-contract call, they come from. Only receipts that
+/// were executed by `contract_id` and succeeded are kept, as the logs of a failed receipt are
+/// reverted along with its state.
+///
+/// # Parameters
+///
+/// - `client`: The NEAR JSON-RPC client.
+/// - `transaction`: The indexed transaction.
+/// - `contract_id`: The account of the NFT contract.
+///
+/// # Returns
+///
+/// The events, in the order they were emitted.
+pub async fn fetch_events(
+    client: &JsonRpcClient,
+    transaction: &JSONTransaction,
+    contract_id: &str,
+) -> Result<Vec<EmittedEvent>, Box<dyn Error>> {
+    let tx_hash = CryptoHash::from_str(&transaction.transaction_hash)
+        .map_err(|e| format!("Invalid transaction hash: {}", e))?;
+    let signer_id = AccountId::from_str(&transaction.signer_account_id)?;
+
+    let response = wait_for_transaction(client, tx_hash, signer_id).await?;
+    let outcome = match response.final_execution_outcome {
+        Some(outcome) => outcome.into_outcome(),
+        None => return Ok(vec![]),
+    };
+
+    let mut events = vec![];
+    for receipt in outcome.receipts_outcome {
+        if receipt.outcome.executor_id.as_str() != contract_id {
+            continue;
+        }
+        if let ExecutionStatusView::Failure(err) = &receipt.outcome.status {
+            debug!("Ignored logs of failed receipt {}: {:?}", receipt.id, err);
+            continue;
+        }
+        events.extend(parse_receipt_logs(
+            &receipt.id.to_string(),
+            &receipt.outcome.logs,
+        ));
+    }
+    Ok(events)
+}
+
+/// Stores the events of a transaction, one table per event type.
+///
+/// Events that were already stored are skipped, so a transaction can be recorded again.
+///
+/// # Parameters
+///
+/// - `db`: The database connection.
+/// - `transaction`: The transaction the events were emitted in.
+/// - `events`: The events returned by `fetch_events`.
+pub async fn record_events(
+    db: &DbConn,
+    transaction: &JSONTransaction,
+    events: &[EmittedEvent],
+) -> Result<(), DbErr> {
+    let block_height: i64 = transaction
+        .block
+        .block_height
+        .try_into()
+        .map_err(|e| DbErr::Custom(format!("Invalid block height: {}", e)))?;
+
+    for emitted in events {
+        match &emitted.event {
+            ContractEvent::TweetMintRequest(event) => {
+                let model = tweet_mint_request_event::ActiveModel {
+                    receipt_id: Set(emitted.receipt_id.clone()),
+                    log_index: Set(emitted.log_index),
+                    transaction_hash: Set(transaction.transaction_hash.clone()),
+                    block_height: Set(block_height),
+                    block_timestamp: Set(transaction.block_timestamp.clone()),
+                    tweet_id: Set(event.tweet_id.clone()),
+                    account: Set(event.account.clone()),
+                    deposit: Set(event.deposit.to_string()),
+                    image_url: Set(event.image_url.clone()),
+                    notify: Set(event.notify.clone()),
+                    ..Default::default()
+                };
+                tweet_mint_request_event::Entity::insert(model)
+                    .on_conflict(
+                        OnConflict::columns([
+                            tweet_mint_request_event::Column::ReceiptId,
+                            tweet_mint_request_event::Column::LogIndex,
+                        ])
+                        .do_nothing()
+                        .to_owned(),
+                    )
+                    .exec_without_returning(db)
+                    .await?;
+            }
+            ContractEvent::CancelMintRequest(event) => {
+                let model = cancel_mint_request_event::ActiveModel {
+                    receipt_id: Set(emitted.receipt_id.clone()),
+                    log_index: Set(emitted.log_index),
+                    transaction_hash: Set(transaction.transaction_hash.clone()),
+                    block_height: Set(block_height),
+                    block_timestamp: Set(transaction.block_timestamp.clone()),
+                    tweet_id: Set(event.tweet_id.clone()),
+                    account: Set(event.account.clone()),
+                    withdraw: Set(event.withdraw.to_string()),
+                    ..Default::default()
+                };
+                cancel_mint_request_event::Entity::insert(model)
+                    .on_conflict(
+                        OnConflict::columns([
+                            cancel_mint_request_event::Column::ReceiptId,
+                            cancel_mint_request_event::Column::LogIndex,
+                        ])
+                        .do_nothing()
+                        .to_owned(),
+                    )
+                    .exec_without_returning(db)
+                    .await?;
+            }
+            ContractEvent::NftMint(mints) => {
+                for mint in mints {
+                    for token_id in &mint.token_ids {
+                        let model = nft_mint_event::ActiveModel {
+                            receipt_id: Set(emitted.receipt_id.clone()),
+                            log_index: Set(emitted.log_index),
+                            transaction_hash: Set(transaction.transaction_hash.clone()),
+                            block_height: Set(block_height),
+                            block_timestamp: Set(transaction.block_timestamp.clone()),
+                            owner_id: Set(mint.owner_id.clone()),
+                            token_id: Set(token_id.clone()),
+                            memo: Set(mint.memo.clone()),
+                            ..Default::default()
+                        };
+                        nft_mint_event::Entity::insert(model)
+                            .on_conflict(
+                                OnConflict::columns([
+                                    nft_mint_event::Column::ReceiptId,
+                                    nft_mint_event::Column::LogIndex,
+                                    nft_mint_event::Column::TokenId,
+                                ])
+                                .do_nothing()
+                                .to_owned(),
+                            )
+                            .exec_without_returning(db)
+                            .await?;
+                    }
+                }
+            }
+            // Bids are not stored, the winning bid is recorded by the `tweet_mint_request`
+            // event emitted when the auction is settled
+            ContractEvent::AuctionOpened(_)
+            | ContractEvent::AuctionBid(_)
+            | ContractEvent::AuctionSettled(_) => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_receipt_logs() {
+        let logs = vec![
+            "lock_time updated: 10".to_string(),
+            r#"EVENT_JSON:{"standard":"custom","version":"1.0.0","event":"tweet_mint_request","data":{"tweet_id":"1858184885493485672","account":"alice.testnet","deposit":5000000000000000000000000,"image_url":"https://example.com/image.png","notify":"@alice"}}"#.to_string(),
+            r#"EVENT_JSON:{"standard":"custom","version":"1.0.0","event":"cancel_mint_request","data":{"tweet_id":"1858184885493485672","account":"alice.testnet","withdraw":900000000000000000000000000}}"#.to_string(),
+            r#"EVENT_JSON:{"standard":"nep171","version":"1.0.0","event":"nft_mint","data":[{"owner_id":"alice.testnet","token_ids":["1858184885493485672"]}]}"#.to_string(),
+            r#"EVENT_JSON:{"standard":"nep141","version":"1.0.0","event":"ft_mint","data":[]}"#.to_string(),
+            r#"EVENT_JSON:{"standard":"custom","version":"1.0.0","event":"auction_bid","data":{"tweet_id":"1858184885493485672","account":"bob.testnet","deposit":1050,"outbid":"alice.testnet","refund":1000}}"#.to_string(),
+        ];
+
+        let events = parse_receipt_logs("receipt", &logs);
+        assert_eq!(
+            events.iter().map(|e| e.log_index).collect::<Vec<_>>(),
+            vec![1, 2, 3, 5]
+        );
+        assert_eq!(
+            events[0].event,
+            ContractEvent::TweetMintRequest(TweetMintRequestEvent {
+                tweet_id: "1858184885493485672".to_string(),
+                account: "alice.testnet".to_string(),
+                deposit: 5_000_000_000_000_000_000_000_000,
+                image_url: "https://example.com/image.png".to_string(),
+                notify: "@alice".to_string(),
+            })
+        );
+        assert_eq!(
+            events[1].event,
+            ContractEvent::CancelMintRequest(CancelMintRequestEvent {
+                tweet_id: "1858184885493485672".to_string(),
+                account: "alice.testnet".to_string(),
+                withdraw: 900_000_000_000_000_000_000_000_000,
+            })
+        );
+        assert_eq!(
+            events[2].event,
+            ContractEvent::NftMint(vec![NftMintEvent {
+                owner_id: "alice.testnet".to_string(),
+                token_ids: vec!["1858184885493485672".to_string()],
+                memo: None,
+            }])
+        );
+        assert_eq!(
+            events[3].event,
+            ContractEvent::AuctionBid(AuctionBidEvent {
+                tweet_id: "1858184885493485672".to_string(),
+                account: "bob.testnet".to_string(),
+                deposit: 1050,
+                outbid: "alice.testnet".to_string(),
+                refund: 1000,
+            })
+        );
+    }
+
+    #[test]
+    fn test_mint_requests() {
+        let logs = vec![
+            r#"EVENT_JSON:{"standard":"custom","version":"1.0.0","event":"tweet_mint_request","data":{"tweet_id":"1","account":"alice.testnet","deposit":1000,"image_url":"ipfs://1","notify":"@alice"}}"#.to_string(),
+            r#"EVENT_JSON:{"standard":"custom","version":"1.0.0","event":"cancel_mint_request","data":{"tweet_id":"3","account":"alice.testnet","withdraw":1000}}"#.to_string(),
+            r#"EVENT_JSON:{"standard":"custom","version":"1.0.0","event":"tweet_mint_request","data":{"tweet_id":"2","account":"alice.testnet","deposit":2000,"image_url":"ipfs://2","notify":"@alice"}}"#.to_string(),
+        ];
+
+        // Both requests of the transaction are listed, whichever events are between them
+        let events = parse_receipt_logs("receipt", &logs);
+        let requests = mint_requests(&events);
+        assert_eq!(
+            requests
+                .iter()
+                .map(|request| (request.tweet_id.as_str(), request.deposit))
+                .collect::<Vec<_>>(),
+            vec![("1", 1000), ("2", 2000)]
+        );
+        assert!(mint_requests(&events[1..2]).is_empty());
+    }
+}
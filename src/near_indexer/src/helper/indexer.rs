@@ -1,11 +1,70 @@
+use async_trait::async_trait;
 use std::error::Error;
 use std::marker::{Send, Sync};
+use std::str::FromStr;
 use std::time::Duration;
 use tokio::time::sleep;
 use tracing::info;
 
 use super::{JSONTransaction, TransactionData};
 
+/// A paginated source of transactions sent to the NFT contract.
+///
+/// Implementations walk transactions in ascending order starting from a cursor,
+/// where the cursor is the `id` of the last transaction that has been processed.
+#[async_trait]
+pub trait TransactionSource: Send {
+    /// Retrieves the first batch of transactions after the initial cursor.
+    async fn get_transactions(
+        &mut self,
+    ) -> Result<Vec<JSONTransaction>, Box<dyn Error + Send + Sync>>;
+
+    /// Retrieves the next batch of transactions.
+    async fn next_page(&mut self) -> Result<Vec<JSONTransaction>, Box<dyn Error + Send + Sync>>;
+
+    /// Whether another page can be fetched without waiting for new blocks.
+    fn has_next_page(&self) -> bool;
+
+    /// The current cursor of the source.
+    fn cursor(&self) -> Option<i64>;
+
+    /// The height and hash of the last block scanned in full, for sources that scan blocks.
+    ///
+    /// Checkpointing it lets the source resume after blocks that had no transactions.
+    fn last_block(&self) -> Option<(u64, String)>;
+}
+
+/// The transaction sources the indexer can be configured with through `NEAR_TRANSACTION_SOURCE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionSourceKind {
+    /// The nearblocks.io explorer API. Requires `NEAR_BLOCK_KEY`.
+    NearBlocks,
+    /// Blocks and chunks read directly from `NEAR_RPC_URL`.
+    Rpc,
+}
+
+impl TransactionSourceKind {
+    /// The name the checkpoints of the source are stored under.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransactionSourceKind::NearBlocks => "nearblocks",
+            TransactionSourceKind::Rpc => "rpc",
+        }
+    }
+}
+
+impl FromStr for TransactionSourceKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "nearblocks" => Ok(TransactionSourceKind::NearBlocks),
+            "rpc" => Ok(TransactionSourceKind::Rpc),
+            _ => Err(format!("Invalid transaction source: {}", s)),
+        }
+    }
+}
+
 fn parse_string_to_i64(opt_str: Option<String>) -> Option<i64> {
     opt_str.map(|s| s.parse::<i64>().unwrap_or(0))
 }
@@ -33,47 +92,6 @@ impl<'a> NearExplorerIndexer<'a> {
         })
     }
 
-    /// Retrieves the Latest Batch of transactions.
-    ///
-    /// This method continues fetching transactions from latest
-    ///
-    /// # Returns
-    /// A vector of transactions representing the next page.
-    pub async fn get_transactions(
-        &mut self,
-    ) -> Result<Vec<JSONTransaction>, Box<dyn Error + Send + Sync>> {
-        let data = self.fetch().await?;
-
-        if data.cursor.is_some() {
-            self.cursor = parse_string_to_i64(data.cursor);
-        }
-
-        Ok(data.txns)
-    }
-
-    /// Retrieves the next page of transactions.
-    ///
-    /// This method continues fetching transactions from where the previous call left off.
-    ///
-    /// # Returns
-    /// A vector of transactions representing the next page.
-    pub async fn next_page(
-        &mut self,
-    ) -> Result<Vec<JSONTransaction>, Box<dyn Error + Send + Sync>> {
-        let data = self.fetch().await?;
-
-        if let Some(cursor) = data.cursor {
-            self.cursor = Some(cursor.parse::<i64>().unwrap_or(0));
-        } else {
-            self.cursor = None;
-        }
-
-        Ok(data.txns)
-    }
-
-    pub fn has_next_page(&self) -> bool {
-        self.cursor.is_some()
-    }
     async fn fetch(&mut self) -> Result<TransactionData, Box<dyn Error + Send + Sync>> {
         let client = reqwest::Client::new();
         let base = if self.account_id.ends_with(".testnet") {
@@ -118,6 +136,58 @@ impl<'a> NearExplorerIndexer<'a> {
     }
 }
 
+#[async_trait]
+impl<'a> TransactionSource for NearExplorerIndexer<'a> {
+    /// Retrieves the Latest Batch of transactions.
+    ///
+    /// This method continues fetching transactions from latest
+    ///
+    /// # Returns
+    /// A vector of transactions representing the next page.
+    async fn get_transactions(
+        &mut self,
+    ) -> Result<Vec<JSONTransaction>, Box<dyn Error + Send + Sync>> {
+        let data = self.fetch().await?;
+
+        if data.cursor.is_some() {
+            self.cursor = parse_string_to_i64(data.cursor);
+        }
+
+        Ok(data.txns)
+    }
+
+    /// Retrieves the next page of transactions.
+    ///
+    /// This method continues fetching transactions from where the previous call left off.
+    ///
+    /// # Returns
+    /// A vector of transactions representing the next page.
+    async fn next_page(&mut self) -> Result<Vec<JSONTransaction>, Box<dyn Error + Send + Sync>> {
+        let data = self.fetch().await?;
+
+        if let Some(cursor) = data.cursor {
+            self.cursor = Some(cursor.parse::<i64>().unwrap_or(0));
+        } else {
+            self.cursor = None;
+        }
+
+        Ok(data.txns)
+    }
+
+    fn has_next_page(&self) -> bool {
+        self.cursor.is_some()
+    }
+
+    fn cursor(&self) -> Option<i64> {
+        self.cursor
+    }
+
+    /// Transactions are listed by the explorer, no blocks are scanned.
+    fn last_block(&self) -> Option<(u64, String)> {
+        None
+    }
+}
+
 #[cfg(test)]
 mod test_near_explorer_indexer {
     use std::env;
@@ -147,4 +217,27 @@ mod test_near_explorer_indexer {
         assert!(data_size < 25);
         assert_eq!(indexer.has_next_page(), false);
     }
+
+    #[test]
+    fn test_transaction_source_kind() {
+        assert_eq!(
+            "nearblocks".parse::<TransactionSourceKind>().unwrap(),
+            TransactionSourceKind::NearBlocks
+        );
+        assert_eq!(
+            "RPC".parse::<TransactionSourceKind>().unwrap(),
+            TransactionSourceKind::Rpc
+        );
+        assert!("explorer".parse::<TransactionSourceKind>().is_err());
+
+        for kind in [
+            TransactionSourceKind::NearBlocks,
+            TransactionSourceKind::Rpc,
+        ] {
+            assert_eq!(
+                kind.as_str().parse::<TransactionSourceKind>().unwrap(),
+                kind
+            );
+        }
+    }
 }
@@ -0,0 +1,489 @@
+use near_client::client::NearClient;
+use near_client::prelude::{AccountId, Finality};
+use sea_orm::ActiveValue::Set;
+use sea_orm::{ActiveModelTrait, ColumnTrait, DbConn, DbErr, EntityTrait, QueryFilter, QueryOrder};
+use serde_json::json;
+use std::env;
+use std::error::Error;
+use std::str::FromStr;
+use tracing::{debug, info, warn};
+
+use super::batch::{batch_item_failure, submit_batches};
+use super::cktls::{await_mint, notarize_tweet, submit_mint, transaction_logs, verify_remotely};
+use super::cktls::{NotarizedTweet, RemoteVerification, VerifyError};
+use super::notifier::{NotificationOutcome, Notifiers};
+use super::signer::NearSigner;
+use super::worker_pool::WorkerPool;
+use super::NftData;
+use crate::entity::near_transaction;
+
+/// The clients the stages of a mint request are run with.
+pub struct JobContext {
+    /// Used to query the NFT contract.
+    pub client: NearClient,
+    /// Used to notify the user of the outcome of the request.
+    pub notifiers: Notifiers,
+    /// Signs the mint transactions.
+    pub signer: NearSigner,
+    /// How many notarized requests are verified and minted together, 1 mints each on its own.
+    pub batch_size: usize,
+}
+
+/// Number of consecutive failed attempts of a stage before a mint request is marked as failed.
+pub const MAX_ATTEMPTS: i32 = 5;
+
+/// The stages a mint request goes through once it has been discovered on chain.
+///
+/// The stage is persisted on the `near_transaction` row after every transition, so the
+/// indexer can resume a request from where it left off after a restart.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MintStatus {
+    /// The `mint_tweet_request` transaction has been indexed.
+    Discovered,
+    /// A TLS proof of the tweet has been produced by the prover.
+    Notarized,
+    /// The proof has been verified by the remote verifier.
+    RemotelyVerified,
+    /// The proof has been submitted to the verifier contract.
+    MintSubmitted,
+    /// The mint transaction has been executed successfully.
+    Minted,
+    /// The user has been notified of the mint.
+    Notified,
+    /// The request cannot be completed.
+    Failed(String),
+    /// The deposit has been refunded by the contract, the request was cancelled or expired.
+    Refunded(String),
+}
+
+impl MintStatus {
+    /// The value stored in the `status` column.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MintStatus::Discovered => "discovered",
+            MintStatus::Notarized => "notarized",
+            MintStatus::RemotelyVerified => "remotely_verified",
+            MintStatus::MintSubmitted => "mint_submitted",
+            MintStatus::Minted => "minted",
+            MintStatus::Notified => "notified",
+            MintStatus::Failed(_) => "failed",
+            MintStatus::Refunded(_) => "refunded",
+        }
+    }
+
+    /// Reads the status of a mint request from its row.
+    pub fn from_model(model: &near_transaction::Model) -> Result<Self, String> {
+        match model.status.as_str() {
+            "discovered" => Ok(MintStatus::Discovered),
+            "notarized" => Ok(MintStatus::Notarized),
+            "remotely_verified" => Ok(MintStatus::RemotelyVerified),
+            "mint_submitted" => Ok(MintStatus::MintSubmitted),
+            "minted" => Ok(MintStatus::Minted),
+            "notified" => Ok(MintStatus::Notified),
+            "failed" => Ok(MintStatus::Failed(
+                model.failure_reason.clone().unwrap_or_default(),
+            )),
+            "refunded" => Ok(MintStatus::Refunded(
+                model.failure_reason.clone().unwrap_or_default(),
+            )),
+            status => Err(format!("Invalid mint status: {}", status)),
+        }
+    }
+
+    /// Whether the request has nothing left to do.
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            MintStatus::Notified | MintStatus::Failed(_) | MintStatus::Refunded(_)
+        )
+    }
+}
+
+/// The stage a failed mint request is retried from.
+///
+/// The request restarts from the last stage whose output is stored on its row, so work such
+/// as a TLS proof is not produced again.
+pub fn retry_status(model: &near_transaction::Model) -> MintStatus {
+    if model.mint_transaction_hash.is_some() {
+        MintStatus::MintSubmitted
+    } else if model.verified_proof.is_some() && model.verified_signature.is_some() {
+        MintStatus::RemotelyVerified
+    } else if model.notarized_proof.is_some() && model.notary_public_key.is_some() {
+        MintStatus::Notarized
+    } else {
+        MintStatus::Discovered
+    }
+}
+
+fn required(value: &Option<String>, column: &str) -> Result<String, Box<dyn Error>> {
+    value
+        .clone()
+        .ok_or_else(|| format!("{} missing for the current stage", column).into())
+}
+
+async fn is_minted(client: &NearClient, tweet_id: &str) -> Result<bool, Box<dyn Error>> {
+    let nft_contract_id =
+        env::var("NEAR_NFT_CONTRACT_ACCOUNT_ID").unwrap_or("x-bitte-nfts.testnet".to_owned());
+    let nft_contract_id = AccountId::from_str(&nft_contract_id)?;
+
+    let fetched_nft = client
+        .view::<Option<NftData>>(
+            &nft_contract_id,
+            Finality::Final,
+            "nft_token",
+            Some(json!({
+                    "token_id": tweet_id,
+            })),
+        )
+        .await
+        .map_err(|e| format!("Failed to fetch nft data: {:?}", e))?;
+
+    Ok(fetched_nft.data().is_some())
+}
+
+/// Stores the outcome of the last notification sent for a mint request.
+///
+/// It is saved whether or not the notification was delivered, so failed deliveries are
+/// visible on the row while the stage is retried.
+pub async fn record_notification(
+    db: &DbConn,
+    id: i64,
+    outcome: &NotificationOutcome,
+) -> Result<(), DbErr> {
+    near_transaction::ActiveModel {
+        id: Set(id),
+        notification_channel: Set(Some(outcome.channel.as_str().to_string())),
+        notification_status: Set(Some(outcome.status().to_string())),
+        notification_detail: Set(Some(outcome.detail())),
+        ..Default::default()
+    }
+    .update(db)
+    .await?;
+    Ok(())
+}
+
+/// Tells the user a mint request has failed, with the reason.
+///
+/// Unlike the mint notification this is only attempted once, as the request will not be
+/// advanced again. The outcome is still recorded on the row.
+async fn notify_failure(
+    db: &DbConn,
+    job: &near_transaction::Model,
+    reason: &str,
+    context: &JobContext,
+) -> Result<(), DbErr> {
+    if let Some(notify) = job.user_to_notify.as_ref().filter(|n| !n.is_empty()) {
+        let notification = context.notifiers.templates.failed(&job.tweet_id, reason);
+        let outcome = context.notifiers.notify(notify, &notification).await;
+        record_notification(db, job.id, &outcome).await?;
+    }
+    Ok(())
+}
+
+/// The account the NFT of a request is minted to.
+///
+/// It is the account that made the request, which is not the signer when the request was made
+/// through another contract.
+pub fn mint_owner(job: &near_transaction::Model) -> String {
+    job.minter_account_id
+        .clone()
+        .unwrap_or(job.signer_account_id.clone())
+}
+
+/// Runs the current stage of a mint request.
+///
+/// # Returns
+///
+/// The changes to persist and the status reached by the request.
+async fn run_stage(
+    db: &DbConn,
+    job: &near_transaction::Model,
+    context: &JobContext,
+) -> Result<(near_transaction::ActiveModel, MintStatus), Box<dyn Error>> {
+    let mut update: near_transaction::ActiveModel = job.clone().into();
+
+    let next = match MintStatus::from_model(job)? {
+        MintStatus::Discovered => {
+            if is_minted(&context.client, &job.tweet_id).await? {
+                MintStatus::Failed("NFT already minted".to_string())
+            } else {
+                let notarized = notarize_tweet(job.tweet_id.clone()).await?;
+                update.notarized_proof = Set(Some(notarized.proof));
+                update.notary_public_key = Set(Some(notarized.notary_pub_key));
+                MintStatus::Notarized
+            }
+        }
+        MintStatus::Notarized => {
+            let notarized = NotarizedTweet {
+                proof: required(&job.notarized_proof, "notarized_proof")?,
+                notary_pub_key: required(&job.notary_public_key, "notary_public_key")?,
+            };
+            let verified = verify_remotely(&notarized).await?;
+            update.verified_proof = Set(Some(verified.proof));
+            update.verified_signature = Set(Some(verified.signature));
+            MintStatus::RemotelyVerified
+        }
+        MintStatus::RemotelyVerified => {
+            let verified = RemoteVerification {
+                proof: required(&job.verified_proof, "verified_proof")?,
+                signature: required(&job.verified_signature, "verified_signature")?,
+            };
+            let tx_hash = submit_mint(
+                &context.signer,
+                &verified,
+                job.image_url.clone(),
+                mint_owner(job),
+            )
+            .await?;
+            update.mint_transaction_hash = Set(Some(tx_hash));
+            MintStatus::MintSubmitted
+        }
+        MintStatus::MintSubmitted => {
+            let tx_hash = required(&job.mint_transaction_hash, "mint_transaction_hash")?;
+            let response = await_mint(&context.signer, &tx_hash).await?;
+            // A batch transaction succeeds even when some of its tweets are not minted
+            if let Some(reason) = batch_item_failure(&transaction_logs(&response), &job.tweet_id) {
+                return Err(VerifyError::ContractRejected(reason).into());
+            }
+            MintStatus::Minted
+        }
+        MintStatus::Minted => {
+            if let Some(notify) = job.user_to_notify.as_ref().filter(|n| !n.is_empty()) {
+                let outcome = context
+                    .notifiers
+                    .notify(notify, &context.notifiers.templates.minted(&job.tweet_id))
+                    .await;
+                record_notification(db, job.id, &outcome).await?;
+                outcome.result.map_err(|e| e.to_string())?;
+            }
+            MintStatus::Notified
+        }
+        status => return Ok((update, status)),
+    };
+
+    Ok((update, next))
+}
+
+/// Drives a mint request through its stages until it completes, fails or a stage errors.
+///
+/// When batching is enabled, a notarized request is left for `submit_batches` to pick up.
+///
+/// Every transition is persisted before the next stage starts. When a stage errors, its
+/// attempt counter and the error are stored, and the request is marked as failed once
+/// `MAX_ATTEMPTS` is reached or if the error is not retryable. Otherwise the stage is
+/// retried on the next call.
+///
+/// # Parameters
+///
+/// - `db`: The database connection.
+/// - `job`: The `near_transaction` row of the mint request.
+/// - `context`: The clients used by the stages.
+///
+/// # Returns
+///
+/// The updated row.
+pub async fn advance(
+    db: &DbConn,
+    mut job: near_transaction::Model,
+    context: &JobContext,
+) -> Result<near_transaction::Model, DbErr> {
+    loop {
+        let status = MintStatus::from_model(&job).map_err(DbErr::Custom)?;
+        if status.is_terminal() {
+            return Ok(job);
+        }
+        // Notarized requests are verified and minted together by `submit_batches`
+        if status == MintStatus::Notarized && context.batch_size > 1 {
+            return Ok(job);
+        }
+
+        match run_stage(db, &job, context).await {
+            Ok((mut update, next)) => {
+                info!(
+                    "mint request {}: {} -> {}",
+                    job.tweet_id,
+                    status.as_str(),
+                    next.as_str()
+                );
+                update.status = Set(next.as_str().to_string());
+                update.attempts = Set(0);
+                update.failure_reason = Set(match &next {
+                    MintStatus::Failed(reason) => Some(reason.clone()),
+                    _ => None,
+                });
+                job = update.update(db).await?;
+                if let MintStatus::Failed(reason) = &next {
+                    notify_failure(db, &job, reason, context).await?;
+                }
+            }
+            Err(err) => return record_failure(db, job, &status, err, context).await,
+        }
+    }
+}
+
+/// Records a failed attempt of a stage of a mint request.
+///
+/// The request is marked as failed once `MAX_ATTEMPTS` is reached or if the error is not
+/// retryable, otherwise the stage is retried on the next call.
+///
+/// # Returns
+///
+/// The updated row.
+pub async fn record_failure(
+    db: &DbConn,
+    job: near_transaction::Model,
+    status: &MintStatus,
+    err: Box<dyn Error>,
+    context: &JobContext,
+) -> Result<near_transaction::Model, DbErr> {
+    let attempts = job.attempts + 1;
+    // Errors such as a rejection by the contract will not go away on retry
+    let retryable = match err.downcast_ref::<VerifyError>() {
+        Some(err) => err.is_retryable(),
+        None => true,
+    };
+    warn!(
+        "mint request {}: {} attempt {}/{} failed: {}",
+        job.tweet_id,
+        status.as_str(),
+        attempts,
+        MAX_ATTEMPTS,
+        err
+    );
+
+    let reason = err.to_string();
+    let mut update: near_transaction::ActiveModel = job.into();
+    update.attempts = Set(attempts);
+    update.failure_reason = Set(Some(reason.clone()));
+    let failed = !retryable || attempts >= MAX_ATTEMPTS;
+    if failed {
+        update.status = Set(MintStatus::Failed(reason.clone()).as_str().to_string());
+    }
+    let job = update.update(db).await?;
+    if failed {
+        notify_failure(db, &job, &reason, context).await?;
+    }
+    Ok(job)
+}
+
+/// Advances every mint request that has not reached a terminal status with the worker pool.
+///
+/// This is how newly discovered requests are processed, and how requests interrupted by a
+/// restart or a failing stage are picked up again.
+pub async fn resume_pending(
+    db: &DbConn,
+    pool: &WorkerPool,
+    context: &JobContext,
+) -> Result<(), DbErr> {
+    let pending = near_transaction::Entity::find()
+        .filter(near_transaction::Column::Status.is_not_in([
+            MintStatus::Notified.as_str(),
+            MintStatus::Failed(String::new()).as_str(),
+            MintStatus::Refunded(String::new()).as_str(),
+        ]))
+        .order_by_asc(near_transaction::Column::Id)
+        .all(db)
+        .await?;
+    debug!("Resuming {} pending mint requests", pending.len());
+
+    pool.process(db, pending, context).await?;
+    if context.batch_size > 1 {
+        let submitted = submit_batches(db, context).await?;
+        pool.process(db, submitted, context).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+impl near_transaction::Model {
+    /// A mint request of `tweet_id` that has just been discovered, for tests.
+    pub fn test_fixture(id: i64, tweet_id: &str) -> Self {
+        near_transaction::Model {
+            id,
+            transaction_hash: format!("hash-{}", id),
+            signer_account_id: "signer.testnet".to_string(),
+            receiver_account_id: "x-bitte-nfts.testnet".to_string(),
+            block_timestamp: "0".to_string(),
+            block_height: 1,
+            action: "FUNCTION_CALL".to_string(),
+            method: "mint_tweet_request".to_string(),
+            outcomes_status: true,
+            tweet_id: tweet_id.to_string(),
+            image_url: "https://example.com/image.png".to_string(),
+            mint_transaction_hash: None,
+            user_to_notify: None,
+            notarized_proof: None,
+            zk_proof: None,
+            status: MintStatus::Discovered.as_str().to_string(),
+            failure_reason: None,
+            attempts: 0,
+            notary_public_key: None,
+            verified_proof: None,
+            verified_signature: None,
+            notification_channel: None,
+            notification_status: None,
+            notification_detail: None,
+            refund_transaction_hash: None,
+            refunded_amount: None,
+            minter_account_id: None,
+            event_index: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TWEET_ID: &str = "1858184885493485672";
+
+    #[test]
+    fn test_mint_status_round_trip() {
+        let statuses = vec![
+            MintStatus::Discovered,
+            MintStatus::Notarized,
+            MintStatus::RemotelyVerified,
+            MintStatus::MintSubmitted,
+            MintStatus::Minted,
+            MintStatus::Notified,
+            MintStatus::Failed("NFT already minted".to_string()),
+            MintStatus::Refunded("cancelled by the requester".to_string()),
+        ];
+
+        for status in statuses {
+            let model = near_transaction::Model {
+                status: status.as_str().to_string(),
+                failure_reason: match &status {
+                    MintStatus::Failed(reason) | MintStatus::Refunded(reason) => {
+                        Some(reason.clone())
+                    }
+                    _ => None,
+                },
+                ..near_transaction::Model::test_fixture(1, TWEET_ID)
+            };
+            assert_eq!(MintStatus::from_model(&model).unwrap(), status);
+        }
+    }
+
+    #[test]
+    fn test_retry_status() {
+        let mut model = near_transaction::Model {
+            status: "failed".to_string(),
+            failure_reason: Some("prover unavailable".to_string()),
+            attempts: MAX_ATTEMPTS,
+            ..near_transaction::Model::test_fixture(1, TWEET_ID)
+        };
+        assert_eq!(retry_status(&model), MintStatus::Discovered);
+
+        model.notarized_proof = Some("proof".to_string());
+        model.notary_public_key = Some("key".to_string());
+        assert_eq!(retry_status(&model), MintStatus::Notarized);
+
+        model.verified_proof = Some("proof".to_string());
+        model.verified_signature = Some("signature".to_string());
+        assert_eq!(retry_status(&model), MintStatus::RemotelyVerified);
+
+        model.mint_transaction_hash = Some("hash".to_string());
+        assert_eq!(retry_status(&model), MintStatus::MintSubmitted);
+    }
+}
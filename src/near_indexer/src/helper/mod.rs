@@ -3,9 +3,20 @@ pub mod near;
 
 pub mod twitter;
 
+pub mod admin;
+pub mod auctions;
+pub mod batch;
+pub mod checkpoint;
 pub mod cktls;
+pub mod events;
 pub mod indexer;
+pub mod job_queue;
+pub mod notifier;
 pub mod proof;
+pub mod refunds;
+pub mod rpc_indexer;
+pub mod signer;
+pub mod worker_pool;
 
 use std::collections::HashMap;
 
@@ -303,12 +314,6 @@ pub struct Tab {
     pub tab: String,
 }
 
-#[derive(Debug, Deserialize, Clone)]
-pub struct MintRequestData {
-    pub notify: String,
-    pub tweet_id: String,
-    pub image_url: String,
-}
 #[allow(non_snake_case)]
 #[derive(Serialize, Deserialize, Clone)]
 pub struct IpfsData {
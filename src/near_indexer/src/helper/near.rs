@@ -1,112 +1,38 @@
+use super::signer::{wait_for_transaction, NearSigner};
 use super::{AssetMetadata, TweetResponse, User};
 use near_contract_standards::non_fungible_token::metadata::TokenMetadata;
-use near_jsonrpc_client::methods::query::RpcQueryRequest;
 use near_jsonrpc_client::methods::tx::RpcTransactionResponse;
-use near_jsonrpc_client::{methods, JsonRpcClient};
-use near_jsonrpc_primitives::types::query::QueryResponseKind;
-use near_jsonrpc_primitives::types::transactions::{RpcTransactionError, TransactionInfo};
-use near_primitives::transaction::{Action, FunctionCallAction, Transaction};
-use near_primitives::types::BlockReference;
-use near_primitives::views::{QueryRequest, TxExecutionStatus};
+use near_jsonrpc_client::JsonRpcClient;
 use serde_json::json;
 use std::env;
-use tokio::time;
-use tracing::debug;
 
 pub async fn verify_near_proof(
     journal_output: Vec<u8>,
     token_metadata: TokenMetadata,
 ) -> Result<RpcTransactionResponse, Box<dyn std::error::Error>> {
     let rpc_url = env::var("NEAR_RPC_URL").expect("RPC_URL_NOT_PRESENT");
-    let account_id = env::var("NEAR_SIGNER_ACCOUNT_ID").expect("ACCOUNT_ID_NOT_PRESENT");
-    let secret_key = env::var("NEAR_ACCOUNT_SECRET_KEY").expect("SECRET_KEY_NOT_PRESENT");
     let contract_account_id = env::var("NEAR_VERIFIER_CONTRACT_ACCOUNT_ID")
         .expect("NEAR_VERIFIER_CONTRACT_ACCOUNT_ID_NOT_PRESENT");
 
-    let signer_account_id: near_primitives::types::AccountId = account_id.parse()?;
-    let signer_secret_key: near_crypto::SecretKey = secret_key.parse()?;
-
-    let signer = near_crypto::InMemorySigner::from_secret_key(signer_account_id, signer_secret_key);
-
+    let signer = NearSigner::from_env();
     let client = JsonRpcClient::connect(rpc_url);
-    let access_key_query_response = client
-        .call(RpcQueryRequest {
-            block_reference: BlockReference::latest(),
-            request: QueryRequest::ViewAccessKey {
-                account_id: signer.account_id.clone(),
-                public_key: signer.public_key.clone(),
-            },
-        })
-        .await?;
-
-    let current_nonce = match access_key_query_response.kind {
-        QueryResponseKind::AccessKey(access_key) => access_key.nonce,
-        _ => Err("failed to extract current nonce")?,
-    };
-
-    let transaction = Transaction {
-        signer_id: signer.account_id.clone(),
-        public_key: signer.public_key.clone(),
-        nonce: current_nonce + 1,
-        receiver_id: contract_account_id.parse()?,
-        block_hash: access_key_query_response.block_hash,
-        actions: vec![Action::FunctionCall(Box::new(FunctionCallAction {
-            method_name: "verify_proof".to_string(),
-            args: json!({
+    let submitted = signer
+        .submit_function_call(
+            &client,
+            contract_account_id.parse()?,
+            "verify_proof",
+            json!({
                 "journal": journal_output,
                 "token_metadata": token_metadata
             })
             .to_string()
             .into_bytes(),
-            gas: 100_000_000_000_000, // 100 TeraGas
-            deposit: 0,
-        }))],
-    };
-
-    let request = methods::broadcast_tx_async::RpcBroadcastTxAsyncRequest {
-        signed_transaction: transaction.sign(&signer),
-    };
-
-    let sent_at = time::Instant::now();
-    let tx_hash = client.call(request).await?;
-    let res: Option<RpcTransactionResponse>;
-    loop {
-        let response = client
-            .call(methods::tx::RpcTransactionStatusRequest {
-                transaction_info: TransactionInfo::TransactionId {
-                    tx_hash,
-                    sender_account_id: signer.account_id.clone(),
-                },
-                wait_until: TxExecutionStatus::Executed,
-            })
-            .await;
-        let received_at = time::Instant::now();
-        let delta = (received_at - sent_at).as_secs();
-
-        if delta > 60 {
-            Err("time limit exceeded for the transaction to be recognized")?;
-        }
-
-        match response {
-            Err(err) => match err.handler_error() {
-                Some(
-                    RpcTransactionError::TimeoutError
-                    | RpcTransactionError::UnknownTransaction { .. },
-                ) => {
-                    time::sleep(time::Duration::from_secs(2)).await;
-                    continue;
-                }
-                _ => Err(err)?,
-            },
-            Ok(response) => {
-                debug!("response gotten after: {}s", delta);
-                res = Some(response);
-                break;
-            }
-        }
-    }
+            100_000_000_000_000, // 100 TeraGas
+            0,
+        )
+        .await?;
 
-    Ok(res.unwrap())
+    wait_for_transaction(&client, submitted.hash, submitted.signer_id).await
 }
 
 /// generate the nft payload
@@ -162,6 +88,7 @@ mod tests {
     use dotenv::dotenv;
     use ethers::utils::hex;
     use tokio::{self, task::spawn_blocking};
+    use tracing::debug;
 
     #[tokio::test]
     async fn test_get_verify() {
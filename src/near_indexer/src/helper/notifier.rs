@@ -0,0 +1,563 @@
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde_json::json;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::marker::{Send, Sync};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::debug;
+
+use super::twitter::OathTweeterHandler;
+
+/// Header carrying the signature of webhook payloads.
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "X-Signature";
+
+/// A message sent to the user who requested a mint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    /// The kind of event the user is notified of, e.g. `minted`.
+    pub event: String,
+    /// The tweet the mint request is for.
+    pub tweet_id: String,
+    /// Short summary, used as the subject of emails.
+    pub subject: String,
+    /// The message itself.
+    pub body: String,
+}
+
+/// The subject and body of a kind of notification.
+///
+/// `{tweet_id}`, `{amount}` and `{reason}` are replaced by the details of the mint request,
+/// when they apply to the event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotificationTemplate {
+    pub subject: String,
+    pub body: String,
+}
+
+impl NotificationTemplate {
+    fn new(subject: &str, body: &str) -> Self {
+        NotificationTemplate {
+            subject: subject.to_string(),
+            body: body.to_string(),
+        }
+    }
+
+    /// Overrides the template with `NOTIFY_SUBJECT_<EVENT>` and `NOTIFY_TEMPLATE_<EVENT>`.
+    fn with_env(mut self, event: &str) -> Self {
+        let event = event.to_uppercase();
+        if let Some(subject) = configured(&format!("NOTIFY_SUBJECT_{}", event)) {
+            self.subject = subject;
+        }
+        if let Some(body) = configured(&format!("NOTIFY_TEMPLATE_{}", event)) {
+            self.body = body;
+        }
+        self
+    }
+
+    /// Builds the notification of an event from the template.
+    pub fn render(&self, event: &str, tweet_id: &str, values: &[(&str, &str)]) -> Notification {
+        let fill = |template: &str| {
+            values.iter().fold(
+                template.replace("{tweet_id}", tweet_id),
+                |text, (name, value)| text.replace(&format!("{{{}}}", name), value),
+            )
+        };
+        Notification {
+            event: event.to_string(),
+            tweet_id: tweet_id.to_string(),
+            subject: fill(&self.subject),
+            body: fill(&self.body),
+        }
+    }
+}
+
+/// The templates of the notifications sent for each outcome of a mint request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotificationTemplates {
+    pub minted: NotificationTemplate,
+    pub failed: NotificationTemplate,
+    pub refunded: NotificationTemplate,
+}
+
+impl Default for NotificationTemplates {
+    fn default() -> Self {
+        NotificationTemplates {
+            minted: NotificationTemplate::new(
+                "Your X-NFT {tweet_id} has been minted",
+                "Your X-NFT with ID:{tweet_id} has been successfully minted! 🎉\nCheck your wallet to confirm receipt of your new digital collectible. Enjoy owning a unique piece of blockchain art! #NFT #Web3",
+            ),
+            failed: NotificationTemplate::new(
+                "Your X-NFT {tweet_id} could not be minted",
+                "Your X-NFT with ID:{tweet_id} could not be minted: {reason}.\nYour deposit can be reclaimed by cancelling the mint request once its lock time has passed.",
+            ),
+            refunded: NotificationTemplate::new(
+                "Your X-NFT {tweet_id} mint request has been refunded",
+                "Your mint request for the X-NFT with ID:{tweet_id} has been refunded: {reason}.\n{amount} have been returned to your wallet.",
+            ),
+        }
+    }
+}
+
+impl NotificationTemplates {
+    /// Loads the default templates, overridden by the environment.
+    ///
+    /// # Environment Variables
+    ///
+    /// - `NOTIFY_SUBJECT_MINTED`, `NOTIFY_TEMPLATE_MINTED`: Sent once the NFT has been minted.
+    /// - `NOTIFY_SUBJECT_FAILED`, `NOTIFY_TEMPLATE_FAILED`: Sent when the request cannot be completed.
+    /// - `NOTIFY_SUBJECT_REFUNDED`, `NOTIFY_TEMPLATE_REFUNDED`: Sent when the deposit is refunded.
+    pub fn from_env() -> Self {
+        let templates = Self::default();
+        NotificationTemplates {
+            minted: templates.minted.with_env("minted"),
+            failed: templates.failed.with_env("failed"),
+            refunded: templates.refunded.with_env("refunded"),
+        }
+    }
+
+    /// The notification sent once the NFT of a tweet has been minted.
+    pub fn minted(&self, tweet_id: &str) -> Notification {
+        self.minted.render("minted", tweet_id, &[])
+    }
+
+    /// The notification sent when a mint request cannot be completed.
+    pub fn failed(&self, tweet_id: &str, reason: &str) -> Notification {
+        self.failed
+            .render("failed", tweet_id, &[("reason", reason)])
+    }
+
+    /// The notification sent when the deposit of a mint request is refunded.
+    ///
+    /// `amount` is in yoctoNEAR.
+    pub fn refunded(&self, tweet_id: &str, amount: u128, reason: &str) -> Notification {
+        self.refunded.render(
+            "refunded",
+            tweet_id,
+            &[("amount", &format_near(amount)), ("reason", reason)],
+        )
+    }
+}
+
+/// Formats an amount of yoctoNEAR in NEAR, without trailing zeros.
+pub fn format_near(yocto: u128) -> String {
+    const YOCTO_PER_NEAR: u128 = 1_000_000_000_000_000_000_000_000;
+    let whole = yocto / YOCTO_PER_NEAR;
+    let fraction = yocto % YOCTO_PER_NEAR;
+    if fraction == 0 {
+        return format!("{} NEAR", whole);
+    }
+    let fraction = format!("{:024}", fraction);
+    format!("{}.{} NEAR", whole, fraction.trim_end_matches('0'))
+}
+
+/// A channel users can be notified through.
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    /// Sends a notification.
+    ///
+    /// # Parameters
+    ///
+    /// - `recipient`: The recipient on this channel, with the scheme prefix removed.
+    /// - `notification`: The notification to send.
+    ///
+    /// # Returns
+    ///
+    /// A short description of the delivery, such as the id of the message sent.
+    async fn notify(
+        &self,
+        recipient: &str,
+        notification: &Notification,
+    ) -> Result<String, Box<dyn Error + Send + Sync>>;
+}
+
+/// The channels available to notify users.
+///
+/// The channel is picked from the scheme prefix of the `notify` field of a mint request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NotificationChannel {
+    /// A reply on X, the default when `notify` has no scheme, or with `x:`.
+    X,
+    /// A signed JSON POST to an `https:` URL.
+    Webhook,
+    /// An email to a `mailto:` address.
+    Email,
+    /// A message from the Telegram bot to a `tg:` chat id.
+    Telegram,
+}
+
+impl NotificationChannel {
+    /// Splits the `notify` field of a mint request into its channel and recipient.
+    pub fn parse(notify: &str) -> (Self, &str) {
+        let notify = notify.trim();
+        if let Some(chat_id) = notify.strip_prefix("tg:") {
+            (NotificationChannel::Telegram, chat_id)
+        } else if let Some(address) = notify.strip_prefix("mailto:") {
+            (NotificationChannel::Email, address)
+        } else if notify.starts_with("https:") {
+            (NotificationChannel::Webhook, notify)
+        } else if let Some(username) = notify.strip_prefix("x:") {
+            (NotificationChannel::X, username)
+        } else {
+            (NotificationChannel::X, notify)
+        }
+    }
+
+    /// The value stored in the `notification_channel` column.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationChannel::X => "x",
+            NotificationChannel::Webhook => "webhook",
+            NotificationChannel::Email => "email",
+            NotificationChannel::Telegram => "telegram",
+        }
+    }
+}
+
+/// The result of notifying a user, as stored on the `near_transaction` row.
+#[derive(Debug)]
+pub struct NotificationOutcome {
+    pub channel: NotificationChannel,
+    pub result: Result<String, Box<dyn Error + Send + Sync>>,
+}
+
+impl NotificationOutcome {
+    /// The value stored in the `notification_status` column.
+    pub fn status(&self) -> &'static str {
+        match self.result {
+            Ok(_) => "sent",
+            Err(_) => "failed",
+        }
+    }
+
+    /// The value stored in the `notification_detail` column.
+    pub fn detail(&self) -> String {
+        match &self.result {
+            Ok(detail) => detail.clone(),
+            Err(err) => err.to_string(),
+        }
+    }
+}
+
+/// Reads an environment variable, treating an empty value as unset.
+fn configured(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+/// The notifiers configured for each channel, and the templates of the notifications.
+#[derive(Default)]
+pub struct Notifiers {
+    channels: HashMap<NotificationChannel, Box<dyn Notifier>>,
+    pub templates: NotificationTemplates,
+}
+
+impl Notifiers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the notifier of a channel.
+    pub fn with(mut self, channel: NotificationChannel, notifier: Box<dyn Notifier>) -> Self {
+        self.channels.insert(channel, notifier);
+        self
+    }
+
+    /// Configures every channel whose environment variables are set and not empty.
+    ///
+    /// # Environment Variables
+    ///
+    /// - `TWEET_CONSUMER_KEY`, `TWEET_CONSUMER_SECRET`, `TWEET_ACCESS_TOKEN`, `TWEET_TOKEN_SECRET`: X.
+    /// - `NOTIFY_WEBHOOK_SECRET`: The key webhook payloads are signed with.
+    /// - `SMTP_HOST`, `SMTP_USERNAME`, `SMTP_PASSWORD`, `SMTP_FROM` and optionally `SMTP_PORT`: Email.
+    /// - `TELEGRAM_BOT_TOKEN`: Telegram.
+    ///
+    /// The templates are loaded with `NotificationTemplates::from_env`.
+    pub fn from_env() -> Self {
+        let mut notifiers = Self::new();
+        notifiers.templates = NotificationTemplates::from_env();
+        if configured("TWEET_CONSUMER_KEY").is_some() {
+            notifiers = notifiers.with(
+                NotificationChannel::X,
+                Box::new(OathTweeterHandler::default()),
+            );
+        }
+        if let Some(secret) = configured("NOTIFY_WEBHOOK_SECRET") {
+            notifiers = notifiers.with(
+                NotificationChannel::Webhook,
+                Box::new(WebhookNotifier::new(&secret)),
+            );
+        }
+        if let Some(host) = configured("SMTP_HOST") {
+            notifiers = notifiers.with(
+                NotificationChannel::Email,
+                Box::new(EmailNotifier {
+                    host,
+                    port: configured("SMTP_PORT")
+                        .map(|port| port.parse().expect("SMTP_PORT must be a number"))
+                        .unwrap_or(587),
+                    username: env::var("SMTP_USERNAME").expect("SMTP_USERNAME_NOT_PRESENT"),
+                    password: env::var("SMTP_PASSWORD").expect("SMTP_PASSWORD_NOT_PRESENT"),
+                    from: env::var("SMTP_FROM").expect("SMTP_FROM_NOT_PRESENT"),
+                }),
+            );
+        }
+        if let Some(token) = configured("TELEGRAM_BOT_TOKEN") {
+            notifiers = notifiers.with(
+                NotificationChannel::Telegram,
+                Box::new(TelegramNotifier::new(&token)),
+            );
+        }
+        notifiers
+    }
+
+    /// The channels that have a notifier.
+    pub fn configured(&self) -> Vec<&'static str> {
+        let mut channels: Vec<&'static str> = self
+            .channels
+            .keys()
+            .map(|channel| channel.as_str())
+            .collect();
+        channels.sort();
+        channels
+    }
+
+    /// Notifies the recipient described by the `notify` field of a mint request.
+    pub async fn notify(&self, notify: &str, notification: &Notification) -> NotificationOutcome {
+        let (channel, recipient) = NotificationChannel::parse(notify);
+        let result = match self.channels.get(&channel) {
+            Some(notifier) => notifier.notify(recipient, notification).await,
+            None => Err(format!("{} notifications are not configured", channel.as_str()).into()),
+        };
+        debug!("notified {} via {}: {:?}", notify, channel.as_str(), result);
+
+        NotificationOutcome { channel, result }
+    }
+}
+
+#[async_trait]
+impl Notifier for OathTweeterHandler {
+    /// Replies on X, mentioning the user.
+    async fn notify(
+        &self,
+        recipient: &str,
+        notification: &Notification,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let res = self.mention(recipient, &notification.body).await?;
+        Ok(res["data"]["id"].as_str().unwrap_or_default().to_string())
+    }
+}
+
+/// Posts notifications as JSON to a URL, signed with HMAC-SHA256.
+///
+/// The hex encoded signature of the body is sent in the `X-Signature` header as `sha256=<signature>`.
+pub struct WebhookNotifier {
+    secret: Vec<u8>,
+    client: reqwest::Client,
+}
+
+impl WebhookNotifier {
+    pub fn new(secret: &str) -> Self {
+        WebhookNotifier {
+            secret: secret.as_bytes().to_vec(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Signs a payload with the webhook secret.
+    pub fn sign(&self, payload: &[u8]) -> String {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.secret).expect("HMAC can take a key of any size");
+        mac.update(payload);
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    async fn notify(
+        &self,
+        recipient: &str,
+        notification: &Notification,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let payload = json!({
+            "event": notification.event,
+            "tweet_id": notification.tweet_id,
+            "message": notification.body,
+            "timestamp": timestamp,
+        })
+        .to_string();
+
+        let resp = self
+            .client
+            .post(recipient)
+            .header("content-type", "application/json")
+            .header(WEBHOOK_SIGNATURE_HEADER, self.sign(payload.as_bytes()))
+            .body(payload)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(resp.status().to_string())
+    }
+}
+
+/// Sends notifications by email through an SMTP relay.
+pub struct EmailNotifier {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub password: String,
+    pub from: String,
+}
+
+#[async_trait]
+impl Notifier for EmailNotifier {
+    async fn notify(
+        &self,
+        recipient: &str,
+        notification: &Notification,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let email = Message::builder()
+            .from(self.from.parse()?)
+            .to(recipient.parse()?)
+            .subject(notification.subject.clone())
+            .header(ContentType::TEXT_PLAIN)
+            .body(notification.body.clone())?;
+
+        let mailer = AsyncSmtpTransport::<Tokio1Executor>::relay(&self.host)?
+            .port(self.port)
+            .credentials(Credentials::new(
+                self.username.clone(),
+                self.password.clone(),
+            ))
+            .build();
+        let response = mailer.send(email).await?;
+        Ok(response.code().to_string())
+    }
+}
+
+/// Sends notifications as messages from a Telegram bot.
+///
+/// Users need to have started a conversation with the bot for it to be able to message them.
+pub struct TelegramNotifier {
+    token: String,
+    client: reqwest::Client,
+}
+
+impl TelegramNotifier {
+    pub fn new(token: &str) -> Self {
+        TelegramNotifier {
+            token: token.to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for TelegramNotifier {
+    async fn notify(
+        &self,
+        recipient: &str,
+        notification: &Notification,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let endpoint = format!("https://api.telegram.org/bot{}/sendMessage", self.token);
+        let res: serde_json::Value = self
+            .client
+            .post(endpoint)
+            .json(&json!({
+                "chat_id": recipient,
+                "text": notification.body,
+            }))
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        if res["ok"].as_bool() != Some(true) {
+            return Err(format!("telegram error: {}", res["description"]).into());
+        }
+        Ok(res["result"]["message_id"].to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_channel() {
+        let cases = vec![
+            ("tg:123456789", NotificationChannel::Telegram, "123456789"),
+            (
+                "mailto:user@example.com",
+                NotificationChannel::Email,
+                "user@example.com",
+            ),
+            (
+                "https://example.com/hook",
+                NotificationChannel::Webhook,
+                "https://example.com/hook",
+            ),
+            ("x:@ryan_soury", NotificationChannel::X, "@ryan_soury"),
+            ("@ryan_soury", NotificationChannel::X, "@ryan_soury"),
+            ("ryan_soury", NotificationChannel::X, "ryan_soury"),
+        ];
+
+        for (notify, channel, recipient) in cases {
+            assert_eq!(NotificationChannel::parse(notify), (channel, recipient));
+        }
+    }
+
+    #[test]
+    fn test_webhook_signature() {
+        let notifier = WebhookNotifier::new("secret");
+        assert_eq!(
+            notifier.sign(b"{\"event\":\"minted\"}"),
+            "sha256=cb27f694c7af8842cf15a63cb7145d5b8dd5b7c0720ea11139a356676b7d5b9a"
+        );
+    }
+
+    #[test]
+    fn test_templates() {
+        let templates = NotificationTemplates::default();
+        let notification = templates.refunded(
+            "1858184885493485672",
+            900_000_000_000_000_000_000_000,
+            "cancelled by the requester",
+        );
+        assert_eq!(notification.event, "refunded");
+        assert_eq!(
+            notification.body,
+            "Your mint request for the X-NFT with ID:1858184885493485672 has been refunded: cancelled by the requester.\n0.9 NEAR have been returned to your wallet."
+        );
+
+        let template = NotificationTemplate::new("{tweet_id}", "{reason} ({tweet_id})");
+        let notification = template.render("failed", "1", &[("reason", "NFT already minted")]);
+        assert_eq!(notification.subject, "1");
+        assert_eq!(notification.body, "NFT already minted (1)");
+    }
+
+    #[test]
+    fn test_format_near() {
+        assert_eq!(format_near(0), "0 NEAR");
+        assert_eq!(format_near(5_000_000_000_000_000_000_000_000), "5 NEAR");
+        assert_eq!(format_near(1_250_000_000_000_000_000_000), "0.00125 NEAR");
+    }
+
+    #[tokio::test]
+    async fn test_unconfigured_channel() {
+        let outcome = Notifiers::new()
+            .notify(
+                "tg:123456789",
+                &NotificationTemplates::default().minted("1858184885493485672"),
+            )
+            .await;
+        assert_eq!(outcome.channel, NotificationChannel::Telegram);
+        assert_eq!(outcome.status(), "failed");
+    }
+}
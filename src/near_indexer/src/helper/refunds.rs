@@ -0,0 +1,178 @@
+use sea_orm::ActiveValue::Set;
+use sea_orm::{ActiveModelTrait, ColumnTrait, DbConn, EntityTrait, QueryFilter, QueryOrder};
+use std::error::Error;
+use tracing::{debug, info};
+
+use super::events::{ContractEvent, EmittedEvent};
+use super::job_queue::{record_notification, JobContext, MintStatus};
+use super::JSONTransaction;
+use crate::entity::near_transaction;
+
+/// Why the contract refunded the deposit of a mint request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefundReason {
+    /// The requester called `cancel_mint_request` once the lock time had passed.
+    Cancelled,
+    /// The lock time passed and another `mint_tweet_request`, or a bid, was made for the tweet.
+    Expired,
+}
+
+impl RefundReason {
+    /// The reason the request for `tweet_id` was refunded, from the events of the transaction.
+    ///
+    /// A refund emitted along with a new request, or a new auction, for the same tweet is for
+    /// the expired request it replaced.
+    pub fn from_events(events: &[EmittedEvent], tweet_id: &str) -> Self {
+        let replaced = events.iter().any(|emitted| match &emitted.event {
+            ContractEvent::TweetMintRequest(event) => event.tweet_id == tweet_id,
+            ContractEvent::AuctionOpened(event) => event.tweet_id == tweet_id,
+            _ => false,
+        });
+        if replaced {
+            RefundReason::Expired
+        } else {
+            RefundReason::Cancelled
+        }
+    }
+
+    /// The reason as shown to the user and stored in the `failure_reason` column.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RefundReason::Cancelled => "cancelled by the requester",
+            RefundReason::Expired => "expired after its lock time",
+        }
+    }
+}
+
+/// Marks the mint requests refunded by a transaction and notifies their users.
+///
+/// Refunds are found from the `CancelMintRequest` events of the transaction. The refunded
+/// request is the latest one indexed for the tweet that has not completed. Transactions
+/// already handled are skipped, so a transaction can safely be processed again.
+///
+/// # Parameters
+///
+/// - `db`: The database connection.
+/// - `transaction`: An indexed transaction.
+/// - `events`: The events emitted by the NFT contract during the transaction.
+/// - `context`: The clients the users are notified with.
+///
+/// # Returns
+///
+/// The number of mint requests refunded by the transaction.
+pub async fn process_refunds(
+    db: &DbConn,
+    transaction: &JSONTransaction,
+    events: &[EmittedEvent],
+    context: &JobContext,
+) -> Result<usize, Box<dyn Error>> {
+    let refunds: Vec<_> = events
+        .iter()
+        .filter_map(|emitted| match &emitted.event {
+            ContractEvent::CancelMintRequest(event) => Some(event),
+            _ => None,
+        })
+        .collect();
+    if refunds.is_empty() {
+        return Ok(0);
+    }
+
+    let processed = near_transaction::Entity::find()
+        .filter(near_transaction::Column::RefundTransactionHash.eq(&transaction.transaction_hash))
+        .one(db)
+        .await?;
+    if processed.is_some() {
+        return Ok(0);
+    }
+
+    let mut refunded = 0;
+    for event in refunds {
+        let reason = RefundReason::from_events(events, &event.tweet_id);
+        let request = near_transaction::Entity::find()
+            .filter(near_transaction::Column::TweetId.eq(&event.tweet_id))
+            // A `mint_tweet_request` refunds the request it replaces, not itself
+            .filter(near_transaction::Column::TransactionHash.ne(&transaction.transaction_hash))
+            .filter(near_transaction::Column::RefundTransactionHash.is_null())
+            .filter(near_transaction::Column::Status.is_not_in([
+                MintStatus::Notified.as_str(),
+                MintStatus::Refunded(String::new()).as_str(),
+            ]))
+            // Ids of different transaction sources are not comparable, block heights are
+            .order_by_desc(near_transaction::Column::BlockHeight)
+            .order_by_desc(near_transaction::Column::Id)
+            .one(db)
+            .await?;
+        let request = match request {
+            Some(request) => request,
+            None => {
+                debug!("No mint request indexed for refund {:?}", event);
+                continue;
+            }
+        };
+
+        info!(
+            "mint request {}: {} -> refunded {} ({})",
+            request.tweet_id,
+            request.status,
+            event.withdraw,
+            reason.as_str()
+        );
+        let mut update: near_transaction::ActiveModel = request.clone().into();
+        update.status = Set(MintStatus::Refunded(String::new()).as_str().to_string());
+        update.failure_reason = Set(Some(reason.as_str().to_string()));
+        update.attempts = Set(0);
+        update.refund_transaction_hash = Set(Some(transaction.transaction_hash.clone()));
+        update.refunded_amount = Set(Some(event.withdraw.to_string()));
+        let request = update.update(db).await?;
+        refunded += 1;
+
+        if let Some(notify) = request.user_to_notify.as_ref().filter(|n| !n.is_empty()) {
+            let notification = context.notifiers.templates.refunded(
+                &request.tweet_id,
+                event.withdraw,
+                reason.as_str(),
+            );
+            let outcome = context.notifiers.notify(notify, &notification).await;
+            record_notification(db, request.id, &outcome).await?;
+        }
+    }
+
+    Ok(refunded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::events::parse_receipt_logs;
+
+    #[test]
+    fn test_refund_reason() {
+        let cancel = r#"EVENT_JSON:{"standard":"custom","version":"1.0.0","event":"cancel_mint_request","data":{"tweet_id":"1","account":"alice.testnet","withdraw":1000}}"#.to_string();
+        let request = r#"EVENT_JSON:{"standard":"custom","version":"1.0.0","event":"tweet_mint_request","data":{"tweet_id":"1","account":"bob.testnet","deposit":1000,"image_url":"","notify":"@bob"}}"#.to_string();
+
+        let logs = vec![cancel, request];
+
+        let events = parse_receipt_logs("receipt", &logs[..1]);
+        assert_eq!(
+            RefundReason::from_events(&events, "1"),
+            RefundReason::Cancelled
+        );
+
+        let events = parse_receipt_logs("receipt", &logs);
+        assert_eq!(
+            RefundReason::from_events(&events, "1"),
+            RefundReason::Expired
+        );
+        assert_eq!(
+            RefundReason::from_events(&events, "2"),
+            RefundReason::Cancelled
+        );
+
+        let auction = r#"EVENT_JSON:{"standard":"custom","version":"1.0.0","event":"auction_opened","data":{"tweet_id":"1","account":"bob.testnet","deposit":1000,"ends_at":600000}}"#.to_string();
+        let events = parse_receipt_logs("receipt", &[logs[0].clone(), auction]);
+        assert_eq!(
+            RefundReason::from_events(&events, "1"),
+            RefundReason::Expired
+        );
+    }
+}
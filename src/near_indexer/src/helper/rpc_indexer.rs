@@ -0,0 +1,329 @@
+use async_trait::async_trait;
+use near_jsonrpc_client::{methods, JsonRpcClient};
+use near_jsonrpc_primitives::types::blocks::RpcBlockError;
+use near_jsonrpc_primitives::types::chunks::ChunkReference;
+use near_jsonrpc_primitives::types::transactions::TransactionInfo;
+use near_primitives::types::{BlockId, BlockReference, Finality};
+use near_primitives::views::{
+    ActionView, BlockView, FinalExecutionStatus, SignedTransactionView, TxExecutionStatus,
+};
+use std::error::Error;
+use std::marker::{Send, Sync};
+use tracing::{debug, info};
+
+use super::indexer::TransactionSource;
+use super::{Block, JSONAction, JSONTransaction, Outcomes};
+
+/// Transaction ids produced by the RPC source are `block_height * TX_ID_MULTIPLIER + position`,
+/// where `position` is the index of the transaction inside the block.
+///
+/// This keeps ids unique and ascending within the source. They are not comparable with the ids
+/// of nearblocks, so the source resumes from the block height of its checkpoint rather than
+/// from an id.
+pub const TX_ID_MULTIPLIER: i64 = 100_000;
+
+/// Number of blocks scanned per page.
+const BLOCKS_PER_PAGE: u64 = 50;
+
+/// Derives the id of a transaction from the height of the block including it and its position.
+pub fn transaction_id(block_height: u64, position: usize) -> i64 {
+    (block_height as i64) * TX_ID_MULTIPLIER + position as i64
+}
+
+/// Reads transactions sent to `account_id` from the blocks and chunks of a NEAR JSON-RPC node.
+///
+/// Only final blocks are scanned. Unlike `NearExplorerIndexer` this has no dependency on a
+/// third-party explorer, so it can be pointed at any node, including a local sandbox.
+pub struct NearRpcIndexer<'a> {
+    pub cursor: Option<i64>,
+    pub account_id: &'a str,
+    client: JsonRpcClient,
+    next_block: Option<u64>,
+    final_height: u64,
+    last_block: Option<(u64, String)>,
+}
+
+impl<'a> NearRpcIndexer<'a> {
+    /// Creates a new RPC indexer.
+    ///
+    /// # Parameters
+    ///
+    /// - `account_id`: The account whose incoming transactions are indexed.
+    /// - `rpc_url`: The NEAR JSON-RPC endpoint.
+    /// - `cursor`: The id of the last transaction processed by this source, transactions of
+    ///   `start_block` up to it are skipped.
+    /// - `start_block`: The first block to scan, the latest final block if not set.
+    pub fn new(
+        account_id: &'a str,
+        rpc_url: &str,
+        cursor: Option<i64>,
+        start_block: Option<u64>,
+    ) -> Result<Self, Box<dyn Error>> {
+        if account_id
+            .parse::<near_primitives::types::AccountId>()
+            .is_err()
+        {
+            return Err("Invalid account_id".into());
+        }
+
+        Ok(NearRpcIndexer {
+            cursor,
+            account_id,
+            client: JsonRpcClient::connect(rpc_url),
+            next_block: start_block,
+            final_height: 0,
+            last_block: None,
+        })
+    }
+
+    async fn fetch_final_height(&mut self) -> Result<u64, Box<dyn Error + Send + Sync>> {
+        let block = self
+            .client
+            .call(methods::block::RpcBlockRequest {
+                block_reference: BlockReference::Finality(Finality::Final),
+            })
+            .await?;
+        self.final_height = block.header.height;
+        Ok(self.final_height)
+    }
+
+    /// Fetches a block by height, returning `None` if the height was skipped by the chain.
+    async fn fetch_block(
+        &self,
+        height: u64,
+    ) -> Result<Option<BlockView>, Box<dyn Error + Send + Sync>> {
+        let response = self
+            .client
+            .call(methods::block::RpcBlockRequest {
+                block_reference: BlockReference::BlockId(BlockId::Height(height)),
+            })
+            .await;
+
+        match response {
+            Ok(block) => Ok(Some(block)),
+            Err(err) => match err.handler_error() {
+                Some(RpcBlockError::UnknownBlock { .. }) => Ok(None),
+                _ => Err(err.into()),
+            },
+        }
+    }
+
+    async fn fetch_status(
+        &self,
+        transaction: &SignedTransactionView,
+    ) -> Result<(Option<bool>, String), Box<dyn Error + Send + Sync>> {
+        let response = self
+            .client
+            .call(methods::tx::RpcTransactionStatusRequest {
+                transaction_info: TransactionInfo::TransactionId {
+                    tx_hash: transaction.hash,
+                    sender_account_id: transaction.signer_id.clone(),
+                },
+                wait_until: TxExecutionStatus::Final,
+            })
+            .await?;
+
+        let outcome = match response.final_execution_outcome {
+            Some(outcome) => outcome.into_outcome(),
+            None => return Ok((None, "0".to_string())),
+        };
+        let status = match outcome.status {
+            FinalExecutionStatus::SuccessValue(_) => Some(true),
+            FinalExecutionStatus::Failure(_) => Some(false),
+            _ => None,
+        };
+
+        Ok((
+            status,
+            outcome.transaction_outcome.outcome.tokens_burnt.to_string(),
+        ))
+    }
+
+    /// Collects the transactions to `account_id` included in the given block.
+    async fn block_transactions(
+        &self,
+        block: &BlockView,
+    ) -> Result<Vec<JSONTransaction>, Box<dyn Error + Send + Sync>> {
+        let mut transactions = vec![];
+        let mut position = 0;
+
+        for chunk_header in &block.chunks {
+            // Chunks that were not produced at this height are repeated from a previous block
+            if chunk_header.height_included != block.header.height {
+                continue;
+            }
+
+            let chunk = self
+                .client
+                .call(methods::chunk::RpcChunkRequest {
+                    chunk_reference: ChunkReference::ChunkHash {
+                        chunk_id: chunk_header.chunk_hash,
+                    },
+                })
+                .await?;
+
+            for transaction in chunk.transactions {
+                let id = transaction_id(block.header.height, position);
+                position += 1;
+
+                if transaction.receiver_id.as_str() != self.account_id {
+                    continue;
+                }
+                if self.cursor.is_some_and(|cursor| id <= cursor) {
+                    continue;
+                }
+
+                let (status, tokens_burnt) = self.fetch_status(&transaction).await?;
+                transactions.push(JSONTransaction {
+                    id: id.to_string(),
+                    signer_account_id: transaction.signer_id.to_string(),
+                    receiver_account_id: transaction.receiver_id.to_string(),
+                    transaction_hash: transaction.hash.to_string(),
+                    included_in_block_hash: block.header.hash.to_string(),
+                    block_timestamp: block.header.timestamp.to_string(),
+                    receipt_conversion_tokens_burnt: tokens_burnt,
+                    block: Block {
+                        block_height: block.header.height as u128,
+                    },
+                    actions: Some(transaction.actions.iter().map(to_json_action).collect()),
+                    outcomes: Outcomes { status },
+                });
+            }
+        }
+
+        Ok(transactions)
+    }
+
+    /// Scans the next range of final blocks.
+    async fn fetch(&mut self) -> Result<Vec<JSONTransaction>, Box<dyn Error + Send + Sync>> {
+        let final_height = self.fetch_final_height().await?;
+        let from = match self.next_block {
+            Some(height) => height,
+            None => final_height,
+        };
+        let to = final_height.min(from + BLOCKS_PER_PAGE - 1);
+        info!("scanning blocks {}..={} of {}", from, to, final_height);
+
+        let mut transactions = vec![];
+        for height in from..=to {
+            match self.fetch_block(height).await? {
+                Some(block) => {
+                    transactions.extend(self.block_transactions(&block).await?);
+                    self.last_block = Some((height, block.header.hash.to_string()));
+                }
+                None => debug!("skipped block at height {}", height),
+            }
+        }
+
+        if let Some(last) = transactions.last() {
+            self.cursor = Some(last.id.parse::<i64>()?);
+        }
+        self.next_block = Some(to + 1);
+
+        Ok(transactions)
+    }
+}
+
+/// Converts an action into the shape returned by the explorer API.
+fn to_json_action(action: &ActionView) -> JSONAction {
+    let (kind, method, args) = match action {
+        ActionView::FunctionCall {
+            method_name, args, ..
+        } => (
+            "FUNCTION_CALL",
+            Some(method_name.clone()),
+            String::from_utf8(args.to_vec()).ok(),
+        ),
+        ActionView::CreateAccount => ("CREATE_ACCOUNT", None, None),
+        ActionView::DeployContract { .. } => ("DEPLOY_CONTRACT", None, None),
+        ActionView::Transfer { .. } => ("TRANSFER", None, None),
+        ActionView::Stake { .. } => ("STAKE", None, None),
+        ActionView::AddKey { .. } => ("ADD_KEY", None, None),
+        ActionView::DeleteKey { .. } => ("DELETE_KEY", None, None),
+        ActionView::DeleteAccount { .. } => ("DELETE_ACCOUNT", None, None),
+        _ => ("DELEGATE", None, None),
+    };
+
+    JSONAction {
+        action: kind.to_string(),
+        method,
+        args,
+    }
+}
+
+#[async_trait]
+impl<'a> TransactionSource for NearRpcIndexer<'a> {
+    /// Retrieves the transactions of the first range of blocks after the cursor.
+    async fn get_transactions(
+        &mut self,
+    ) -> Result<Vec<JSONTransaction>, Box<dyn Error + Send + Sync>> {
+        self.fetch().await
+    }
+
+    /// Retrieves the transactions of the next range of blocks.
+    async fn next_page(&mut self) -> Result<Vec<JSONTransaction>, Box<dyn Error + Send + Sync>> {
+        self.fetch().await
+    }
+
+    /// There is a next page as long as the final head has not been reached.
+    fn has_next_page(&self) -> bool {
+        self.next_block
+            .is_some_and(|height| height <= self.final_height)
+    }
+
+    fn cursor(&self) -> Option<i64> {
+        self.cursor
+    }
+
+    fn last_block(&self) -> Option<(u64, String)> {
+        self.last_block.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transaction_id() {
+        let id = transaction_id(183_401_230, 42);
+        assert_eq!(id, 18_340_123_000_042);
+        assert!(transaction_id(183_401_230, 1) < transaction_id(183_401_231, 0));
+    }
+
+    #[test]
+    fn test_resume_from_start_block() {
+        // A cursor is never mapped back to a block, it may come from another source
+        let indexer = NearRpcIndexer::new(
+            "x-bitte-nfts.testnet",
+            "https://rpc.testnet.near.org",
+            Some(transaction_id(1000, 3)),
+            Some(1200),
+        )
+        .unwrap();
+        assert_eq!(indexer.next_block, Some(1200));
+
+        let indexer = NearRpcIndexer::new(
+            "x-bitte-nfts.testnet",
+            "http://localhost:3030",
+            Some(6_340_794_012),
+            None,
+        )
+        .unwrap();
+        assert_eq!(indexer.next_block, None);
+    }
+
+    #[tokio::test]
+    async fn test_near_rpc_indexer() {
+        let mut indexer = NearRpcIndexer::new(
+            "x-bitte-nfts.testnet",
+            "https://rpc.testnet.near.org",
+            Some(0),
+            None,
+        )
+        .unwrap();
+        let _ = indexer.get_transactions().await.unwrap();
+        assert!(indexer.final_height > 0);
+        assert!(!indexer.has_next_page());
+    }
+}
@@ -0,0 +1,356 @@
+use near_crypto::InMemorySigner;
+use near_jsonrpc_client::methods::query::RpcQueryRequest;
+use near_jsonrpc_client::methods::tx::RpcTransactionResponse;
+use near_jsonrpc_client::{methods, JsonRpcClient};
+use near_jsonrpc_primitives::types::query::QueryResponseKind;
+use near_jsonrpc_primitives::types::transactions::{RpcTransactionError, TransactionInfo};
+use near_primitives::errors::InvalidTxError;
+use near_primitives::hash::CryptoHash;
+use near_primitives::transaction::{Action, FunctionCallAction, Transaction};
+use near_primitives::types::{AccountId, Balance, BlockReference, Finality, Gas};
+use near_primitives::views::{QueryRequest, TxExecutionStatus};
+use std::env;
+use std::error::Error;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use tokio::time;
+use tracing::{debug, warn};
+
+/// Number of times a transaction is re-signed after being rejected for an invalid nonce.
+const MAX_NONCE_RETRIES: usize = 3;
+
+/// Time given to a submitted transaction to be executed.
+pub const TRANSACTION_TIMEOUT: time::Duration = time::Duration::from_secs(60);
+
+/// An access key of the signer account, along with the last nonce used with it.
+struct SignerKey {
+    signer: InMemorySigner,
+    nonce: Mutex<Option<u64>>,
+}
+
+impl SignerKey {
+    /// Reserves the nonce following the last one used, if it is known.
+    fn reserve_cached(&self) -> Option<u64> {
+        let mut nonce = self.nonce.lock().unwrap();
+        let next = (*nonce)? + 1;
+        *nonce = Some(next);
+        Some(next)
+    }
+
+    /// Reserves the nonce following both the chain nonce and the last one used.
+    fn reserve(&self, chain_nonce: u64) -> u64 {
+        let mut nonce = self.nonce.lock().unwrap();
+        let next = nonce.unwrap_or(0).max(chain_nonce) + 1;
+        *nonce = Some(next);
+        next
+    }
+
+    /// Replaces the cached nonce with the one reported by the chain.
+    fn resync(&self, chain_nonce: Option<u64>) {
+        *self.nonce.lock().unwrap() = chain_nonce;
+    }
+}
+
+/// A transaction that has been accepted by the network.
+#[derive(Debug, Clone)]
+pub struct SubmittedTransaction {
+    pub hash: CryptoHash,
+    pub signer_id: AccountId,
+}
+
+/// Signs and submits transactions on behalf of a NEAR account.
+///
+/// Nonces are cached and incremented locally for every access key, so concurrent
+/// transactions never reuse one, and are resynchronised from the chain when a transaction
+/// is rejected with `InvalidNonce`. When several access keys are configured, transactions
+/// are spread across them in turn.
+pub struct NearSigner {
+    pub account_id: AccountId,
+    keys: Vec<SignerKey>,
+    next_key: AtomicUsize,
+}
+
+impl NearSigner {
+    /// Creates a signer from an account and the secret keys of some of its access keys.
+    pub fn new(account_id: &str, secret_keys: &[&str]) -> Result<Self, Box<dyn Error>> {
+        let account_id: AccountId = account_id.parse()?;
+        if secret_keys.is_empty() {
+            return Err("At least one secret key is required".into());
+        }
+
+        let mut keys = vec![];
+        for secret_key in secret_keys {
+            let secret_key: near_crypto::SecretKey = secret_key.trim().parse()?;
+            keys.push(SignerKey {
+                signer: InMemorySigner::from_secret_key(account_id.clone(), secret_key),
+                nonce: Mutex::new(None),
+            });
+        }
+
+        Ok(NearSigner {
+            account_id,
+            keys,
+            next_key: AtomicUsize::new(0),
+        })
+    }
+
+    /// Creates a signer from `NEAR_SIGNER_ACCOUNT_ID` and `NEAR_ACCOUNT_SECRET_KEY`.
+    ///
+    /// `NEAR_ACCOUNT_SECRET_KEY` can hold several comma separated keys of the account.
+    pub fn from_env() -> Self {
+        let account_id = env::var("NEAR_SIGNER_ACCOUNT_ID").expect("ACCOUNT_ID_NOT_PRESENT");
+        let secret_keys = env::var("NEAR_ACCOUNT_SECRET_KEY").expect("SECRET_KEY_NOT_PRESENT");
+        let secret_keys: Vec<&str> = secret_keys.split(',').collect();
+        Self::new(&account_id, &secret_keys).unwrap()
+    }
+
+    /// Number of access keys transactions are spread across.
+    pub fn key_count(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns the balance of the signer account, in yoctoNEAR.
+    pub async fn balance(&self, client: &JsonRpcClient) -> Result<Balance, Box<dyn Error>> {
+        let account_query_response = client
+            .call(RpcQueryRequest {
+                block_reference: BlockReference::Finality(Finality::Final),
+                request: QueryRequest::ViewAccount {
+                    account_id: self.account_id.clone(),
+                },
+            })
+            .await?;
+
+        match account_query_response.kind {
+            QueryResponseKind::ViewAccount(account) => Ok(account.amount),
+            _ => Err("failed to extract account balance")?,
+        }
+    }
+
+    fn rotate(&self) -> &SignerKey {
+        let index = self.next_key.fetch_add(1, Ordering::Relaxed) % self.keys.len();
+        &self.keys[index]
+    }
+
+    async fn next_nonce(
+        &self,
+        client: &JsonRpcClient,
+        key: &SignerKey,
+    ) -> Result<u64, Box<dyn Error>> {
+        if let Some(nonce) = key.reserve_cached() {
+            return Ok(nonce);
+        }
+
+        let access_key_query_response = client
+            .call(RpcQueryRequest {
+                block_reference: BlockReference::latest(),
+                request: QueryRequest::ViewAccessKey {
+                    account_id: key.signer.account_id.clone(),
+                    public_key: key.signer.public_key.clone(),
+                },
+            })
+            .await?;
+
+        match access_key_query_response.kind {
+            QueryResponseKind::AccessKey(access_key) => Ok(key.reserve(access_key.nonce)),
+            _ => Err("failed to extract current nonce")?,
+        }
+    }
+
+    /// Signs a function call with the next access key and submits it to the network.
+    ///
+    /// # Parameters
+    ///
+    /// - `client`: The NEAR JSON-RPC client.
+    /// - `receiver_id`: The contract to call.
+    /// - `method_name`: The method to call.
+    /// - `args`: The serialized arguments of the call.
+    /// - `gas`: The gas attached to the call.
+    /// - `deposit`: The deposit attached to the call.
+    ///
+    /// # Returns
+    ///
+    /// The submitted transaction, once it has been accepted by the network.
+    pub async fn submit_function_call(
+        &self,
+        client: &JsonRpcClient,
+        receiver_id: AccountId,
+        method_name: &str,
+        args: Vec<u8>,
+        gas: Gas,
+        deposit: Balance,
+    ) -> Result<SubmittedTransaction, Box<dyn Error>> {
+        let key = self.rotate();
+        let mut retries = 0;
+        loop {
+            let block_hash = client
+                .call(methods::block::RpcBlockRequest {
+                    block_reference: BlockReference::Finality(Finality::Final),
+                })
+                .await?
+                .header
+                .hash;
+
+            let nonce = self.next_nonce(client, key).await?;
+            let transaction = Transaction {
+                signer_id: key.signer.account_id.clone(),
+                public_key: key.signer.public_key.clone(),
+                nonce,
+                receiver_id: receiver_id.clone(),
+                block_hash,
+                actions: vec![Action::FunctionCall(Box::new(FunctionCallAction {
+                    method_name: method_name.to_string(),
+                    args: args.clone(),
+                    gas,
+                    deposit,
+                }))],
+            };
+            let signed_transaction = transaction.sign(&key.signer);
+            let hash = signed_transaction.get_hash();
+
+            let response = client
+                .call(methods::send_tx::RpcSendTransactionRequest {
+                    signed_transaction,
+                    wait_until: TxExecutionStatus::None,
+                })
+                .await;
+
+            match response {
+                Ok(_) => {
+                    debug!(
+                        "submitted {} with key {} and nonce {}",
+                        hash, key.signer.public_key, nonce
+                    );
+                    return Ok(SubmittedTransaction {
+                        hash,
+                        signer_id: key.signer.account_id.clone(),
+                    });
+                }
+                Err(err) => match err.handler_error() {
+                    Some(RpcTransactionError::InvalidTransaction {
+                        context: InvalidTxError::InvalidNonce { tx_nonce, ak_nonce },
+                    }) if retries < MAX_NONCE_RETRIES => {
+                        warn!(
+                            "nonce {} of key {} is behind {}, resyncing",
+                            tx_nonce, key.signer.public_key, ak_nonce
+                        );
+                        key.resync(Some(*ak_nonce));
+                        retries += 1;
+                    }
+                    _ => {
+                        // The nonce may or may not have been consumed, read it again next time
+                        key.resync(None);
+                        Err(err)?
+                    }
+                },
+            }
+        }
+    }
+}
+
+/// Polls the status of a submitted transaction until it has been executed.
+///
+/// # Parameters
+///
+/// - `client`: The NEAR JSON-RPC client the transaction was submitted with.
+/// - `tx_hash`: The hash of the transaction.
+/// - `sender_account_id`: The account that signed the transaction.
+///
+/// # Returns
+///
+/// The transaction response, or an error if it is not recognized within `TRANSACTION_TIMEOUT`.
+pub async fn wait_for_transaction(
+    client: &JsonRpcClient,
+    tx_hash: CryptoHash,
+    sender_account_id: AccountId,
+) -> Result<RpcTransactionResponse, Box<dyn Error>> {
+    let sent_at = time::Instant::now();
+    loop {
+        let response = client
+            .call(methods::tx::RpcTransactionStatusRequest {
+                transaction_info: TransactionInfo::TransactionId {
+                    tx_hash,
+                    sender_account_id: sender_account_id.clone(),
+                },
+                wait_until: TxExecutionStatus::Executed,
+            })
+            .await;
+        let delta = sent_at.elapsed();
+
+        if delta > TRANSACTION_TIMEOUT {
+            Err("time limit exceeded for the transaction to be recognized")?;
+        }
+
+        match response {
+            Err(err) => match err.handler_error() {
+                Some(
+                    RpcTransactionError::TimeoutError
+                    | RpcTransactionError::UnknownTransaction { .. },
+                ) => {
+                    time::sleep(time::Duration::from_secs(2)).await;
+                    continue;
+                }
+                _ => Err(err)?,
+            },
+            Ok(response) => {
+                debug!("response gotten after: {}s", delta.as_secs());
+                return Ok(response);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signer(seeds: &[&str]) -> NearSigner {
+        let account_id: AccountId = "x-bitte-nfts.testnet".parse().unwrap();
+        NearSigner {
+            account_id: account_id.clone(),
+            keys: seeds
+                .iter()
+                .map(|seed| SignerKey {
+                    signer: InMemorySigner::from_seed(
+                        account_id.clone(),
+                        near_crypto::KeyType::ED25519,
+                        seed,
+                    ),
+                    nonce: Mutex::new(None),
+                })
+                .collect(),
+            next_key: AtomicUsize::new(0),
+        }
+    }
+
+    #[test]
+    fn test_nonce_cache() {
+        let signer = signer(&["test"]);
+        let key = &signer.keys[0];
+
+        assert_eq!(key.reserve_cached(), None);
+        assert_eq!(key.reserve(10), 11);
+        // Following transactions do not need to query the chain
+        assert_eq!(key.reserve_cached(), Some(12));
+        assert_eq!(key.reserve_cached(), Some(13));
+        // Nonces used outside of the indexer are skipped
+        assert_eq!(key.reserve(20), 21);
+
+        // Rejected with InvalidNonce { ak_nonce: 30 }
+        key.resync(Some(30));
+        assert_eq!(key.reserve_cached(), Some(31));
+
+        key.resync(None);
+        assert_eq!(key.reserve_cached(), None);
+    }
+
+    #[test]
+    fn test_key_rotation() {
+        let signer = signer(&["first", "second"]);
+        let first = signer.keys[0].signer.public_key.clone();
+        let second = signer.keys[1].signer.public_key.clone();
+
+        assert_eq!(signer.rotate().signer.public_key, first);
+        assert_eq!(signer.rotate().signer.public_key, second);
+        assert_eq!(signer.rotate().signer.public_key, first);
+    }
+}
@@ -6,6 +6,8 @@ use std::env;
 use std::error::Error;
 use std::marker::{Send, Sync};
 
+use super::notifier::NotificationTemplates;
+
 pub struct OathTweeterHandler {
     consumer_key: String,
     consumer_secret: String,
@@ -92,6 +94,25 @@ impl OathTweeterHandler {
         &self,
         tweet_id: &str,
         notify: &str,
+    ) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        let notification = NotificationTemplates::from_env().minted(tweet_id);
+        self.mention(notify, &notification.body).await
+    }
+
+    /// Posts a tweet greeting a user.
+    ///
+    /// # Parameters
+    ///
+    /// - `notify`: The username to mention in the tweet.
+    /// - `text`: The text following the greeting.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing a `Value` representing the API response, or an error if the request fails.
+    pub async fn mention(
+        &self,
+        notify: &str,
+        text: &str,
     ) -> Result<Value, Box<dyn Error + Send + Sync>> {
         let secrets =
             reqwest_oauth1::Secrets::new(self.consumer_key.clone(), self.consumer_secret.clone())
@@ -99,8 +120,9 @@ impl OathTweeterHandler {
         let endpoint = "https://api.x.com/2/tweets";
 
         let payload = json!({
-            "text": format!("Hey {},  Your X-NFT with ID:{} has been successfully minted! 🎉\nCheck your wallet to confirm receipt of your new digital collectible. Enjoy owning a unique piece of blockchain art! #NFT #Web3", add_prefix_if_needed(notify), tweet_id)
-        }).to_string();
+            "text": format!("Hey {},  {}", add_prefix_if_needed(notify), text)
+        })
+        .to_string();
 
         let client = reqwest::Client::new();
         let resp = client
@@ -111,7 +133,7 @@ impl OathTweeterHandler {
             .header("content-type", "application/json")
             .send()
             .await?;
-        let res: Value = resp.json().await.unwrap();
+        let res: Value = resp.json().await?;
         Ok(res)
     }
 }
@@ -0,0 +1,128 @@
+use futures::stream::{self, StreamExt};
+use sea_orm::{DbConn, DbErr};
+use std::env;
+use tracing::error;
+
+use super::job_queue::{advance, JobContext, MintStatus};
+use crate::entity::near_transaction;
+
+/// Number of mint requests processed at the same time when `MINT_WORKER_CONCURRENCY` is not set.
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+/// A bounded pool of workers advancing mint requests concurrently.
+///
+/// Requests for different tweets run in parallel, up to `concurrency` at a time.
+/// Requests for the same tweet are processed one after the other, in the order they were indexed.
+pub struct WorkerPool {
+    pub concurrency: usize,
+}
+
+impl WorkerPool {
+    pub fn new(concurrency: usize) -> Self {
+        WorkerPool {
+            concurrency: concurrency.max(1),
+        }
+    }
+
+    /// Creates a pool sized from `MINT_WORKER_CONCURRENCY`.
+    pub fn from_env() -> Self {
+        let concurrency = env::var("MINT_WORKER_CONCURRENCY")
+            .map(|value| {
+                value
+                    .parse::<usize>()
+                    .expect("MINT_WORKER_CONCURRENCY must be a number")
+            })
+            .unwrap_or(DEFAULT_CONCURRENCY);
+        Self::new(concurrency)
+    }
+
+    /// Advances the given mint requests until each one completes, fails or a stage errors.
+    ///
+    /// # Parameters
+    ///
+    /// - `db`: The database connection.
+    /// - `jobs`: The `near_transaction` rows of the mint requests, ordered by id.
+    /// - `context`: The clients used by the stages.
+    ///
+    /// # Returns
+    ///
+    /// The first database error encountered, once every worker has finished.
+    pub async fn process(
+        &self,
+        db: &DbConn,
+        jobs: Vec<near_transaction::Model>,
+        context: &JobContext,
+    ) -> Result<(), DbErr> {
+        let results = stream::iter(group_by_tweet(jobs))
+            .map(|jobs| async move {
+                for job in jobs {
+                    let job = advance(db, job, context).await?;
+                    // A later request for the tweet must wait for this one to complete
+                    if !MintStatus::from_model(&job)
+                        .map_err(DbErr::Custom)?
+                        .is_terminal()
+                    {
+                        break;
+                    }
+                }
+                Ok::<(), DbErr>(())
+            })
+            .buffer_unordered(self.concurrency)
+            .collect::<Vec<_>>()
+            .await;
+
+        let mut first_error = None;
+        for result in results {
+            if let Err(err) = result {
+                error!("worker-error: {:?}", err);
+                first_error.get_or_insert(err);
+            }
+        }
+
+        match first_error {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Groups mint requests by tweet, keeping the order in which they were given.
+pub fn group_by_tweet(jobs: Vec<near_transaction::Model>) -> Vec<Vec<near_transaction::Model>> {
+    let mut groups: Vec<Vec<near_transaction::Model>> = vec![];
+    for job in jobs {
+        match groups
+            .iter_mut()
+            .find(|group| group[0].tweet_id == job.tweet_id)
+        {
+            Some(group) => group.push(job),
+            None => groups.push(vec![job]),
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_by_tweet() {
+        let groups = group_by_tweet(vec![
+            near_transaction::Model::test_fixture(1, "a"),
+            near_transaction::Model::test_fixture(2, "b"),
+            near_transaction::Model::test_fixture(3, "a"),
+            near_transaction::Model::test_fixture(4, "c"),
+        ]);
+        let ids: Vec<Vec<i64>> = groups
+            .iter()
+            .map(|group| group.iter().map(|job| job.id).collect())
+            .collect();
+        assert_eq!(ids, vec![vec![1, 3], vec![2], vec![4]]);
+    }
+
+    #[test]
+    fn test_concurrency_is_at_least_one() {
+        assert_eq!(WorkerPool::new(0).concurrency, 1);
+        assert_eq!(WorkerPool::new(8).concurrency, 8);
+    }
+}
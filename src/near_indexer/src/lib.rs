@@ -1,2 +1,3 @@
+pub mod entity;
 pub mod generated;
 pub mod helper;
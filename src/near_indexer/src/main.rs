@@ -5,17 +5,25 @@ pub mod helper;
 use async_std::task::sleep;
 use dotenv::dotenv;
 use entity::near_transaction;
-use helper::cktls::verify_near_proof_v2;
+use helper::admin::{AdminApi, AdminState};
+use helper::batch::{batch_size_from_env, check_batch_size};
+use helper::events::EmittedEvent;
+use helper::indexer::{NearExplorerIndexer, TransactionSource, TransactionSourceKind};
+use helper::job_queue::{self, JobContext, MintStatus};
+use helper::notifier::Notifiers;
+use helper::rpc_indexer::NearRpcIndexer;
+use helper::signer::NearSigner;
+use helper::worker_pool::WorkerPool;
 use helper::*;
 use migration::{Migrator, MigratorTrait};
 use near_client::client::NearClient;
-use near_client::prelude::{AccountId, Finality};
+use near_jsonrpc_client::JsonRpcClient;
 use reqwest::Url;
 use sea_orm::ActiveValue::Set;
-use sea_orm::{Database, QueryOrder};
+use sea_orm::{ColumnTrait, Database, QueryFilter};
 use sea_orm::{DbConn, DbErr, EntityTrait};
-use serde_json::{json, Error as SJError};
 use std::str::FromStr;
+use std::sync::Arc;
 use std::{env, time::Duration};
 use tracing::{debug, error, info};
 
@@ -42,7 +50,24 @@ async fn main() {
 
     // Init Near Client
     let client = NearClient::new(Url::from_str(&near_rpc).unwrap()).unwrap();
-    let near_block_key = env::var("NEAR_BLOCK_KEY").expect("NEAR_BLOCK_KEY must be set");
+    // Used to read the events emitted during indexed transactions
+    let rpc_client = JsonRpcClient::connect(&near_rpc);
+
+    // Select where transactions are read from, nearblocks.io by default
+    let source_kind = env::var("NEAR_TRANSACTION_SOURCE")
+        .unwrap_or("nearblocks".to_owned())
+        .parse::<TransactionSourceKind>()
+        .unwrap();
+    let near_block_key = if source_kind == TransactionSourceKind::NearBlocks {
+        env::var("NEAR_BLOCK_KEY").expect("NEAR_BLOCK_KEY must be set")
+    } else {
+        String::new()
+    };
+    let mut start_block = env::var("NEAR_INDEXER_START_BLOCK").ok().map(|height| {
+        height
+            .parse::<u64>()
+            .expect("NEAR_INDEXER_START_BLOCK must be a number")
+    });
 
     // Initialize tracing. In order to view logs, run `RUST_LOG=info cargo run`
     tracing_subscriber::fmt()
@@ -50,28 +75,96 @@ async fn main() {
         .with_env_filter(tracing_subscriber::filter::EnvFilter::from_default_env())
         .init();
 
-    let twitter_client = twitter::OathTweeterHandler::default();
+    let context = Arc::new(JobContext {
+        client,
+        notifiers: Notifiers::from_env(),
+        signer: NearSigner::from_env(),
+        batch_size: check_batch_size(batch_size_from_env()).await,
+    });
+    let pool = WorkerPool::from_env();
+    info!(
+        "Processing mint requests with {} workers, {} signer keys and batches of {}, notifying via {:?}",
+        pool.concurrency,
+        context.signer.key_count(),
+        context.batch_size,
+        context.notifiers.configured()
+    );
+
+    // Serve the admin API when ADMIN_API_PORT is set
+    if let Some(admin) = AdminApi::from_env() {
+        admin.spawn(AdminState::new(
+            db.clone(),
+            rpc_client.clone(),
+            context.clone(),
+            source_kind.as_str(),
+        ));
+    }
 
     loop {
-        let query = near_transaction::Entity::find()
-            .order_by_desc(near_transaction::Column::Id)
-            .one(&db)
-            .await;
+        // Pick up requests interrupted by a restart or a failing stage
+        if let Err(err) = job_queue::resume_pending(&db, &pool, &context).await {
+            error!("resume-error: {:?}", err);
+        }
 
-        if query.is_err() {
-            error!("DB-error: {:?}", query.err());
-            return;
+        // Turn the highest bid of closed auctions into mint requests
+        if let Err(err) =
+            auctions::settle_closed_auctions(&context, &rpc_client, &nft_contract_id).await
+        {
+            error!("auction-error: {:?}", err);
         }
-        let query = query.unwrap();
-        let cursor = if query.is_some() {
-            Some(query.clone().unwrap().id)
-        } else {
-            Some(0)
+
+        // Resume from the last block processed, rewinding if it has been orphaned
+        let checkpoint = match checkpoint::resume(&db, &rpc_client, source_kind.as_str()).await {
+            Ok(checkpoint) => checkpoint,
+            Err(err) => {
+                error!("checkpoint-error: {:?}", err);
+                return;
+            }
+        };
+        let (cursor, next_block) = match &checkpoint {
+            Some(checkpoint) => (
+                Some(checkpoint.cursor),
+                Some(checkpoint.block_height as u64 + 1),
+            ),
+            None => match checkpoint::initial_position(&db, source_kind).await {
+                Ok((cursor, next_block)) => (cursor, next_block.max(start_block)),
+                Err(err) => {
+                    error!("DB-error: {:?}", err);
+                    return;
+                }
+            },
+        };
+        // Without anything to resume from, the RPC source starts at the latest final block. It
+        // is kept for the next passes, so blocks produced in between are not skipped when
+        // nothing was checkpointed.
+        let next_block = match next_block {
+            None if source_kind == TransactionSourceKind::Rpc => {
+                match checkpoint::final_height(&rpc_client).await {
+                    Ok(height) => {
+                        start_block = Some(height);
+                        start_block
+                    }
+                    Err(err) => {
+                        error!("final-block-error: {:?}", err);
+                        None
+                    }
+                }
+            }
+            next_block => next_block,
         };
 
         debug!("Cursor at:{:?}\n\n", &cursor);
 
-        let indexer = indexer::NearExplorerIndexer::new(&nft_contract_id, &near_block_key, cursor);
+        let indexer: Result<Box<dyn TransactionSource + '_>, _> = match source_kind {
+            TransactionSourceKind::NearBlocks => {
+                NearExplorerIndexer::new(&nft_contract_id, &near_block_key, cursor)
+                    .map(|indexer| Box::new(indexer) as Box<dyn TransactionSource + '_>)
+            }
+            TransactionSourceKind::Rpc => {
+                NearRpcIndexer::new(&nft_contract_id, &near_rpc, cursor, next_block)
+                    .map(|indexer| Box::new(indexer) as Box<dyn TransactionSource + '_>)
+            }
+        };
         if indexer.is_err() {
             error!("indexer-init-error: {:?}", indexer.err());
             return;
@@ -80,21 +173,108 @@ async fn main() {
         let mut indexer = indexer.unwrap();
         let mut data = indexer.get_transactions().await;
 
-        if data.is_err() {
-            error!("init-fetch-error: {:?}", data.err());
-            return;
-        }
-
         loop {
-            let transactions = data.unwrap();
+            let transactions = match data {
+                Ok(transactions) => transactions,
+                Err(err) => {
+                    error!("fetch-error: {:?}", err);
+                    break;
+                }
+            };
             debug!("Found {} Transactions", transactions.len());
+            let final_height = match checkpoint::final_height(&rpc_client).await {
+                Ok(height) => height,
+                Err(err) => {
+                    error!("final-block-error: {:?}", err);
+                    break;
+                }
+            };
+            let mut recorded = true;
             for transaction in transactions {
                 println!("{transaction:?}");
-                let _ = process_near_transaction(&db, &transaction, &client, &twitter_client)
-                    .await
-                    .unwrap();
+                // Blocks that are not final yet can still be orphaned, wait for the next pass
+                let block_height = transaction.block.block_height as u64;
+                if block_height > final_height {
+                    debug!(
+                        "Waiting for block {} of {} to be final",
+                        block_height, transaction.transaction_hash
+                    );
+                    recorded = false;
+                    break;
+                }
+                let events =
+                    match events::fetch_events(&rpc_client, &transaction, &nft_contract_id).await {
+                        Ok(events) => events,
+                        Err(err) => {
+                            error!("events-error: {} {:?}", transaction.transaction_hash, err);
+                            recorded = false;
+                            break;
+                        }
+                    };
+                if let Err(err) = events::record_events(&db, &transaction, &events).await {
+                    error!("events-error: {} {:?}", transaction.transaction_hash, err);
+                    recorded = false;
+                    break;
+                }
+                // Refunds are handled first, so they are retried with the transaction on failure
+                if let Err(err) =
+                    refunds::process_refunds(&db, &transaction, &events, &context).await
+                {
+                    error!("refund-error: {} {:?}", transaction.transaction_hash, err);
+                    recorded = false;
+                    break;
+                }
+                if let Err(err) = process_near_transaction(&db, &transaction, &events).await {
+                    // Stop here so the transaction is picked up again on the next pass
+                    error!("process-error: {} {:?}", transaction.transaction_hash, err);
+                    recorded = false;
+                    break;
+                }
+                // Failed and ignored transactions move the checkpoint too, so they are not
+                // fetched again
+                let saved = match transaction.id.parse::<i64>() {
+                    Ok(id) => {
+                        checkpoint::save(
+                            &db,
+                            source_kind.as_str(),
+                            id,
+                            block_height,
+                            &transaction.included_in_block_hash,
+                        )
+                        .await
+                    }
+                    Err(e) => Err(DbErr::Custom(format!(
+                        "Invalid transaction id {}: {}",
+                        transaction.id, e
+                    ))),
+                };
+                if let Err(err) = saved {
+                    error!(
+                        "checkpoint-error: {} {:?}",
+                        transaction.transaction_hash, err
+                    );
+                    recorded = false;
+                    break;
+                }
+            }
+            // Blocks scanned without any transaction are checkpointed as well
+            if let Some((height, hash)) = indexer.last_block().filter(|_| recorded) {
+                let cursor = indexer.cursor().unwrap_or(0);
+                if let Err(err) =
+                    checkpoint::save(&db, source_kind.as_str(), cursor, height, &hash).await
+                {
+                    error!("checkpoint-error: {:?}", err);
+                    recorded = false;
+                }
+            }
+            // Process the mint requests found in this page concurrently
+            if let Err(err) = job_queue::resume_pending(&db, &pool, &context).await {
+                error!("worker-pool-error: {:?}", err);
+            }
+            debug!("cursor: {:?}", indexer.cursor());
+            if !recorded {
+                break;
             }
-            println!("cursor: {:?}", indexer.cursor);
             // Walk pages
             if !indexer.has_next_page() {
                 println!("All transaction indexed");
@@ -108,171 +288,89 @@ async fn main() {
         sleep(Duration::from_secs(300)).await;
     }
 }
+
+/// Records the mint requests made in a transaction.
+///
+/// The requests are read from the `TweetMintRequest` events emitted by the NFT contract rather
+/// than from the arguments of the first action, so requests made by any action of a batch
+/// transaction, or by a receipt the transaction led to, are recorded too. Each request becomes a
+/// job of its own, keyed by the transaction and the position of its event.
+///
+/// # Parameters
+///
+/// - `db`: The database connection.
+/// - `transaction`: The indexed transaction.
+/// - `events`: The events emitted by the NFT contract during the transaction.
+///
+/// # Returns
+///
+/// Whether the transaction had already been recorded.
 pub async fn process_near_transaction(
     db: &DbConn,
     transaction: &JSONTransaction,
-    client: &NearClient,
-    notifier: &twitter::OathTweeterHandler,
+    events: &[EmittedEvent],
 ) -> Result<bool, DbErr> {
-    // Get the NFT contract ID from environment variable or use default value
-    let nft_contract_id =
-        env::var("NEAR_NFT_CONTRACT_ACCOUNT_ID").unwrap_or("x-bitte-nfts.testnet".to_owned());
-    let nft_contract_id = AccountId::from_str(&nft_contract_id).unwrap();
-
-    // Parse the transaction ID as an integer
-    let pk = transaction.id.parse::<i64>().unwrap();
-
-    // Find the near_transaction in the database by primary key
-    let _near_transaction: Option<near_transaction::Model> =
-        near_transaction::Entity::find_by_id(pk).one(db).await?;
-
-    match _near_transaction {
-        Some(_) => Ok(true), // Transaction exists, return true
-        None => {
-            // Check if the transaction status is failed
-            if transaction.outcomes.status.is_none()
-                || !transaction.outcomes.status.unwrap_or(false)
-            {
-                debug!(
-                    "Found Failed BlockChain Transaction Ignored, {}",
-                    transaction.transaction_hash
-                );
-                return Ok(false);
-            }
-
-            if (&transaction.clone().actions).is_none() {
-                debug!(
-                    "Ignored Transaction: {} Invalid",
-                    transaction.transaction_hash
-                );
-                return Ok(false);
-            }
-            // Check if there's no action method in the transaction
-            let _action = transaction.actions.clone().expect("REASON");
-            if _action[0].method.is_none() {
-                debug!(
-                    "Ignored Transaction: {} No method Found",
-                    transaction.transaction_hash
-                );
-                return Ok(false);
-            }
-
-            // Extract the first action from the transaction
-            let action = match _action.get(0) {
-                Some(action) => action,
-                None => {
-                    &(JSONAction {
-                        action: "".to_string(),
-                        method: None,
-                        args: None,
-                    })
-                }
-            };
-
-            // Check if the action method is "mint_tweet_request"
-            if let Some(method) = &action.method {
-                // LIST OPERATIONS
-                if method == "mint_tweet_request" {
-                    // Parse the mint data from the action arguments
-                    let mint_data: Result<MintRequestData, SJError> = serde_json::from_str(
-                        action.args.clone().unwrap_or("".to_string()).as_str(),
-                    );
-                    if mint_data.is_err() {
-                        debug!(
-                            "mint_tweet_request: Could not parse data :{}",
-                            transaction.transaction_hash
-                        );
-                        return Ok(false);
-                    }
-                    let mint_data = mint_data.unwrap();
-
-                    // Fetch NFT data from the contract
-                    let fetched_nft = client
-                        .view::<Option<NftData>>(
-                            &nft_contract_id,
-                            Finality::Final,
-                            "nft_token",
-                            Some(json!({
-                                    "token_id": mint_data.tweet_id.clone(),
-                            })),
-                        )
-                        .await;
-                    if fetched_nft.is_err() {
-                        info!(
-                            "Failed to fetched nft data at :{}",
-                            transaction.transaction_hash
-                        );
-                        return Ok(false);
-                    }
-
-                    let fetched_nft = fetched_nft.unwrap().data();
-
-                    // Check if the NFT has already been minted
-                    if fetched_nft.is_some() {
-                        debug!("NFT already minted :{}", transaction.transaction_hash);
-                        return Ok(false);
-                    }
-                    debug!("fetched_nft: {:?}\nmint_data:{:?}", fetched_nft, &mint_data);
+    // Find the requests of the transaction in the database by hash, which is the same whichever
+    // transaction source recorded them
+    if near_transaction::Entity::find()
+        .filter(near_transaction::Column::TransactionHash.eq(&transaction.transaction_hash))
+        .one(db)
+        .await?
+        .is_some()
+    {
+        return Ok(true);
+    }
 
-                    // send verified journal to near for the mint transaction to be triggered
-                    let proof = verify_near_proof_v2(
-                        mint_data.tweet_id.clone(),
-                        mint_data.image_url.to_string(),
-                        transaction.signer_account_id.clone(),
-                    )
-                    .await;
-                    if proof.is_err() {
-                        info!(
-                            "Failed to mint {}\n {:?}\n",
-                            &mint_data.tweet_id,
-                            proof.err()
-                        );
-                        return Ok(false);
-                    }
-                    let (near_tx_response, tx_hash) = proof.expect("NEAR_VERIFICATION FAILED");
-                    debug!(
-                        "Near transaction has been verified with response: {:?}\n",
-                        near_tx_response
-                    );
+    let requests = events::mint_requests(events);
+    if requests.is_empty() {
+        debug!(
+            "Ignored Transaction: {} No mint request",
+            transaction.transaction_hash
+        );
+        return Ok(false);
+    }
 
-                    // Create a new transaction record
-                    let new_transaction = near_transaction::ActiveModel {
-                        id: Set(pk),
-                        transaction_hash: Set(transaction.transaction_hash.clone()),
-                        signer_account_id: Set(transaction.signer_account_id.clone()),
-                        receiver_account_id: Set(transaction.receiver_account_id.clone()),
-                        block_timestamp: Set(transaction.transaction_hash.clone()),
-                        block_height: Set(transaction.block.block_height.try_into().unwrap()),
-                        action: Set(action.action.clone()),
-                        method: Set(method.clone()),
-                        outcomes_status: Set(transaction.outcomes.status.unwrap_or(false)),
-                        tweet_id: Set(mint_data.tweet_id.clone().to_string()),
-                        image_url: Set(mint_data.image_url.clone()),
-                        user_to_notify: Set(Some(mint_data.notify.clone())),
-                        mint_transaction_hash: Set(Some(tx_hash.to_string())),
-                        ..Default::default() // all other attributes are `NotSet`
-                    };
-                    near_transaction::Entity::insert(new_transaction)
-                        .exec(db)
-                        .await?;
+    let action = transaction
+        .actions
+        .as_ref()
+        .and_then(|actions| actions.first())
+        .map(|action| action.action.clone())
+        .unwrap_or_default();
 
-                    // Notify the user on Twitter if specified
-                    if !mint_data.notify.is_empty() {
-                        let _ = notifier
-                            .notifier(&mint_data.tweet_id.clone(), &mint_data.notify)
-                            .await;
-                    }
-                    return Ok(false);
-                }
-            } else {
-                // Log ignored transactions for other methods
-                debug!(
-                    "Ignored Transaction: {} Method Called: {:?}",
-                    transaction.transaction_hash, action.method
-                );
-                return Ok(false);
-            }
-            Ok(false)
-        }
-    }
+    // Record the requests, each one is then proved, verified, minted and notified
+    // by the worker pool, which resumes it from its last completed stage
+    // if the indexer stops
+    let block_height: i32 = transaction
+        .block
+        .block_height
+        .try_into()
+        .map_err(|e| DbErr::Custom(format!("Invalid block height: {}", e)))?;
+    let new_transactions =
+        requests
+            .iter()
+            .enumerate()
+            .map(|(event_index, mint_data)| near_transaction::ActiveModel {
+                transaction_hash: Set(transaction.transaction_hash.clone()),
+                event_index: Set(event_index as i32),
+                signer_account_id: Set(transaction.signer_account_id.clone()),
+                receiver_account_id: Set(transaction.receiver_account_id.clone()),
+                block_timestamp: Set(transaction.block_timestamp.clone()),
+                block_height: Set(block_height),
+                action: Set(action.clone()),
+                method: Set("mint_tweet_request".to_string()),
+                outcomes_status: Set(transaction.outcomes.status.unwrap_or(false)),
+                tweet_id: Set(mint_data.tweet_id.clone()),
+                image_url: Set(mint_data.image_url.clone()),
+                user_to_notify: Set(Some(mint_data.notify.clone())),
+                minter_account_id: Set(Some(mint_data.account.clone())),
+                status: Set(MintStatus::Discovered.as_str().to_string()),
+                attempts: Set(0),
+                ..Default::default() // all other attributes are `NotSet`
+            });
+    // The requests of a transaction are recorded together, so none is left out on failure
+    near_transaction::Entity::insert_many(new_transactions)
+        .exec(db)
+        .await?;
+    debug!("mint_data:{:?}", &requests);
+    Ok(false)
 }
@@ -5,6 +5,7 @@ use near_jsonrpc_primitives::types::query::QueryResponseKind;
 use near_primitives::types::{BlockReference, Finality, FunctionArgs};
 use near_primitives::views::QueryRequest;
 
+use http_transcript::Response;
 use serde_json::from_slice;
 use serde_json::json;
 use std::env;
@@ -48,10 +49,10 @@ pub fn generate_tweet_nft_payload(
     response_http_string: String,
     meta_data: AssetMetadata,
 ) -> (TokenMetadata, String) {
-    let lines: Vec<&str> = response_http_string.split("\n").collect();
-
-    // the json string is the last line in the http payload
-    let json_tweet = lines.last().unwrap().to_owned();
+    // the json string is the body of the http response, which may have been sent in chunks
+    let response = Response::parse(&response_http_string).expect("invalid http response");
+    response.expect_status(200).expect("unsuccessful http response");
+    let json_tweet = response.json_body().expect("http response is not json");
 
     print!("data:{}", json_tweet);
 
@@ -28,8 +28,9 @@ fn main() {
 
     // Builds can be made deterministic, and thereby reproducible, by using Docker to build the
     // guest. Check the RISC0_USE_DOCKER variable and use Docker to build the guest if set.
+    // The build context includes the crates shared with the contracts and the indexer.
     let use_docker = env::var("RISC0_USE_DOCKER").ok().map(|_| DockerOptions {
-        root_dir: Some("../".into()),
+        root_dir: Some("../../".into()),
     });
 
     // Generate Rust source files for the methods crate.
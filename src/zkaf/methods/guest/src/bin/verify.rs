@@ -2,6 +2,7 @@ use sha256::digest;
 use std::io::Read;
 
 use alloy_sol_types::SolValue;
+use http_transcript::{Request, Response};
 use risc0_zkvm::guest::env;
 
 use near_contract_standards::non_fungible_token::metadata::TokenMetadata;
@@ -111,10 +112,12 @@ pub fn generate_tweet_nft_payload(
     response_http_string: String,
     meta_data: AssetMetadata,
 ) -> (TokenMetadata, String) {
-    let lines: Vec<&str> = response_http_string.split("\n").collect();
-
-    // the json string is the last line in the http payload
-    let json_tweet = lines.last().unwrap().to_owned();
+    // the json string is the body of the http response, which may have been sent in chunks
+    let response = Response::parse(&response_http_string).expect("invalid http response");
+    response
+        .expect_status(200)
+        .expect("unsuccessful http response");
+    let json_tweet = response.json_body().expect("http response is not json");
 
     print!("data:{}", json_tweet);
 
@@ -165,7 +168,19 @@ fn main() {
     let proof_params: String = String::from_utf8(input_bytes).unwrap();
     let proof_params: ZkInputParam = serde_json::from_str(proof_params.as_str()).unwrap();
 
-    let (response, _request) = verify_proof(&proof_params.proof).unwrap();
+    let (response, request) = verify_proof(&proof_params.proof).unwrap();
+
+    // only a lookup of the tweet being minted can be proven
+    let request = Request::parse(&request).expect("invalid http request");
+    let url = request
+        .check_url("api.x.com", "/2/tweets")
+        .expect("request is not a tweet lookup");
+    assert_eq!(
+        url.query_param("ids"),
+        Some(proof_params.meta_data.token_id.as_str()),
+        "request is not for the tweet being minted"
+    );
+
     let (_, string_metadata) = generate_tweet_nft_payload(response, proof_params.meta_data);
 
     env::log(&format!("Derived metadata: {}", string_metadata));